@@ -0,0 +1,432 @@
+//! Alarm time parsing.
+
+use std::num::ParseIntError;
+use std::str::FromStr;
+
+use rezz::tz::TimeZone;
+use time::error::ComponentRange;
+use time::format_description::well_known::Rfc3339;
+use time::{Date, Duration, Month, OffsetDateTime, PrimitiveDateTime, Time, Weekday};
+
+/// DateTime wrapper with `FromStr` implementation.
+///
+/// The following formats are supported:
+///  - RFC3339 with an explicit offset: `2025-06-30T07:00:00+02:00`
+///  - Offset relative to now: `+25m`, `in 8h`, `in 1h 30m`
+///  - Local time, with an optional day: `07:00`, `12:30pm`, `tomorrow 7am`,
+///    `mon 06:30`, `2025-06-30 07:00`, `2025-06-30T07:00:00.500`
+///  - Time in an IANA timezone: `09:00 in America/New_York`
+///
+/// Local times without a day will use the next occurrence of that time, while
+/// explicit dates and times today which have passed already are rejected.
+#[derive(Clone, Debug)]
+pub struct ClapDateTime(pub OffsetDateTime);
+
+impl ClapDateTime {
+    /// Parse an alarm time relative to `now`.
+    ///
+    /// Local times are resolved using the offset of `timezone` at the alarm
+    /// time, falling back to the offset of `now` without a timezone.
+    pub fn parse(
+        s: &str,
+        now: OffsetDateTime,
+        timezone: Option<&TimeZone>,
+    ) -> Result<Self, DateTimeError> {
+        let s = s.trim();
+
+        // Parse wall-clock time in an explicit timezone.
+        if let Some((time, timezone)) = s.rsplit_once(" in ") {
+            let timezone = TimeZone::load(timezone.trim())?;
+            return Self::parse(time, timezone.to_local(now), Some(&timezone));
+        }
+
+        // Parse absolute time with explicit offset.
+        if let Ok(time) = OffsetDateTime::parse(s, &Rfc3339) {
+            return Ok(Self(time));
+        }
+
+        // Parse time relative to now.
+        let lowercase = s.to_ascii_lowercase();
+        if let Some(duration) =
+            lowercase.strip_prefix('+').or_else(|| lowercase.strip_prefix("in "))
+        {
+            let time = now.checked_add(parse_duration(duration)?);
+            return time.map(Self).ok_or_else(|| DateTimeError::OutOfRange(s.into()));
+        }
+
+        let mut day = None;
+        let mut time: Option<String> = None;
+        for token in lowercase.split_whitespace() {
+            // Split combined date and time.
+            let (date, time_token) = match token.split_once('t') {
+                Some((date, time)) if date.contains('-') => (date, Some(time)),
+                _ => (token, None),
+            };
+
+            match (date, time_token) {
+                // Attach separate meridiem to the previous time.
+                ("am" | "pm", None) => match &mut time {
+                    Some(time) => time.push_str(token),
+                    None => return Err(DateTimeError::InvalidFormat(s.into())),
+                },
+                (date, time_token) if day.is_none() && Day::is_day(date) => {
+                    day = Some(Day::from_str(date)?);
+                    if let Some(time_token) = time_token {
+                        time = Some(time_token.into());
+                    }
+                },
+                (_, None) if time.is_none() => time = Some(token.into()),
+                _ => return Err(DateTimeError::InvalidFormat(s.into())),
+            }
+        }
+
+        let time = time.ok_or_else(|| DateTimeError::InvalidFormat(s.into()))?;
+        let time = parse_time(&time)?;
+
+        let date = match &day {
+            Some(day) => day.date(now, time),
+            // Add one day if time has already passed.
+            None if time < now.time() => now.date() + Duration::days(1),
+            None => now.date(),
+        };
+
+        // Resolve the offset at the alarm time, rather than the current offset.
+        let local = PrimitiveDateTime::new(date, time);
+        let time = match timezone {
+            Some(timezone) => timezone.resolve(local),
+            None => local.assume_offset(now.offset()),
+        };
+
+        // Reject explicit dates which have passed already, since they never ring.
+        if matches!(day, Some(Day::Date(_) | Day::Today)) && time < now {
+            return Err(DateTimeError::InPast(s.into()));
+        }
+
+        Ok(Self(time))
+    }
+}
+
+impl FromStr for ClapDateTime {
+    type Err = DateTimeError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        // Prefer the system's timezone, to match Rezz's floating alarms.
+        let timezone = TimeZone::local().ok();
+        let now = match &timezone {
+            Some(timezone) => timezone.to_local(OffsetDateTime::now_utc()),
+            None => OffsetDateTime::now_local().unwrap_or_else(|_| OffsetDateTime::now_utc()),
+        };
+
+        Self::parse(s, now, timezone.as_ref())
+    }
+}
+
+/// Day of an alarm.
+enum Day {
+    Today,
+    Tomorrow,
+    Weekday(Weekday),
+    Date(Date),
+}
+
+impl Day {
+    /// Check if a token describes a day.
+    fn is_day(token: &str) -> bool {
+        token.contains('-') || Self::from_str(token).is_ok()
+    }
+
+    /// Get the date of the next occurrence of this day.
+    fn date(&self, now: OffsetDateTime, time: Time) -> Date {
+        match self {
+            Self::Today => now.date(),
+            Self::Tomorrow => now.date() + Duration::days(1),
+            Self::Weekday(weekday) => {
+                let target = weekday.number_days_from_monday() as i64;
+                let current = now.weekday().number_days_from_monday() as i64;
+                let mut days = (target - current).rem_euclid(7);
+
+                // Use next week if the time has already passed today.
+                if days == 0 && time < now.time() {
+                    days = 7;
+                }
+
+                now.date() + Duration::days(days)
+            },
+            Self::Date(date) => *date,
+        }
+    }
+}
+
+impl FromStr for Day {
+    type Err = DateTimeError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let weekday = match s {
+            "today" => return Ok(Self::Today),
+            "tomorrow" => return Ok(Self::Tomorrow),
+            "mon" | "monday" => Weekday::Monday,
+            "tue" | "tuesday" => Weekday::Tuesday,
+            "wed" | "wednesday" => Weekday::Wednesday,
+            "thu" | "thursday" => Weekday::Thursday,
+            "fri" | "friday" => Weekday::Friday,
+            "sat" | "saturday" => Weekday::Saturday,
+            "sun" | "sunday" => Weekday::Sunday,
+            date => return Ok(Self::Date(parse_date(date)?)),
+        };
+        Ok(Self::Weekday(weekday))
+    }
+}
+
+/// Weekday wrapper with `FromStr` implementation, like `mon` or `monday`.
+#[derive(Copy, Clone, Debug)]
+pub struct ClapWeekday(pub Weekday);
+
+impl FromStr for ClapWeekday {
+    type Err = DateTimeError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match Day::from_str(s) {
+            Ok(Day::Weekday(weekday)) => Ok(Self(weekday)),
+            _ => Err(DateTimeError::InvalidFormat(s.into())),
+        }
+    }
+}
+
+/// Date wrapper with `FromStr` implementation, like `2025-12-24`.
+#[derive(Copy, Clone, Debug)]
+pub struct ClapDate(pub Date);
+
+impl FromStr for ClapDate {
+    type Err = DateTimeError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        parse_date(s).map(Self)
+    }
+}
+
+/// Parse a `YYYY-MM-DD` date.
+fn parse_date(date: &str) -> Result<Date, DateTimeError> {
+    let mut components = date.splitn(3, '-');
+    let mut next_component =
+        || components.next().ok_or_else(|| DateTimeError::InvalidFormat(date.into()));
+
+    let year = i32::from_str(next_component()?)?;
+    let month = Month::try_from(u8::from_str(next_component()?)?)?;
+    let day = u8::from_str(next_component()?)?;
+
+    Ok(Date::from_calendar_date(year, month, day)?)
+}
+
+/// Parse a `HH[:MM[:SS[.fff]]][am|pm]` time.
+///
+/// Minutes may only be omitted for 12-hour times.
+fn parse_time(time: &str) -> Result<Time, DateTimeError> {
+    // Split 12-hour clock suffix.
+    let (time, pm) = match time.strip_suffix("am") {
+        Some(time) => (time, Some(false)),
+        None => match time.strip_suffix("pm") {
+            Some(time) => (time, Some(true)),
+            None => (time, None),
+        },
+    };
+
+    let mut components = time.splitn(3, ':');
+    let hour = components.next().unwrap_or_default();
+    let minute = components.next();
+    let second = components.next();
+
+    // Split fractional seconds.
+    let (second, fraction) = match second.and_then(|second| second.split_once('.')) {
+        Some((second, fraction)) => (Some(second), Some(fraction)),
+        None => (second, None),
+    };
+
+    let mut hour = u8::from_str(hour)?;
+    let minute = match minute {
+        Some(minute) => u8::from_str(minute)?,
+        None if pm.is_some() => 0,
+        None => return Err(DateTimeError::InvalidFormat(time.into())),
+    };
+    let second = second.map_or(Ok(0), u8::from_str)?;
+    let millisecond = fraction.map_or(Ok(0), parse_millis)?;
+
+    // Convert 12-hour clock to 24-hour clock.
+    if let Some(pm) = pm {
+        if !(1..=12).contains(&hour) {
+            return Err(DateTimeError::InvalidFormat(time.into()));
+        }
+        hour = (hour % 12) + if pm { 12 } else { 0 };
+    }
+
+    Ok(Time::from_hms_milli(hour, minute, second, millisecond)?)
+}
+
+/// Parse fractional seconds into milliseconds.
+///
+/// Any digits beyond millisecond precision are ignored.
+fn parse_millis(fraction: &str) -> Result<u16, DateTimeError> {
+    if fraction.is_empty() || !fraction.bytes().all(|byte| byte.is_ascii_digit()) {
+        return Err(DateTimeError::InvalidFormat(fraction.into()));
+    }
+
+    // Pad or truncate to exactly three digits.
+    let millis: String = fraction.chars().chain("00".chars()).take(3).collect();
+
+    Ok(u16::from_str(&millis)?)
+}
+
+/// Parse a relative duration like `1h 30m` or `2 hours`.
+pub fn parse_duration(duration: &str) -> Result<Duration, DateTimeError> {
+    let invalid = || DateTimeError::InvalidFormat(duration.into());
+    let out_of_range = || DateTimeError::OutOfRange(duration.into());
+
+    let mut total = Duration::ZERO;
+    let mut chars = duration.trim().chars().peekable();
+    while chars.peek().is_some() {
+        // Parse the amount.
+        let mut amount = String::new();
+        while let Some(c) = chars.next_if(|c| c.is_ascii_digit()) {
+            amount.push(c);
+        }
+        let amount = i64::from_str(&amount).map_err(|_| invalid())?;
+
+        // Parse the unit, allowing whitespace before it.
+        while chars.next_if(|c| c.is_whitespace()).is_some() {}
+        let mut unit = String::new();
+        while let Some(c) = chars.next_if(|c| c.is_ascii_alphabetic()) {
+            unit.push(c);
+        }
+        while chars.next_if(|c| c.is_whitespace()).is_some() {}
+
+        let unit_seconds = match unit.as_str() {
+            "s" | "sec" | "secs" | "second" | "seconds" => 1,
+            "m" | "min" | "mins" | "minute" | "minutes" => 60,
+            "h" | "hr" | "hrs" | "hour" | "hours" => 60 * 60,
+            "d" | "day" | "days" => 24 * 60 * 60,
+            _ => return Err(invalid()),
+        };
+
+        let seconds = amount.checked_mul(unit_seconds).ok_or_else(out_of_range)?;
+        total = total.checked_add(Duration::seconds(seconds)).ok_or_else(out_of_range)?;
+    }
+
+    if total.is_zero() {
+        return Err(invalid());
+    }
+
+    Ok(total)
+}
+
+#[derive(thiserror::Error, Debug)]
+pub enum DateTimeError {
+    #[error("invalid format: {0}")]
+    ComponentRange(#[from] ComponentRange),
+    #[error("invalid format: {0}")]
+    InvalidFormat(String),
+    #[error("time is in the past: {0}")]
+    InPast(String),
+    #[error("time out of range: {0}")]
+    OutOfRange(String),
+    #[error("{0}")]
+    ParseInt(#[from] ParseIntError),
+    #[error("{0}")]
+    TimeZone(#[from] rezz::Error),
+}
+
+#[cfg(test)]
+mod tests {
+    use time::UtcOffset;
+
+    use super::*;
+
+    /// Create a time at UTC+2.
+    fn at(year: i32, month: Month, day: u8, hour: u8, minute: u8) -> OffsetDateTime {
+        let date = Date::from_calendar_date(year, month, day).unwrap();
+        let offset = UtcOffset::from_hms(2, 0, 0).unwrap();
+        date.with_hms(hour, minute, 0).unwrap().assume_offset(offset)
+    }
+
+    /// Parse a time relative to Monday, June 30th 2025 10:00 at UTC+2.
+    fn parse(s: &str) -> Result<OffsetDateTime, DateTimeError> {
+        let now = at(2025, Month::June, 30, 10, 0);
+        ClapDateTime::parse(s, now, None).map(|ClapDateTime(time)| time)
+    }
+
+    #[test]
+    fn relative() {
+        let now = at(2025, Month::June, 30, 10, 0);
+        assert_eq!(parse("+25m").unwrap(), now + Duration::minutes(25));
+        assert_eq!(parse("in 8h").unwrap(), now + Duration::hours(8));
+        assert_eq!(parse("In 1h 30m").unwrap(), now + Duration::minutes(90));
+        assert!(matches!(parse("+0m"), Err(DateTimeError::InvalidFormat(_))));
+    }
+
+    #[test]
+    fn time_of_day() {
+        assert_eq!(parse("10:30").unwrap(), at(2025, Month::June, 30, 10, 30));
+        assert_eq!(parse("12:30pm").unwrap(), at(2025, Month::June, 30, 12, 30));
+        assert_eq!(parse("12:30 am").unwrap(), at(2025, Month::July, 1, 0, 30));
+        assert!(matches!(parse("13pm"), Err(DateTimeError::InvalidFormat(_))));
+        assert!(parse("24:00").is_err());
+    }
+
+    #[test]
+    fn time_of_day_rollover() {
+        // Times which have passed already today ring tomorrow.
+        assert_eq!(parse("09:30").unwrap(), at(2025, Month::July, 1, 9, 30));
+        assert_eq!(parse("7am").unwrap(), at(2025, Month::July, 1, 7, 0));
+    }
+
+    #[test]
+    fn day_and_time() {
+        assert_eq!(parse("tomorrow 07:00").unwrap(), at(2025, Month::July, 1, 7, 0));
+        assert_eq!(parse("wed 7am").unwrap(), at(2025, Month::July, 2, 7, 0));
+
+        // Weekdays which have passed already today ring next week.
+        assert_eq!(parse("mon 06:30").unwrap(), at(2025, Month::July, 7, 6, 30));
+        assert_eq!(parse("monday 11:00").unwrap(), at(2025, Month::June, 30, 11, 0));
+    }
+
+    #[test]
+    fn date_and_time() {
+        assert_eq!(parse("2025-07-01 07:00").unwrap(), at(2025, Month::July, 1, 7, 0));
+
+        let millis = at(2025, Month::July, 1, 7, 0) + Duration::milliseconds(500);
+        assert_eq!(parse("2025-07-01T07:00:00.500").unwrap(), millis);
+    }
+
+    #[test]
+    fn date_in_past() {
+        let result = parse("2025-06-29 07:00");
+        assert!(matches!(result, Err(DateTimeError::InPast(_))));
+        assert!(matches!(parse("2025-06-30T09:59"), Err(DateTimeError::InPast(_))));
+        assert!(matches!(parse("today 09:30"), Err(DateTimeError::InPast(_))));
+        assert_eq!(parse("today 10:30").unwrap(), at(2025, Month::June, 30, 10, 30));
+    }
+
+    #[test]
+    fn rfc3339() {
+        let expected = at(2025, Month::July, 1, 7, 0);
+
+        let time = parse("2025-07-01T07:00:00+02:00").unwrap();
+        assert_eq!(time, expected);
+        assert_eq!(time.offset(), expected.offset());
+
+        let time = parse("2025-07-01T05:00:00Z").unwrap();
+        assert_eq!(time, expected);
+        assert_eq!(time.offset(), UtcOffset::UTC);
+    }
+
+    #[test]
+    fn timezone_offset_at_alarm_time() {
+        // Berlin switches from UTC+1 to UTC+2 on March 30th, 2025.
+        let timezone = TimeZone::load("Europe/Berlin").unwrap();
+        let now = Date::from_calendar_date(2025, Month::March, 29).unwrap().midnight().assume_utc();
+        let now = timezone.to_local(now);
+
+        let ClapDateTime(time) =
+            ClapDateTime::parse("tomorrow 07:00", now, Some(&timezone)).unwrap();
+        let expected = Date::from_calendar_date(2025, Month::March, 30).unwrap();
+        assert_eq!(time, expected.with_hms(5, 0, 0).unwrap().assume_utc());
+    }
+}
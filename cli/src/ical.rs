@@ -0,0 +1,1337 @@
+//! iCalendar import and export.
+//!
+//! Alarms are represented as `VEVENT`s with a `VALARM` triggering at the start
+//! of the event. Times with a `TZID` parameter are resolved using the system's
+//! timezone database, falling back to local time for unknown timezones.
+//!
+//! Floating alarms use floating times without `Z` suffix or `TZID`, which keep
+//! their wall-clock time in the local timezone.
+//!
+//! Repeating alarms are exported as weekly `RRULE`s with their exceptions as
+//! `EXDATE`s. Recurring events are imported as repeating alarms if they recur
+//! every day or week on the local wall-clock time without `COUNT` or `UNTIL`.
+//! Other recurrences cannot be stored by rezz, so only their next occurrence
+//! is imported.
+
+use std::fmt::Write;
+use std::num::ParseIntError;
+use std::str::FromStr;
+use std::{iter, mem};
+
+use rezz::tz::TimeZone;
+use rezz::{Alarm, AlarmKind};
+use time::error::ComponentRange;
+use time::{Date, Duration, Month, OffsetDateTime, PrimitiveDateTime, Time, UtcOffset, Weekday};
+
+/// Product identifier for exported calendars.
+const PRODID: &str = "-//catacombing//alarm//EN";
+
+/// Custom property storing the alarm's ring duration.
+const RING_SECONDS_PROPERTY: &str = "X-REZZ-RING-SECONDS";
+
+/// Custom property storing whether the alarm is enabled.
+const ENABLED_PROPERTY: &str = "X-REZZ-ENABLED";
+
+/// Custom property storing the milliseconds of the alarm time.
+const MILLIS_PROPERTY: &str = "X-REZZ-UNIX-MILLIS";
+
+/// Maximum number of recurrences evaluated for a single event.
+const MAX_RECURRENCES: usize = 100_000;
+
+/// Time after which occurrences of recurring holidays are ignored.
+const HOLIDAY_HORIZON: Duration = Duration::days(10 * 366);
+
+/// Convert alarms to an iCalendar file.
+pub fn export(alarms: &[Alarm]) -> String {
+    let mut ics = String::new();
+    let _ = write!(ics, "BEGIN:VCALENDAR\r\nVERSION:2.0\r\nPRODID:{PRODID}\r\n");
+
+    let now = format_utc(OffsetDateTime::now_utc());
+    let zone = TimeZone::local().ok();
+    for alarm in alarms {
+        let time = OffsetDateTime::UNIX_EPOCH + Duration::milliseconds(alarm.unix_time_ms());
+
+        // Export reminders as display alarms, which require a description.
+        let action = match alarm.kind {
+            AlarmKind::WakeUp => String::from("AUDIO"),
+            AlarmKind::Reminder => format!("DISPLAY\r\nDESCRIPTION:{}", escape_text(&alarm.id)),
+        };
+
+        // Repeating alarms recur on the wall-clock time of the local timezone.
+        let wall_time = zone
+            .as_ref()
+            .filter(|_| alarm.repeats())
+            .and_then(|zone| Some((zone, alarm.wall_time(zone)?)));
+        let start = match (alarm.local_time(), wall_time) {
+            (Some(local_time), _) => format!(":{}", format_date_time(local_time)),
+            (None, Some((zone, wall_time))) => {
+                format!(";TZID={}:{}", zone.name(), format_date_time(wall_time))
+            },
+            (None, None) => format!(":{}", format_utc(time)),
+        };
+        let enabled = if alarm.enabled { "TRUE" } else { "FALSE" };
+
+        #[rustfmt::skip]
+        let _ = write!(
+            ics,
+            "BEGIN:VEVENT\r\nUID:{}\r\nDTSTAMP:{now}\r\nDTSTART{start}\r\nDURATION:PT{}S\r\n\
+             SUMMARY:{}\r\n{RING_SECONDS_PROPERTY}:{}\r\n{ENABLED_PROPERTY}:{enabled}\r\n\
+             {MILLIS_PROPERTY}:{}\r\n",
+            escape_text(&alarm.id),
+            alarm.ring_seconds,
+            escape_text(&alarm.id),
+            alarm.ring_seconds,
+            alarm.unix_millis,
+        );
+
+        if alarm.repeats() {
+            let weekdays: Vec<_> = iter::successors(Some(Weekday::Monday), |day| Some(day.next()))
+                .take(7)
+                .filter(|weekday| alarm.repeats_on(*weekday))
+                .map(format_weekday)
+                .collect();
+            let _ = write!(ics, "RRULE:FREQ=WEEKLY;BYDAY={}\r\n", weekdays.join(","));
+
+            let exdates: Vec<_> = alarm
+                .exceptions
+                .iter()
+                .filter_map(|day| Date::from_julian_day(*day).ok())
+                .map(format_date)
+                .collect();
+            if !exdates.is_empty() {
+                let _ = write!(ics, "EXDATE;VALUE=DATE:{}\r\n", exdates.join(","));
+            }
+        }
+
+        #[rustfmt::skip]
+        let _ = write!(
+            ics,
+            "BEGIN:VALARM\r\nACTION:{action}\r\nTRIGGER;RELATED=START:PT0S\r\nEND:VALARM\r\n\
+             END:VEVENT\r\n",
+        );
+    }
+
+    ics.push_str("END:VCALENDAR\r\n");
+    ics
+}
+
+/// Occurrences excluded when importing recurring events.
+#[derive(Default)]
+pub struct Exceptions {
+    /// Local dates without recurring alarms, like public holidays.
+    pub holidays: Vec<Date>,
+    /// Skip the next occurrence of every recurring event.
+    pub skip_next: bool,
+}
+
+/// Alarms imported from an iCalendar file.
+#[derive(Default)]
+pub struct Import {
+    pub alarms: Vec<Alarm>,
+    /// IDs of alarms for recurring events which will only ring once.
+    pub one_shot: Vec<String>,
+}
+
+/// Convert an iCalendar file to alarms.
+///
+/// Every `VALARM` of a `VEVENT` is converted to one alarm at its next trigger
+/// time. Alarms which will not trigger in the future are ignored. `DISPLAY`
+/// alarms are imported as reminders, all other actions as wake-up alarms.
+/// Alarms relative to a floating event start are imported as floating alarms.
+///
+/// Occurrences of recurring events are skipped if they are excluded by the
+/// event's `EXDATE` or by the `exceptions`. Alarms for recurring events repeat
+/// if rezz supports their recurrence, with all excluded dates stored as their
+/// exceptions. Other recurring events only ring at their next occurrence and
+/// are reported in [`Import::one_shot`].
+///
+/// Alarm IDs are derived from the event's UID, so importing the same event
+/// multiple times will always produce the same IDs.
+pub fn import(
+    ics: &str,
+    default_ring_seconds: u32,
+    exceptions: &Exceptions,
+) -> Result<Import, IcalError> {
+    import_at(ics, default_ring_seconds, exceptions, OffsetDateTime::now_utc())
+}
+
+/// Convert an iCalendar file to alarms triggering after `now`.
+fn import_at(
+    ics: &str,
+    default_ring_seconds: u32,
+    exceptions: &Exceptions,
+    now: OffsetDateTime,
+) -> Result<Import, IcalError> {
+    let calendar = Component::parse(ics)?;
+
+    let mut import = Import::default();
+    for event in calendar.children.iter().filter(|child| child.name == "VEVENT") {
+        let uid = event.property("UID").ok_or(IcalError::MissingProperty("UID"))?;
+        let start = event.property("DTSTART").ok_or(IcalError::MissingProperty("DTSTART"))?;
+        let zone = time_zone(start);
+        let floating = is_floating(start);
+        let start = parse_date_time(start)?;
+
+        // Get the event's end for triggers relative to it.
+        let end = match (event.property("DTEND"), event.property("DURATION")) {
+            (Some(end), _) => parse_date_time(end)?,
+            (None, Some(duration)) => start + parse_duration(&duration.value)?,
+            (None, None) => start,
+        };
+
+        let ring_seconds = match event.property(RING_SECONDS_PROPERTY) {
+            Some(ring_seconds) => u32::from_str(&ring_seconds.value)?,
+            None => default_ring_seconds,
+        };
+
+        let enabled = match event.property(ENABLED_PROPERTY) {
+            Some(enabled) => parse_boolean(&enabled.value)?,
+            None => true,
+        };
+
+        let unix_millis = match event.property(MILLIS_PROPERTY) {
+            Some(millis) => parse_millis(&millis.value)?,
+            None => 0,
+        };
+
+        let recurrence = Recurrence::from_event(event)?;
+
+        let valarms = event.children.iter().filter(|child| child.name == "VALARM");
+        for (i, valarm) in valarms.enumerate() {
+            let trigger =
+                valarm.property("TRIGGER").ok_or(IcalError::MissingProperty("TRIGGER"))?;
+
+            // Absolute triggers are always in UTC, so only relative ones can float.
+            let mut repeat = None;
+            let (trigger_time, floating) = match Trigger::parse(trigger)? {
+                Trigger::Absolute(time) => (Some(time).filter(|time| *time > now), false),
+                Trigger::Relative { offset, related_end } => {
+                    let reference = if related_end { end } else { start };
+                    let trigger_time = match &recurrence {
+                        Some(recurrence) => {
+                            let zone = zone.as_ref();
+                            repeat = recurrence.repeat(reference, zone, offset, now, exceptions);
+                            recurrence.next_after(reference, zone, offset, now, exceptions)
+                        },
+                        None => Some(reference + offset).filter(|time| *time > now),
+                    };
+                    (trigger_time, floating)
+                },
+            };
+
+            let Some(trigger_time) = trigger_time else { continue };
+            let trigger_time = trigger_time + Duration::milliseconds(unix_millis as i64);
+
+            // Derive a stable ID from the event's UID.
+            let id = if i == 0 { uid.value.clone() } else { format!("{}#{i}", uid.value) };
+
+            let unix_time_ms = (trigger_time - OffsetDateTime::UNIX_EPOCH).whole_milliseconds();
+            let mut alarm = Alarm::from_unix_time_ms(id, unix_time_ms as i64, ring_seconds);
+            if valarm.property("ACTION").is_some_and(|action| action.value == "DISPLAY") {
+                alarm.kind = AlarmKind::Reminder;
+            }
+            if floating {
+                let local_time = wall_time(trigger_time, zone.as_ref());
+                alarm.local_time = local_time.assume_utc().unix_timestamp();
+                alarm.floating = true;
+            }
+            alarm.enabled = enabled;
+
+            match repeat {
+                Some(repeat) => {
+                    alarm.set_repeat_days(&repeat.weekdays);
+                    alarm.set_exceptions(repeat.exceptions);
+                },
+                None if recurrence.is_some() => import.one_shot.push(alarm.id.clone()),
+                None => (),
+            }
+
+            import.alarms.push(alarm);
+        }
+    }
+
+    Ok(import)
+}
+
+/// Get all local dates covered by the events of an iCalendar file.
+///
+/// Recurring events cover the dates of all their occurrences, up to ten years
+/// into the future.
+pub fn holidays(ics: &str) -> Result<Vec<Date>, IcalError> {
+    let calendar = Component::parse(ics)?;
+    let horizon = OffsetDateTime::now_utc() + HOLIDAY_HORIZON;
+
+    let mut dates = Vec::new();
+    for event in calendar.children.iter().filter(|child| child.name == "VEVENT") {
+        let start = event.property("DTSTART").ok_or(IcalError::MissingProperty("DTSTART"))?;
+        let all_day = start.param("VALUE") == Some("DATE");
+        let zone = time_zone(start);
+        let start = parse_date_time(start)?;
+
+        // All-day events without an end cover their start date.
+        let end = match (event.property("DTEND"), event.property("DURATION")) {
+            (Some(end), _) => parse_date_time(end)?,
+            (None, Some(duration)) => start + parse_duration(&duration.value)?,
+            (None, None) if all_day => start + Duration::DAY,
+            (None, None) => start,
+        };
+
+        let occurrences = match Recurrence::from_event(event)? {
+            Some(recurrence) => recurrence
+                .instances(start, zone.as_ref())
+                .take_while(|occurrence| *occurrence <= horizon)
+                .collect(),
+            None => vec![start],
+        };
+
+        // Add every date overlapping with any occurrence.
+        for occurrence in occurrences {
+            let end = occurrence + (end - start);
+
+            dates.push(occurrence.date());
+            let mut date = occurrence.date();
+            for _ in 0..MAX_RECURRENCES {
+                date = match date.next_day() {
+                    Some(date) => date,
+                    None => break,
+                };
+
+                if date.midnight().assume_offset(occurrence.offset()) >= end {
+                    break;
+                }
+
+                dates.push(date);
+            }
+        }
+    }
+
+    Ok(dates)
+}
+
+/// iCalendar component.
+#[derive(Default, Debug)]
+struct Component {
+    name: String,
+    properties: Vec<Property>,
+    children: Vec<Component>,
+}
+
+impl Component {
+    /// Parse the first component of an iCalendar file.
+    fn parse(ics: &str) -> Result<Self, IcalError> {
+        let mut stack: Vec<Component> = Vec::new();
+
+        for line in unfold(ics) {
+            if line.is_empty() {
+                continue;
+            }
+
+            let property = Property::parse(&line)?;
+            match property.name.as_str() {
+                "BEGIN" => stack.push(Component { name: property.value, ..Default::default() }),
+                "END" => {
+                    let component = stack.pop().ok_or(IcalError::InvalidLine(line.clone()))?;
+                    if component.name != property.value {
+                        return Err(IcalError::InvalidLine(line));
+                    }
+
+                    match stack.last_mut() {
+                        Some(parent) => parent.children.push(component),
+                        None => return Ok(component),
+                    }
+                },
+                _ => match stack.last_mut() {
+                    Some(component) => component.properties.push(property),
+                    None => return Err(IcalError::InvalidLine(line)),
+                },
+            }
+        }
+
+        Err(IcalError::UnexpectedEof)
+    }
+
+    /// Get the first property with the specified name.
+    fn property(&self, name: &str) -> Option<&Property> {
+        self.properties.iter().find(|property| property.name == name)
+    }
+
+    /// Get all properties with the specified name.
+    fn properties<'a>(&'a self, name: &'a str) -> impl Iterator<Item = &'a Property> {
+        self.properties.iter().filter(move |property| property.name == name)
+    }
+}
+
+/// iCalendar content line.
+#[derive(Debug)]
+struct Property {
+    name: String,
+    params: Vec<(String, String)>,
+    value: String,
+}
+
+impl Property {
+    /// Parse a single unfolded content line.
+    fn parse(line: &str) -> Result<Self, IcalError> {
+        // Find the value separator, ignoring colons in quoted parameters.
+        let mut quoted = false;
+        let separator = line.char_indices().find(|(_, c)| match c {
+            '"' => {
+                quoted = !quoted;
+                false
+            },
+            ':' => !quoted,
+            _ => false,
+        });
+        let (head, value) = match separator {
+            Some((index, _)) => (&line[..index], &line[index + 1..]),
+            None => return Err(IcalError::InvalidLine(line.into())),
+        };
+
+        let mut components = head.split(';');
+        let name = components.next().unwrap_or_default().to_ascii_uppercase();
+        let params = components
+            .filter_map(|param| param.split_once('='))
+            .map(|(key, value)| (key.to_ascii_uppercase(), value.trim_matches('"').into()))
+            .collect();
+
+        Ok(Self { name, params, value: unescape_text(value) })
+    }
+
+    /// Get the value of a parameter.
+    fn param(&self, name: &str) -> Option<&str> {
+        self.params.iter().find(|(key, _)| key == name).map(|(_, value)| value.as_str())
+    }
+}
+
+/// `VALARM` trigger.
+enum Trigger {
+    Absolute(OffsetDateTime),
+    Relative { offset: Duration, related_end: bool },
+}
+
+impl Trigger {
+    fn parse(property: &Property) -> Result<Self, IcalError> {
+        if property.param("VALUE") == Some("DATE-TIME") {
+            return Ok(Self::Absolute(parse_date_time(property)?));
+        }
+
+        let offset = parse_duration(&property.value)?;
+        let related_end = property.param("RELATED") == Some("END");
+        Ok(Self::Relative { offset, related_end })
+    }
+}
+
+/// Recurrence frequency.
+#[derive(Copy, Clone, PartialEq, Eq)]
+enum Frequency {
+    Daily,
+    Weekly,
+    Yearly,
+}
+
+/// Weekly repetition of an alarm.
+struct Repeat {
+    weekdays: Vec<Weekday>,
+    exceptions: Vec<Date>,
+}
+
+/// Event recurrence rule.
+///
+/// Only daily, weekly and yearly recurrences are supported.
+struct Recurrence {
+    frequency: Frequency,
+    interval: u16,
+    weekdays: Vec<Weekday>,
+    months: Vec<Month>,
+    month_days: Vec<u8>,
+    count: Option<usize>,
+    until: Option<OffsetDateTime>,
+    exdates: Vec<ExDate>,
+}
+
+impl Recurrence {
+    /// Get an event's recurrence rule, including its excluded instances.
+    fn from_event(event: &Component) -> Result<Option<Self>, IcalError> {
+        let mut recurrence = match event.property("RRULE") {
+            Some(rrule) => Self::parse(&rrule.value)?,
+            None => return Ok(None),
+        };
+
+        for exdate in event.properties("EXDATE") {
+            recurrence.exdates.extend(ExDate::parse(exdate)?);
+        }
+
+        Ok(Some(recurrence))
+    }
+
+    fn parse(rrule: &str) -> Result<Self, IcalError> {
+        let unsupported = || IcalError::UnsupportedRecurrence(rrule.into());
+
+        let mut recurrence = Self {
+            frequency: Frequency::Daily,
+            interval: 1,
+            weekdays: Vec::new(),
+            months: Vec::new(),
+            month_days: Vec::new(),
+            count: None,
+            until: None,
+            exdates: Vec::new(),
+        };
+
+        for part in rrule.split(';') {
+            let (key, value) = part.split_once('=').ok_or(IcalError::InvalidLine(rrule.into()))?;
+            match key {
+                "FREQ" => {
+                    recurrence.frequency = match value {
+                        "DAILY" => Frequency::Daily,
+                        "WEEKLY" => Frequency::Weekly,
+                        "YEARLY" => Frequency::Yearly,
+                        _ => return Err(unsupported()),
+                    }
+                },
+                "INTERVAL" => recurrence.interval = u16::from_str(value)?.max(1),
+                "COUNT" => recurrence.count = Some(usize::from_str(value)?),
+                "UNTIL" => {
+                    let until =
+                        Property { name: key.into(), params: Vec::new(), value: value.into() };
+                    recurrence.until = Some(parse_date_time(&until)?);
+                },
+                "BYDAY" => {
+                    for day in value.split(',') {
+                        recurrence.weekdays.push(parse_weekday(day)?);
+                    }
+                },
+                "BYMONTH" => {
+                    for month in value.split(',') {
+                        recurrence.months.push(Month::try_from(u8::from_str(month)?)?);
+                    }
+                },
+                "BYMONTHDAY" => {
+                    for day in value.split(',') {
+                        match u8::from_str(day) {
+                            Ok(day @ 1..=31) => recurrence.month_days.push(day),
+                            _ => return Err(unsupported()),
+                        }
+                    }
+                },
+                "WKST" => (),
+                _ => return Err(unsupported()),
+            }
+        }
+
+        // Weekday filters are only supported for weekly recurrences, and month
+        // filters only for yearly recurrences.
+        let yearly = recurrence.frequency == Frequency::Yearly;
+        let weekly = recurrence.frequency == Frequency::Weekly;
+        if (!weekly && !recurrence.weekdays.is_empty())
+            || (!yearly && (!recurrence.months.is_empty() || !recurrence.month_days.is_empty()))
+        {
+            return Err(unsupported());
+        }
+
+        Ok(recurrence)
+    }
+
+    /// Get the first trigger time after `now`.
+    fn next_after(
+        &self,
+        start: OffsetDateTime,
+        zone: Option<&TimeZone>,
+        offset: Duration,
+        now: OffsetDateTime,
+        exceptions: &Exceptions,
+    ) -> Option<OffsetDateTime> {
+        let mut skip_next = exceptions.skip_next;
+        self.instances(start, zone)
+            .filter(|occurrence| !exceptions.holidays.contains(&occurrence.date()))
+            .map(|occurrence| occurrence + offset)
+            .filter(|trigger| *trigger > now)
+            .find(|_| !mem::take(&mut skip_next))
+    }
+
+    /// Get the alarm repetition equivalent to this recurrence.
+    ///
+    /// Returns `None` if the recurrence cannot be represented by repeating
+    /// alarms, which ring every week on the local wall-clock time.
+    fn repeat(
+        &self,
+        start: OffsetDateTime,
+        zone: Option<&TimeZone>,
+        offset: Duration,
+        now: OffsetDateTime,
+        exceptions: &Exceptions,
+    ) -> Option<Repeat> {
+        if self.interval != 1 || self.count.is_some() || self.until.is_some() {
+            return None;
+        }
+
+        // Rezz repeats alarms in the local timezone, on the occurrence's date.
+        let local_zone = TimeZone::local().ok();
+        let wall_start = wall_time(start, zone);
+        if zone.map(TimeZone::name) != local_zone.as_ref().map(TimeZone::name)
+            || wall_time(start + offset, zone).date() != wall_start.date()
+        {
+            return None;
+        }
+
+        let weekdays = match self.frequency {
+            Frequency::Daily => {
+                iter::successors(Some(Weekday::Monday), |day| Some(day.next())).take(7).collect()
+            },
+            Frequency::Weekly if self.weekdays.is_empty() => vec![wall_start.weekday()],
+            Frequency::Weekly => self.weekdays.clone(),
+            Frequency::Yearly => return None,
+        };
+
+        let mut dates: Vec<_> = self
+            .exdates
+            .iter()
+            .map(|exdate| match exdate {
+                ExDate::Date(date) => *date,
+                ExDate::DateTime(time) => wall_time(*time, zone).date(),
+            })
+            .chain(exceptions.holidays.iter().copied())
+            .collect();
+
+        // Exclude the skipped occurrence's date.
+        if exceptions.skip_next {
+            let holidays = Exceptions { holidays: exceptions.holidays.clone(), skip_next: false };
+            if let Some(skipped) = self.next_after(start, zone, offset, now, &holidays) {
+                dates.push(wall_time(skipped, zone).date());
+            }
+        }
+
+        // Ignore exceptions which cannot affect any future occurrence.
+        let today = wall_time(now, zone).date();
+        let yesterday = today.previous_day().unwrap_or(today);
+        dates.retain(|date| *date >= yesterday);
+
+        Some(Repeat { weekdays, exceptions: dates })
+    }
+
+    /// Get all occurrences of an event in chronological order.
+    ///
+    /// Recurrences are calculated on the wall-clock time of the event's
+    /// timezone, to keep the event time stable across daylight saving time
+    /// changes. Occurrences excluded by `EXDATE` are skipped, but still count
+    /// towards the recurrence limit.
+    fn instances<'a>(
+        &'a self,
+        start: OffsetDateTime,
+        zone: Option<&'a TimeZone>,
+    ) -> impl Iterator<Item = OffsetDateTime> + 'a {
+        let wall_start = wall_time(start, zone);
+
+        (0..MAX_RECURRENCES as i64)
+            .map_while(move |period| self.period(wall_start, period))
+            .flatten()
+            .take(self.count.unwrap_or(usize::MAX))
+            .map(move |occurrence| resolve_wall_time(occurrence, zone))
+            .take_while(|occurrence| self.until.is_none_or(|until| *occurrence <= until))
+            .filter(|occurrence| !self.exdates.iter().any(|exdate| exdate.matches(*occurrence)))
+    }
+
+    /// Get the wall-clock occurrences within one recurrence period.
+    ///
+    /// Returns `None` once the period is outside of the supported date range.
+    fn period(&self, wall_start: PrimitiveDateTime, period: i64) -> Option<Vec<PrimitiveDateTime>> {
+        let interval = self.interval as i64 * period;
+
+        let mut occurrences = match self.frequency {
+            Frequency::Daily => vec![wall_start.checked_add(Duration::days(interval))?],
+            Frequency::Weekly if self.weekdays.is_empty() => {
+                vec![wall_start.checked_add(Duration::weeks(interval))?]
+            },
+            Frequency::Weekly => {
+                let period_start = wall_start.checked_add(Duration::weeks(interval))?;
+                let weekday_offset = wall_start.weekday().number_days_from_monday();
+                let week_start = period_start.checked_sub(Duration::days(weekday_offset as i64))?;
+                (0..7)
+                    .filter_map(|day| week_start.checked_add(Duration::days(day)))
+                    .filter(|day| self.weekdays.contains(&day.weekday()))
+                    .collect()
+            },
+            Frequency::Yearly => {
+                let year = i32::try_from(wall_start.year() as i64 + interval).ok()?;
+                Date::from_calendar_date(year, Month::January, 1).ok()?;
+
+                let months = if self.months.is_empty() {
+                    vec![wall_start.month()]
+                } else {
+                    self.months.clone()
+                };
+                let days = if self.month_days.is_empty() {
+                    vec![wall_start.day()]
+                } else {
+                    self.month_days.clone()
+                };
+
+                // Ignore dates which do not exist in this year, like February 29th.
+                let mut dates: Vec<_> = months
+                    .iter()
+                    .flat_map(|month| days.iter().map(|day| (*month, *day)))
+                    .filter_map(|(month, day)| Date::from_calendar_date(year, month, day).ok())
+                    .collect();
+                dates.sort_unstable();
+                dates.dedup();
+
+                dates
+                    .into_iter()
+                    .map(|date| PrimitiveDateTime::new(date, wall_start.time()))
+                    .collect()
+            },
+        };
+
+        occurrences.retain(|occurrence| *occurrence >= wall_start);
+
+        Some(occurrences)
+    }
+}
+
+/// Recurrence instance excluded by `EXDATE`.
+enum ExDate {
+    Date(Date),
+    DateTime(OffsetDateTime),
+}
+
+impl ExDate {
+    /// Parse all values of an `EXDATE` property.
+    fn parse(property: &Property) -> Result<Vec<Self>, IcalError> {
+        let all_day = property.param("VALUE") == Some("DATE");
+
+        let mut exdates = Vec::new();
+        for value in property.value.split(',') {
+            let value = Property {
+                name: property.name.clone(),
+                params: property.params.clone(),
+                value: value.into(),
+            };
+            let time = parse_date_time(&value)?;
+            exdates.push(if all_day { Self::Date(time.date()) } else { Self::DateTime(time) });
+        }
+
+        Ok(exdates)
+    }
+
+    /// Check if an occurrence is excluded.
+    fn matches(&self, occurrence: OffsetDateTime) -> bool {
+        match self {
+            Self::Date(date) => occurrence.date() == *date,
+            Self::DateTime(time) => occurrence == *time,
+        }
+    }
+}
+
+/// Unfold iCalendar content lines.
+fn unfold(ics: &str) -> Vec<String> {
+    let mut lines: Vec<String> = Vec::new();
+    for line in ics.lines() {
+        match (line.strip_prefix([' ', '\t']), lines.last_mut()) {
+            (Some(continuation), Some(last)) => last.push_str(continuation),
+            _ => lines.push(line.trim_end_matches('\r').into()),
+        }
+    }
+    lines
+}
+
+/// Escape an iCalendar TEXT value.
+fn escape_text(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '\\' | ';' | ',' => {
+                escaped.push('\\');
+                escaped.push(c);
+            },
+            '\n' => escaped.push_str("\\n"),
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// Unescape an iCalendar TEXT value.
+fn unescape_text(text: &str) -> String {
+    let mut unescaped = String::with_capacity(text.len());
+    let mut chars = text.chars();
+    while let Some(c) = chars.next() {
+        match (c, chars.clone().next()) {
+            ('\\', Some('n' | 'N')) => {
+                chars.next();
+                unescaped.push('\n');
+            },
+            ('\\', Some(escaped)) => {
+                chars.next();
+                unescaped.push(escaped);
+            },
+            _ => unescaped.push(c),
+        }
+    }
+    unescaped
+}
+
+/// Format a time as iCalendar UTC DATE-TIME.
+fn format_utc(time: OffsetDateTime) -> String {
+    let time = time.to_offset(UtcOffset::UTC);
+    let time = PrimitiveDateTime::new(time.date(), time.time());
+    format!("{}Z", format_date_time(time))
+}
+
+/// Format a date as iCalendar DATE.
+fn format_date(date: Date) -> String {
+    format!("{:04}{:02}{:02}", date.year(), date.month() as u8, date.day())
+}
+
+/// Format a wall-clock time as iCalendar floating DATE-TIME.
+fn format_date_time(time: PrimitiveDateTime) -> String {
+    format!(
+        "{:04}{:02}{:02}T{:02}{:02}{:02}",
+        time.year(),
+        time.month() as u8,
+        time.day(),
+        time.hour(),
+        time.minute(),
+        time.second(),
+    )
+}
+
+/// Parse an iCalendar DATE or DATE-TIME property.
+///
+/// Times with a `TZID` are resolved in that timezone, floating times are
+/// interpreted as local time.
+fn parse_date_time(property: &Property) -> Result<OffsetDateTime, IcalError> {
+    let value = property.value.as_str();
+    let invalid = || IcalError::InvalidDateTime(value.into());
+
+    let (date, time) = match value.split_once('T') {
+        Some((date, time)) => (date, Some(time)),
+        None => (value, None),
+    };
+
+    // Parse the date.
+    if date.len() != 8 || !date.is_ascii() {
+        return Err(invalid());
+    }
+    let year = i32::from_str(&date[..4])?;
+    let month = Month::try_from(u8::from_str(&date[4..6])?)?;
+    let day = u8::from_str(&date[6..8])?;
+    let date = Date::from_calendar_date(year, month, day)?;
+
+    // Parse the time, defaulting to midnight for DATE values.
+    let (time, utc) = match time {
+        Some(time) => {
+            let (time, utc) = match time.strip_suffix('Z') {
+                Some(time) => (time, true),
+                None => (time, false),
+            };
+
+            if time.len() != 6 || !time.is_ascii() {
+                return Err(invalid());
+            }
+            let hour = u8::from_str(&time[..2])?;
+            let minute = u8::from_str(&time[2..4])?;
+            let second = u8::from_str(&time[4..6])?;
+
+            (Time::from_hms(hour, minute, second)?, utc)
+        },
+        None => (Time::MIDNIGHT, false),
+    };
+
+    let date_time = PrimitiveDateTime::new(date, time);
+    if utc {
+        Ok(date_time.assume_utc())
+    } else {
+        Ok(resolve_wall_time(date_time, time_zone(property).as_ref()))
+    }
+}
+
+/// Get the timezone of a DATE or DATE-TIME property's wall-clock time.
+///
+/// This uses the property's `TZID`, falling back to the local timezone for
+/// missing or unknown timezones.
+fn time_zone(property: &Property) -> Option<TimeZone> {
+    let zone = property.param("TZID").and_then(|tzid| TimeZone::load(tzid).ok());
+    zone.or_else(|| TimeZone::local().ok())
+}
+
+/// Check if a DATE or DATE-TIME property uses floating time.
+///
+/// Floating times have neither a `TZID` nor a UTC `Z` suffix.
+fn is_floating(property: &Property) -> bool {
+    property.param("TZID").is_none() && !property.value.ends_with('Z')
+}
+
+/// Convert an absolute time to wall-clock time.
+///
+/// Without a timezone, the current local UTC offset is used.
+fn wall_time(time: OffsetDateTime, zone: Option<&TimeZone>) -> PrimitiveDateTime {
+    let time = match zone {
+        Some(zone) => zone.to_local(time),
+        None => time.to_offset(UtcOffset::current_local_offset().unwrap_or(UtcOffset::UTC)),
+    };
+    PrimitiveDateTime::new(time.date(), time.time())
+}
+
+/// Convert a wall-clock time to an absolute time.
+///
+/// Without a timezone, the current local UTC offset is used.
+fn resolve_wall_time(wall_time: PrimitiveDateTime, zone: Option<&TimeZone>) -> OffsetDateTime {
+    match zone {
+        Some(zone) => zone.resolve(wall_time),
+        None => {
+            let offset = UtcOffset::current_local_offset().unwrap_or(UtcOffset::UTC);
+            wall_time.assume_offset(offset)
+        },
+    }
+}
+
+/// Parse an iCalendar DURATION value.
+fn parse_duration(value: &str) -> Result<Duration, IcalError> {
+    let invalid = || IcalError::InvalidDuration(value.into());
+
+    let (negative, rest) = match value.strip_prefix('-') {
+        Some(rest) => (true, rest),
+        None => (false, value.strip_prefix('+').unwrap_or(value)),
+    };
+    let rest = rest.strip_prefix('P').ok_or_else(invalid)?;
+
+    let mut duration = Duration::ZERO;
+    let mut number = String::new();
+    for c in rest.chars() {
+        let unit = match c {
+            '0'..='9' => {
+                number.push(c);
+                continue;
+            },
+            'T' => continue,
+            'W' => Duration::WEEK,
+            'D' => Duration::DAY,
+            'H' => Duration::HOUR,
+            'M' => Duration::MINUTE,
+            'S' => Duration::SECOND,
+            _ => return Err(invalid()),
+        };
+
+        let count = i32::from_str(&number).map_err(|_| invalid())?;
+        duration += unit * count;
+        number.clear();
+    }
+
+    if !number.is_empty() {
+        return Err(invalid());
+    }
+
+    Ok(if negative { -duration } else { duration })
+}
+
+/// Parse an iCalendar BOOLEAN value.
+fn parse_boolean(value: &str) -> Result<bool, IcalError> {
+    match value.to_ascii_uppercase().as_str() {
+        "TRUE" => Ok(true),
+        "FALSE" => Ok(false),
+        _ => Err(IcalError::InvalidBoolean(value.into())),
+    }
+}
+
+/// Parse the milliseconds of an alarm time.
+fn parse_millis(value: &str) -> Result<u16, IcalError> {
+    match u16::from_str(value)? {
+        millis @ 0..1000 => Ok(millis),
+        _ => Err(IcalError::InvalidMillis(value.into())),
+    }
+}
+
+/// Format an iCalendar weekday.
+fn format_weekday(day: Weekday) -> &'static str {
+    match day {
+        Weekday::Monday => "MO",
+        Weekday::Tuesday => "TU",
+        Weekday::Wednesday => "WE",
+        Weekday::Thursday => "TH",
+        Weekday::Friday => "FR",
+        Weekday::Saturday => "SA",
+        Weekday::Sunday => "SU",
+    }
+}
+
+/// Parse an iCalendar weekday.
+fn parse_weekday(day: &str) -> Result<Weekday, IcalError> {
+    match day {
+        "MO" => Ok(Weekday::Monday),
+        "TU" => Ok(Weekday::Tuesday),
+        "WE" => Ok(Weekday::Wednesday),
+        "TH" => Ok(Weekday::Thursday),
+        "FR" => Ok(Weekday::Friday),
+        "SA" => Ok(Weekday::Saturday),
+        "SU" => Ok(Weekday::Sunday),
+        _ => Err(IcalError::UnsupportedRecurrence(day.into())),
+    }
+}
+
+#[derive(thiserror::Error, Debug)]
+pub enum IcalError {
+    #[error("invalid date: {0}")]
+    ComponentRange(#[from] ComponentRange),
+    #[error("invalid boolean: {0}")]
+    InvalidBoolean(String),
+    #[error("invalid date: {0}")]
+    InvalidDateTime(String),
+    #[error("invalid duration: {0}")]
+    InvalidDuration(String),
+    #[error("invalid line: {0}")]
+    InvalidLine(String),
+    #[error("invalid milliseconds: {0}")]
+    InvalidMillis(String),
+    #[error("missing property {0}")]
+    MissingProperty(&'static str),
+    #[error("{0}")]
+    ParseInt(#[from] ParseIntError),
+    #[error("unexpected end of file")]
+    UnexpectedEof,
+    #[error("unsupported recurrence: {0}")]
+    UnsupportedRecurrence(String),
+}
+
+#[cfg(test)]
+mod tests {
+    use rezz::UNOWNED;
+
+    use super::*;
+
+    /// Single VALARM triggering at the start of its event.
+    const VALARM: [&str; 3] = ["BEGIN:VALARM", "TRIGGER:PT0S", "END:VALARM"];
+
+    /// Create a date.
+    fn date(year: i32, month: Month, day: u8) -> Date {
+        Date::from_calendar_date(year, month, day).unwrap()
+    }
+
+    /// Create a UTC time.
+    fn utc(year: i32, month: Month, day: u8, hour: u8, minute: u8) -> OffsetDateTime {
+        let date = Date::from_calendar_date(year, month, day).unwrap();
+        date.with_hms(hour, minute, 0).unwrap().assume_utc()
+    }
+
+    /// Join iCalendar content lines.
+    fn ics(lines: &[&str]) -> String {
+        lines.iter().map(|line| format!("{line}\r\n")).collect()
+    }
+
+    /// Import a calendar with a single event, relative to `now`.
+    fn import_event(event: &[&str], now: OffsetDateTime) -> Vec<Alarm> {
+        import_event_except(event, now, &Exceptions::default())
+    }
+
+    /// Import a calendar with a single event and excluded occurrences.
+    fn import_event_except(
+        event: &[&str],
+        now: OffsetDateTime,
+        exceptions: &Exceptions,
+    ) -> Vec<Alarm> {
+        import_at(&calendar(event), 60, exceptions, now).unwrap().alarms
+    }
+
+    /// Create a calendar with a single event.
+    fn calendar(event: &[&str]) -> String {
+        let header = ics(&["BEGIN:VCALENDAR", "VERSION:2.0", "BEGIN:VEVENT", "UID:event"]);
+        header + &ics(event) + &ics(&["END:VEVENT", "END:VCALENDAR"])
+    }
+
+    /// Get the trigger times of imported alarms.
+    fn trigger_times(event: &[&str], now: OffsetDateTime) -> Vec<OffsetDateTime> {
+        let alarms = import_event(event, now);
+        alarms
+            .iter()
+            .map(|alarm| OffsetDateTime::from_unix_timestamp(alarm.unix_time).unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn round_trip() {
+        let now = utc(2025, Month::January, 1, 0, 0);
+        let unix_time = utc(2025, Month::January, 2, 7, 30).unix_timestamp();
+
+        let mut wake_up = Alarm::new("wake, up; now", unix_time, 600);
+        wake_up.unix_millis = 250;
+        let mut reminder = Alarm::new("tea\\kettle\nboiling", unix_time, 30);
+        reminder.kind = AlarmKind::Reminder;
+        reminder.enabled = false;
+        let local_time = PrimitiveDateTime::new(now.date().next_day().unwrap(), Time::MIDNIGHT);
+        let zone = TimeZone::local().unwrap();
+        let floating = Alarm::floating("floating", local_time, 60, &zone);
+
+        // January 6th, 2025 is a Monday.
+        let local_time = PrimitiveDateTime::new(date(2025, Month::January, 6), Time::MIDNIGHT);
+        let mut repeating = Alarm::floating("repeating", local_time, 60, &zone);
+        repeating.set_repeat_days(&[Weekday::Monday, Weekday::Friday]);
+        repeating.set_exceptions([date(2025, Month::January, 10)]);
+
+        let alarms = vec![wake_up, reminder, floating, repeating];
+
+        let ics = export(&alarms);
+        assert!(ics.contains("DTSTART:20250102T000000\r\n"));
+        assert!(ics.contains("X-REZZ-ENABLED:FALSE\r\n"));
+        assert!(ics.contains("X-REZZ-UNIX-MILLIS:250\r\n"));
+        assert!(ics.contains("RRULE:FREQ=WEEKLY;BYDAY=MO,FR\r\n"));
+        assert!(ics.contains("EXDATE;VALUE=DATE:20250110\r\n"));
+
+        let import = import_at(&ics, 0, &Exceptions::default(), now).unwrap();
+        assert_eq!(import.alarms, alarms);
+        assert!(import.alarms.iter().all(|alarm| alarm.owner == UNOWNED));
+        assert!(import.one_shot.is_empty());
+    }
+
+    #[test]
+    fn stable_ids() {
+        let now = utc(2025, Month::January, 1, 0, 0);
+        let event = [
+            "DTSTART:20250102T070000Z",
+            "BEGIN:VALARM",
+            "TRIGGER:PT0S",
+            "END:VALARM",
+            "BEGIN:VALARM",
+            "TRIGGER:-PT5M",
+            "END:VALARM",
+            "BEGIN:VALARM",
+            "TRIGGER:-PT10M",
+            "END:VALARM",
+        ];
+
+        let ids: Vec<_> = import_event(&event, now).into_iter().map(|alarm| alarm.id).collect();
+        assert_eq!(ids, ["event", "event#1", "event#2"]);
+
+        // Re-importing produces the same alarms.
+        assert_eq!(import_event(&event, now), import_event(&event, now));
+    }
+
+    #[test]
+    fn daily_count() {
+        let event = ["DTSTART:20250101T070000Z", "RRULE:FREQ=DAILY;COUNT=3"];
+        let event = [&event[..], &VALARM].concat();
+
+        let now = utc(2025, Month::January, 2, 8, 0);
+        assert_eq!(trigger_times(&event, now), [utc(2025, Month::January, 3, 7, 0)]);
+
+        // Limited recurrences only ring once.
+        let import = import_at(&calendar(&event), 60, &Exceptions::default(), now).unwrap();
+        assert!(!import.alarms[0].repeats());
+        assert_eq!(import.one_shot, ["event"]);
+
+        let now = utc(2025, Month::January, 3, 8, 0);
+        assert!(trigger_times(&event, now).is_empty());
+    }
+
+    #[test]
+    fn weekly_by_day() {
+        // January 6th, 2025 is a Monday.
+        let event = ["DTSTART:20250106T063000Z", "RRULE:FREQ=WEEKLY;BYDAY=MO,WE"];
+        let event = [&event[..], &VALARM].concat();
+
+        let now = utc(2025, Month::January, 6, 7, 0);
+        assert_eq!(trigger_times(&event, now), [utc(2025, Month::January, 8, 6, 30)]);
+
+        let now = utc(2025, Month::January, 8, 7, 0);
+        assert_eq!(trigger_times(&event, now), [utc(2025, Month::January, 13, 6, 30)]);
+
+        // Weekly recurrences are imported as repeating alarms.
+        let alarms = import_event(&event, now);
+        assert!(alarms[0].repeats_on(Weekday::Monday));
+        assert!(alarms[0].repeats_on(Weekday::Wednesday));
+        assert!(!alarms[0].repeats_on(Weekday::Tuesday));
+    }
+
+    #[test]
+    fn yearly_until() {
+        let event = ["DTSTART:20200229T080000Z", "RRULE:FREQ=YEARLY;UNTIL=20290101T000000Z"];
+        let event = [&event[..], &VALARM].concat();
+
+        // Years without February 29th are skipped.
+        let now = utc(2025, Month::January, 1, 0, 0);
+        assert_eq!(trigger_times(&event, now), [utc(2028, Month::February, 29, 8, 0)]);
+
+        let now = utc(2028, Month::March, 1, 0, 0);
+        assert!(trigger_times(&event, now).is_empty());
+    }
+
+    #[test]
+    fn exdate() {
+        let event = [
+            "DTSTART:20250101T070000Z",
+            "RRULE:FREQ=DAILY",
+            "EXDATE:20250102T070000Z,20250103T070000Z",
+            "EXDATE;VALUE=DATE:20250104",
+        ];
+        let event = [&event[..], &VALARM].concat();
+
+        let now = utc(2025, Month::January, 1, 8, 0);
+        assert_eq!(trigger_times(&event, now), [utc(2025, Month::January, 5, 7, 0)]);
+
+        // Excluded dates are stored as the alarm's exceptions.
+        let alarms = import_event(&event, now);
+        assert!(alarms[0].is_exception(date(2025, Month::January, 2)));
+        assert!(alarms[0].is_exception(date(2025, Month::January, 4)));
+        assert!(!alarms[0].is_exception(date(2025, Month::January, 5)));
+    }
+
+    #[test]
+    fn holiday_exceptions() {
+        let event = ["DTSTART:20250101T070000Z", "RRULE:FREQ=DAILY"];
+        let event = [&event[..], &VALARM].concat();
+        let now = utc(2025, Month::January, 1, 8, 0);
+        let holidays = vec![date(2025, Month::January, 2), date(2025, Month::January, 4)];
+
+        let exceptions = Exceptions { holidays: holidays.clone(), skip_next: false };
+        let alarms = import_event_except(&event, now, &exceptions);
+        assert_eq!(alarms[0].unix_time, utc(2025, Month::January, 3, 7, 0).unix_timestamp());
+        assert!(holidays.iter().all(|holiday| alarms[0].is_exception(*holiday)));
+
+        // The skipped occurrence is the next one which is not a holiday.
+        let exceptions = Exceptions { holidays, skip_next: true };
+        let alarms = import_event_except(&event, now, &exceptions);
+        assert_eq!(alarms[0].unix_time, utc(2025, Month::January, 5, 7, 0).unix_timestamp());
+    }
+
+    #[test]
+    fn skip_next() {
+        let event = ["DTSTART:20250101T070000Z", "RRULE:FREQ=DAILY"];
+        let event = [&event[..], &VALARM].concat();
+        let now = utc(2025, Month::January, 1, 8, 0);
+
+        let exceptions = Exceptions { skip_next: true, ..Default::default() };
+        let alarms = import_event_except(&event, now, &exceptions);
+        assert_eq!(alarms[0].unix_time, utc(2025, Month::January, 3, 7, 0).unix_timestamp());
+
+        // Rezz must not reschedule the alarm to the skipped occurrence.
+        assert!(alarms[0].repeats());
+        assert_eq!(alarms[0].exceptions, [date(2025, Month::January, 2).to_julian_day()]);
+
+        // Non-recurring events are not skipped.
+        let event = [&["DTSTART:20250102T070000Z"][..], &VALARM].concat();
+        let alarms = import_event_except(&event, now, &exceptions);
+        assert_eq!(alarms[0].unix_time, utc(2025, Month::January, 2, 7, 0).unix_timestamp());
+    }
+
+    #[test]
+    fn holiday_dates() {
+        let calendar = ics(&[
+            "BEGIN:VCALENDAR",
+            "BEGIN:VEVENT",
+            "DTSTART;VALUE=DATE:20251225",
+            "END:VEVENT",
+            "BEGIN:VEVENT",
+            "DTSTART;VALUE=DATE:20250804",
+            "DTEND;VALUE=DATE:20250807",
+            "END:VEVENT",
+            "BEGIN:VEVENT",
+            "DTSTART;VALUE=DATE:20250501",
+            "RRULE:FREQ=YEARLY",
+            "END:VEVENT",
+            "END:VCALENDAR",
+        ]);
+
+        let holidays = holidays(&calendar).unwrap();
+
+        // Single and multi-day events cover all their dates.
+        let expected = [
+            date(2025, Month::December, 25),
+            date(2025, Month::August, 4),
+            date(2025, Month::August, 5),
+            date(2025, Month::August, 6),
+        ];
+        assert_eq!(holidays[..4], expected);
+        assert!(!holidays.contains(&date(2025, Month::August, 7)));
+
+        // Recurring events cover every occurrence.
+        assert!(holidays.contains(&date(2025, Month::May, 1)));
+        assert!(holidays.contains(&date(2030, Month::May, 1)));
+        assert!(!holidays.contains(&date(2030, Month::May, 2)));
+    }
+
+    #[test]
+    fn folded_lines() {
+        let now = utc(2025, Month::January, 1, 0, 0);
+        let ics = ics(&[
+            "BEGIN:VCALENDAR",
+            "BEGIN:VEVENT",
+            "UID:folded",
+            "  uid\\, escaped\\;\\\\",
+            "DTSTART:20250102",
+            "\tT070000Z",
+            "BEGIN:VALARM",
+            "TRIGGER:PT0S",
+            "END:VALARM",
+            "END:VEVENT",
+            "END:VCALENDAR",
+        ]);
+
+        let alarms = import_at(&ics, 60, &Exceptions::default(), now).unwrap().alarms;
+        assert_eq!(alarms.len(), 1);
+        assert_eq!(alarms[0].id, "folded uid, escaped;\\");
+        assert_eq!(alarms[0].unix_time, utc(2025, Month::January, 2, 7, 0).unix_timestamp());
+    }
+
+    #[test]
+    fn text_escaping() {
+        let text = "a,b;c\\d\ne";
+        assert_eq!(escape_text(text), "a\\,b\\;c\\\\d\\ne");
+        assert_eq!(unescape_text(&escape_text(text)), text);
+        assert_eq!(unescape_text("A\\NB"), "A\nB");
+    }
+
+    #[test]
+    fn relative_triggers() {
+        let now = utc(2025, Month::January, 1, 0, 0);
+        let event = [
+            "DTSTART:20250101T070000Z",
+            "DURATION:PT1H",
+            "BEGIN:VALARM",
+            "TRIGGER:-PT15M",
+            "END:VALARM",
+            "BEGIN:VALARM",
+            "TRIGGER;RELATED=END:-PT5M",
+            "END:VALARM",
+            "BEGIN:VALARM",
+            "TRIGGER;RELATED=END:P1DT1H",
+            "END:VALARM",
+        ];
+
+        let expected = [
+            utc(2025, Month::January, 1, 6, 45),
+            utc(2025, Month::January, 1, 7, 55),
+            utc(2025, Month::January, 2, 9, 0),
+        ];
+        assert_eq!(trigger_times(&event, now), expected);
+
+        // Triggers relative to DTEND.
+        let event = [
+            "DTSTART:20250101T070000Z",
+            "DTEND:20250101T073000Z",
+            "BEGIN:VALARM",
+            "TRIGGER;RELATED=END:-PT10M",
+            "END:VALARM",
+        ];
+        assert_eq!(trigger_times(&event, now), [utc(2025, Month::January, 1, 7, 20)]);
+    }
+
+    #[test]
+    fn tzid_across_dst() {
+        // Berlin switches from UTC+1 to UTC+2 on March 30th, 2025.
+        let event = ["DTSTART;TZID=Europe/Berlin:20250320T070000", "RRULE:FREQ=DAILY"];
+        let event = [&event[..], &VALARM].concat();
+
+        let now = utc(2025, Month::March, 29, 7, 0);
+        assert_eq!(trigger_times(&event, now), [utc(2025, Month::March, 30, 5, 0)]);
+
+        let now = utc(2025, Month::March, 28, 7, 0);
+        assert_eq!(trigger_times(&event, now), [utc(2025, Month::March, 29, 6, 0)]);
+
+        // Times with a TZID are not floating.
+        assert!(import_event(&event, now).iter().all(|alarm| !alarm.floating));
+
+        // Recurrences in other timezones cannot be repeated by rezz.
+        let import = import_at(&calendar(&event), 60, &Exceptions::default(), now).unwrap();
+        if TimeZone::local().is_ok_and(|zone| zone.name() != "Europe/Berlin") {
+            assert_eq!(import.one_shot, ["event"]);
+        }
+    }
+}
@@ -1,21 +1,52 @@
 //! Alarm clock CLI interface.
 
-use std::num::ParseIntError;
+use std::fs;
+use std::io::{self, IsTerminal};
+use std::iter;
+use std::path::{Path, PathBuf};
 use std::process::ExitCode;
-use std::str::FromStr;
 use std::time::Duration as StdDuration;
 
-use alarm::audio::AlarmSound;
-use alarm::{Alarms, Event, Subscriber};
-use clap::{Args, Parser, Subcommand};
-use rezz::Alarm;
-use time::error::ComponentRange;
-use time::format_description::well_known::Rfc2822;
-use time::{Duration, Month, OffsetDateTime, Time, UtcOffset};
+use alarm::error::Error;
+use alarm::notify::{self, Action, RingNotification};
+use alarm::output::{AlarmOutput, OutputConfig};
+use alarm::vibrate::{VibrationPattern, VibratorDevice};
+use alarm::{Alarms, Event, LatePolicy, Subscriber, Timers, reminder};
+use clap::{Args, Parser, Subcommand, ValueEnum};
+use rezz::tz::TimeZone;
+use rezz::{Alarm, AlarmKind, LatePolicy as AlarmLatePolicy, Timer};
+use serde::Serialize;
+use time::format_description::well_known::{Rfc2822, Rfc3339};
+use time::{Date, Duration, OffsetDateTime, PrimitiveDateTime, UtcOffset, Weekday};
 use uuid::Uuid;
 
+use crate::datetime::{ClapDate, ClapDateTime, ClapWeekday};
+use crate::ical::Exceptions;
+
+mod datetime;
+mod ical;
+
+/// Exit code details shown in the help output.
+const EXIT_CODES: &str = "\
+Exit codes:
+  1-9    Failure of the add, remove, list, clear, export, import, timer, enable
+         or disable subcommand
+  10-49  Errors reported by rezz:
+  10     Access denied
+  11     Alarm or timer exists already
+  12     Alarm or timer not found
+  13     Invalid alarm or timer ID
+  14     Invalid alarm time
+  15     Alarm quota exceeded
+  16     RTC wakeup could not be updated
+  17     Invalid ring duration
+  18     Invalid timer duration
+  50-99  Failure of other subcommands:
+  50     Failure of the skip subcommand
+  51     Failure of the exceptions subcommand";
+
 #[derive(Parser, Debug)]
-#[command(author, version, about)]
+#[command(author, version, about, after_long_help = EXIT_CODES)]
 struct Cli {
     #[command(subcommand)]
     subcommand: Subcmd,
@@ -32,34 +63,234 @@ enum Subcmd {
     /// Remove an existing alarm.
     #[clap(alias = "r")]
     Remove(RemoveArgs),
+    /// Remove all alarms of the current user.
+    #[clap(alias = "c")]
+    Clear(ClearArgs),
     /// List all alarms.
     #[clap(alias = "l")]
     List(ListArgs),
+    /// Export all alarms.
+    Export(ExportArgs),
+    /// Import alarms from a file.
+    ///
+    /// Alarms with an ID that exists already are replaced. Events recurring
+    /// daily or weekly are imported as repeating alarms, other recurring events
+    /// only ring at their next occurrence.
+    Import(ImportArgs),
+    /// Start or manage countdown timers.
+    #[clap(alias = "t")]
+    Timer(TimerArgs),
+    /// Enable disabled alarms.
+    Enable(EnableArgs),
+    /// Disable alarms without removing them.
+    Disable(EnableArgs),
+    /// Skip the next occurrence of alarms.
+    ///
+    /// Repeating alarms move to their following occurrence, other alarms are
+    /// disabled.
+    Skip(EnableArgs),
+    /// Set the dates on which an alarm does not ring.
+    ///
+    /// Without any dates or holidays, all exceptions are removed.
+    Exceptions(ExceptionsArgs),
 }
 
 #[derive(Args, Debug)]
-struct DaemonArgs {}
+struct DaemonArgs {
+    /// Handling of alarms noticed after their ring time [remaining, full,
+    /// skip].
+    #[clap(long, default_value = "remaining")]
+    late_policy: LatePolicy,
+    /// Do not vibrate while alarms are ringing.
+    #[clap(long, conflicts_with = "vibrator")]
+    no_vibrate: bool,
+    /// Vibration motor, like `/sys/class/leds/vibrator` or `/dev/input/event1`
+    /// [default: detected motor].
+    #[clap(long, value_name = "PATH")]
+    vibrator: Option<PathBuf>,
+    /// Vibration pattern in milliseconds.
+    #[clap(long, value_name = "ON_MS:OFF_MS", default_value = "1000:1000")]
+    vibration_pattern: VibrationPattern,
+    /// Always use the built-in alarm sound and vibration, ignoring feedbackd.
+    #[clap(long)]
+    no_feedbackd: bool,
+}
 
 #[derive(Args, Debug)]
 struct AddArgs {
     /// ID used to delete the alarm [default: UUID].
     #[clap(long)]
     id: Option<String>,
-    /// Alarm time in RFC3339 format.
-    time: ClapDateTime,
+    /// Alarm time, like `07:30`, `+25m`, `tomorrow 6:30am`, `09:00 in
+    /// America/New_York` or RFC3339.
+    #[clap(required = true, num_args = 1..)]
+    time: Vec<String>,
+    /// Print the resolved alarm time without adding the alarm.
+    #[clap(long)]
+    dry_run: bool,
     /// Seconds to ring the alarm for.
     #[clap(long, short = 's', default_value_t = 600)]
     ring_seconds: u32,
+    /// Keep the alarm at the same local wall-clock time when the system's
+    /// timezone or its UTC offset changes.
+    #[clap(long, short = 'f')]
+    floating: bool,
+    /// Alert style when the alarm rings.
+    #[clap(long, short = 'k', value_enum, default_value_t = KindArg::WakeUp)]
+    kind: KindArg,
+    /// Handling of the alarm when noticed after its ring time [remaining,
+    /// full, skip] [default: daemon's late policy].
+    #[clap(long)]
+    late_policy: Option<LatePolicy>,
+    /// Repeat the alarm on these weekdays, like `mon,tue,wed,thu,fri`.
+    #[clap(long, short = 'r', value_name = "DAYS", value_delimiter = ',')]
+    repeat: Vec<ClapWeekday>,
+    /// iCalendar file with holidays on which the alarm does not ring.
+    #[clap(long)]
+    holidays: Option<PathBuf>,
+}
+
+/// Alarm alert styles.
+#[derive(ValueEnum, Copy, Clone, Debug)]
+enum KindArg {
+    /// Loop the alarm sound for the ring duration.
+    WakeUp,
+    /// Play a short chime.
+    Reminder,
+}
+
+impl From<KindArg> for AlarmKind {
+    fn from(kind: KindArg) -> Self {
+        match kind {
+            KindArg::WakeUp => Self::WakeUp,
+            KindArg::Reminder => Self::Reminder,
+        }
+    }
 }
 
 #[derive(Args, Debug)]
 struct RemoveArgs {
     /// Alarm IDs.
+    #[clap(required = true)]
+    id: Vec<String>,
+}
+
+#[derive(Args, Debug)]
+struct EnableArgs {
+    /// Alarm IDs.
+    #[clap(required = true)]
     id: Vec<String>,
 }
 
 #[derive(Args, Debug)]
-struct ListArgs {}
+struct ExceptionsArgs {
+    /// Alarm ID.
+    id: String,
+    /// Dates on which the alarm does not ring, like `2025-12-24`.
+    dates: Vec<ClapDate>,
+    /// iCalendar file with holidays on which the alarm does not ring.
+    #[clap(long)]
+    holidays: Option<PathBuf>,
+}
+
+#[derive(Args, Debug)]
+struct ClearArgs {
+    /// Remove the alarms of all users.
+    #[clap(long, short = 'a')]
+    all: bool,
+}
+
+#[derive(Args, Debug)]
+struct ListArgs {
+    /// Output format.
+    #[clap(long, value_enum, default_value_t = ListFormat::Plain)]
+    format: ListFormat,
+    /// List the alarms of all users.
+    #[clap(long, short = 'a')]
+    all: bool,
+}
+
+/// Alarm list formats.
+#[derive(ValueEnum, Copy, Clone, Debug)]
+enum ListFormat {
+    /// Human-readable table.
+    Plain,
+    /// Tab-separated values with a header row.
+    Tsv,
+    /// JSON array of alarms.
+    Json,
+}
+
+#[derive(Args, Debug)]
+struct ExportArgs {
+    /// Output format.
+    #[clap(long, value_enum, default_value_t = ExportFormat::Ics)]
+    format: ExportFormat,
+}
+
+/// Alarm export formats.
+#[derive(ValueEnum, Copy, Clone, Debug)]
+enum ExportFormat {
+    /// iCalendar.
+    Ics,
+}
+
+#[derive(Args, Debug)]
+struct ImportArgs {
+    /// iCalendar file path.
+    path: PathBuf,
+    /// Seconds to ring alarms for, unless specified by the file.
+    #[clap(long, short = 's', default_value_t = 600)]
+    ring_seconds: u32,
+    /// iCalendar file with holidays on which recurring alarms are skipped.
+    #[clap(long)]
+    holidays: Option<PathBuf>,
+    /// Skip the next occurrence of recurring events.
+    #[clap(long)]
+    skip_next: bool,
+}
+
+#[derive(Args, Debug)]
+#[command(args_conflicts_with_subcommands = true)]
+struct TimerArgs {
+    #[command(subcommand)]
+    action: Option<TimerAction>,
+    #[command(flatten)]
+    start: TimerStartArgs,
+}
+
+#[derive(Args, Debug)]
+struct TimerStartArgs {
+    /// ID used to manage the timer [default: UUID].
+    #[clap(long)]
+    id: Option<String>,
+    /// Timer duration, like `10m` or `1h 30m`.
+    #[clap(num_args = 1..)]
+    duration: Vec<String>,
+    /// Seconds to ring the timer for.
+    #[clap(long, short = 's', default_value_t = 600)]
+    ring_seconds: u32,
+}
+
+#[derive(Subcommand, Debug)]
+enum TimerAction {
+    /// Pause a running timer.
+    Pause(TimerIdArgs),
+    /// Resume a paused timer.
+    Resume(TimerIdArgs),
+    /// Remove a timer.
+    #[clap(alias = "r")]
+    Remove(TimerIdArgs),
+    /// List all timers.
+    #[clap(alias = "l")]
+    List,
+}
+
+#[derive(Args, Debug)]
+struct TimerIdArgs {
+    /// Timer ID.
+    id: String,
+}
 
 #[tokio::main(flavor = "current_thread")]
 pub async fn main() -> ExitCode {
@@ -67,60 +298,182 @@ pub async fn main() -> ExitCode {
 
     match cli.subcommand {
         Subcmd::Add(args) => {
+            let time = match args.time.join(" ").parse::<ClapDateTime>() {
+                Ok(ClapDateTime(time)) => time,
+                Err(err) => {
+                    eprintln!("Invalid alarm time: {err}");
+                    return ExitCode::from(1);
+                },
+            };
+
+            // Print resolved time without adding the alarm.
+            if args.dry_run {
+                println!("Alarm time: {}", time.format(&Rfc3339).unwrap());
+                return ExitCode::SUCCESS;
+            }
+
             let id = args.id.unwrap_or_else(|| Uuid::new_v4().to_string());
-            let unix_time = (args.time.0 - OffsetDateTime::UNIX_EPOCH).whole_seconds();
-            let alarm = Alarm::new(&id, unix_time, args.ring_seconds);
+            let mut alarm = if args.floating {
+                let timezone = match TimeZone::local() {
+                    Ok(timezone) => timezone,
+                    Err(err) => {
+                        eprintln!("Could not load local timezone: {err}");
+                        return ExitCode::from(1);
+                    },
+                };
+
+                // Anchor the alarm to its wall-clock time in the system's timezone.
+                let local_time = timezone.to_local(time);
+                let local_time = PrimitiveDateTime::new(local_time.date(), local_time.time());
+                Alarm::floating(&id, local_time, args.ring_seconds, &timezone)
+            } else {
+                let unix_time_ms = (time - OffsetDateTime::UNIX_EPOCH).whole_milliseconds();
+                Alarm::from_unix_time_ms(&id, unix_time_ms as i64, args.ring_seconds)
+            };
+            alarm.kind = args.kind.into();
+            if let Some(late_policy) = args.late_policy {
+                alarm.late_policy = late_policy.into();
+            }
+            let weekdays: Vec<_> =
+                args.repeat.iter().map(|ClapWeekday(weekday)| *weekday).collect();
+            alarm.set_repeat_days(&weekdays);
+            if let Some(path) = &args.holidays {
+                match read_holidays(path) {
+                    Ok(holidays) => alarm.set_exceptions(holidays),
+                    Err(err) => {
+                        eprintln!("{err}");
+                        return ExitCode::from(1);
+                    },
+                }
+            }
 
             match Alarms.add(alarm).await {
                 Ok(()) => println!("Added alarm with ID {id:?}"),
                 Err(err) => {
                     eprintln!("Could not add alarm: {err}");
-                    return ExitCode::from(1);
+                    return exit_code(&err, 1);
                 },
             }
         },
-        Subcmd::Remove(args) => {
-            for id in &args.id {
-                match Alarms.remove(id.clone()).await {
-                    Ok(()) => println!("Removed alarm with ID {:?}", args.id),
-                    Err(err) => {
-                        eprintln!("Could not remove alarm: {err}");
-                        return ExitCode::from(2);
-                    },
+        Subcmd::Remove(args) => match Alarms.remove_all(args.id.clone()).await {
+            Ok(()) => {
+                for id in &args.id {
+                    println!("Removed alarm with ID {id:?}");
                 }
+            },
+            Err(err) => {
+                eprintln!("Could not remove alarm: {err}");
+                return exit_code(&err, 2);
+            },
+        },
+        Subcmd::Clear(args) => {
+            let result = if args.all { Alarms.clear_all().await } else { Alarms.clear().await };
+            match result {
+                Ok(()) => println!("Removed all alarms"),
+                Err(err) => {
+                    eprintln!("Could not remove alarms: {err}");
+                    return exit_code(&err, 4);
+                },
             }
         },
-        Subcmd::List(_args) => {
+        Subcmd::List(args) => {
+            let alarms = if args.all { Alarms.load_all().await } else { Alarms.load().await };
+            let mut alarms = match alarms {
+                Ok(alarms) => alarms,
+                Err(err) => {
+                    eprintln!("Could not read alarms database: {err}");
+                    return exit_code(&err, 3);
+                },
+            };
+            alarms.sort_unstable();
+
+            let entries: Vec<_> = alarms.iter().map(ListEntry::new).collect();
+            match args.format {
+                ListFormat::Plain => print_plain(&entries),
+                ListFormat::Tsv => print_tsv(&entries),
+                ListFormat::Json => println!("{}", serde_json::to_string(&entries).unwrap()),
+            }
+        },
+        Subcmd::Export(args) => {
             let alarms = match Alarms.load().await {
                 Ok(alarms) => alarms,
                 Err(err) => {
                     eprintln!("Could not read alarms database: {err}");
-                    return ExitCode::from(3);
+                    return exit_code(&err, 5);
                 },
             };
 
-            // Early return without any alarms.
-            if alarms.is_empty() {
-                println!("No alarms set");
-                return ExitCode::SUCCESS;
+            match args.format {
+                ExportFormat::Ics => print!("{}", ical::export(&alarms)),
             }
+        },
+        Subcmd::Import(args) => {
+            let mut exceptions = Exceptions { skip_next: args.skip_next, ..Default::default() };
+            if let Some(path) = &args.holidays {
+                exceptions.holidays = match read_holidays(path) {
+                    Ok(holidays) => holidays,
+                    Err(err) => {
+                        eprintln!("{err}");
+                        return ExitCode::from(6);
+                    },
+                };
+            }
+
+            let alarms = match fs::read_to_string(&args.path) {
+                Ok(ics) => ical::import(&ics, args.ring_seconds, &exceptions),
+                Err(err) => {
+                    eprintln!("Could not read {:?}: {err}", args.path);
+                    return ExitCode::from(6);
+                },
+            };
+            let import = match alarms {
+                Ok(import) => import,
+                Err(err) => {
+                    eprintln!("Could not parse {:?}: {err}", args.path);
+                    return ExitCode::from(6);
+                },
+            };
 
-            // Print header.
-            println!("\x1b[4;1m{: <36}  {: <31}\x1b[0m", "ID", "Alarm Time");
+            for id in &import.one_shot {
+                eprintln!("Warning: Unsupported recurrence, alarm {id:?} will only ring once");
+            }
+            let alarms = import.alarms;
 
-            // Print each alarm.
-            for alarm in alarms {
-                // Try to convert unix seconds to local time.
-                let mut time = OffsetDateTime::UNIX_EPOCH + Duration::seconds(alarm.unix_time);
-                if let Ok(offset) = UtcOffset::current_local_offset() {
-                    time = time.to_offset(offset);
+            // Replace previously imported alarms, to avoid duplicates.
+            let count = alarms.len();
+            match Alarms.replace_all(alarms).await {
+                Ok(()) => println!("Imported {count} alarms"),
+                Err(err) => {
+                    eprintln!("Could not add alarms: {err}");
+                    return exit_code(&err, 6);
+                },
+            }
+        },
+        Subcmd::Timer(args) => return timer(args).await,
+        Subcmd::Enable(args) => return set_enabled(args, true).await,
+        Subcmd::Disable(args) => return set_enabled(args, false).await,
+        Subcmd::Skip(args) => return skip_next(args).await,
+        Subcmd::Exceptions(args) => {
+            let mut dates: Vec<_> = args.dates.iter().map(|ClapDate(date)| *date).collect();
+            if let Some(path) = &args.holidays {
+                match read_holidays(path) {
+                    Ok(holidays) => dates.extend(holidays),
+                    Err(err) => {
+                        eprintln!("{err}");
+                        return ExitCode::from(51);
+                    },
                 }
-                let time_str = time.format(&Rfc2822).unwrap();
+            }
 
-                println!("{: <36}  {: <31}", alarm.id, time_str);
+            match Alarms.set_exceptions(args.id.clone(), dates).await {
+                Ok(()) => println!("Updated exceptions of alarm with ID {:?}", args.id),
+                Err(err) => {
+                    eprintln!("Could not update alarm exceptions: {err}");
+                    return exit_code(&err, 51);
+                },
             }
         },
-        Subcmd::Daemon(_args) => {
+        Subcmd::Daemon(args) => {
             // Setup listener for DBus events.
             let mut subscriber = match Subscriber::new().await {
                 Ok(subscriber) => subscriber,
@@ -130,20 +483,43 @@ pub async fn main() -> ExitCode {
                 },
             };
 
+            subscriber.set_late_policy(args.late_policy);
+
+            let vibrator = match args.vibrator {
+                Some(path) => Some(VibratorDevice::from_path(path)),
+                None if args.no_vibrate => None,
+                None => VibratorDevice::detect(),
+            };
+            let output = OutputConfig {
+                vibrator,
+                feedbackd: !args.no_feedbackd,
+                vibration_pattern: args.vibration_pattern,
+            };
+
             println!("Successfully started alarm daemon");
 
             loop {
-                // Play alarm sounds.
-                if let Some(Event::Ring(alarm)) = subscriber.next().await {
-                    let sound = match AlarmSound::play() {
-                        Ok(sound) => sound,
-                        Err(err) => {
-                            eprintln!("Could not play alarm sound: {err}");
-                            continue;
-                        },
-                    };
-                    tokio::time::sleep(StdDuration::from_secs(alarm.ring_seconds as u64)).await;
-                    sound.stop();
+                match subscriber.next().await {
+                    // Play alarm sounds.
+                    Some(Event::Ring { alarm, duration }) => match alarm.kind {
+                        AlarmKind::WakeUp => ring_alarm(&output, alarm, duration).await,
+                        AlarmKind::Reminder => remind(&alarm).await,
+                    },
+                    Some(Event::TimerElapsed { timer, duration }) => {
+                        println!("Timer with ID {:?} elapsed", timer.id);
+                        let actions = [Action::Stop];
+                        ring(&output, "Timer elapsed", &timer.id, &actions, duration).await;
+                    },
+                    // Notify about skipped alarms.
+                    Some(Event::Missed(alarm)) => println!("Missed alarm with ID {:?}", alarm.id),
+                    // Notify about rezz restarts.
+                    Some(Event::Disconnected) => eprintln!("Lost connection to rezz daemon"),
+                    Some(Event::Connected) => println!("Reconnected to rezz daemon"),
+                    Some(Event::AlarmsChanged(_) | Event::TimersChanged(_)) => (),
+                    None => {
+                        eprintln!("DBus connection closed");
+                        return ExitCode::from(1);
+                    },
                 }
             }
         },
@@ -152,71 +528,422 @@ pub async fn main() -> ExitCode {
     ExitCode::SUCCESS
 }
 
-/// DateTime wrapper with `FromStr` implementation.
-#[derive(Clone, Debug)]
-struct ClapDateTime(OffsetDateTime);
+/// Handle the `timer` subcommand.
+async fn timer(args: TimerArgs) -> ExitCode {
+    let result = match args.action {
+        Some(TimerAction::Pause(args)) => {
+            Timers.pause(args.id.clone()).await.map(|_| println!("Paused timer {:?}", args.id))
+        },
+        Some(TimerAction::Resume(args)) => {
+            Timers.resume(args.id.clone()).await.map(|_| println!("Resumed timer {:?}", args.id))
+        },
+        Some(TimerAction::Remove(args)) => {
+            Timers.remove(args.id.clone()).await.map(|_| println!("Removed timer {:?}", args.id))
+        },
+        Some(TimerAction::List) => Timers.load().await.map(|timers| print_timers(&timers)),
+        None => {
+            let args = args.start;
+            let duration = match datetime::parse_duration(&args.duration.join(" ")) {
+                Ok(duration) => duration,
+                Err(err) => {
+                    eprintln!("Invalid timer duration: {err}");
+                    return ExitCode::from(7);
+                },
+            };
 
-impl FromStr for ClapDateTime {
-    type Err = DateTimeError;
+            let id = args.id.unwrap_or_else(|| Uuid::new_v4().to_string());
+            let duration = StdDuration::from_millis(duration.whole_milliseconds() as u64);
+            Timers
+                .start(id.clone(), duration, args.ring_seconds)
+                .await
+                .map(|_| println!("Started timer with ID {id:?}"))
+        },
+    };
 
-    fn from_str(s: &str) -> Result<Self, Self::Err> {
-        // Get current time.
-        let mut now = OffsetDateTime::now_local().unwrap_or_else(|_| OffsetDateTime::now_utc());
+    match result {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(err) => {
+            eprintln!("Timer operation failed: {err}");
+            exit_code(&err, 7)
+        },
+    }
+}
 
-        // Split date and time.
-        let (date, time) = match s.split_once('T') {
-            Some((date, time)) => (Some(date), time),
-            None => (None, s),
-        };
+/// Get the exit code for a failed rezz operation.
+///
+/// Errors reported by rezz use distinct exit codes, all other errors use the
+/// subcommand's fallback exit code.
+fn exit_code(err: &Error, fallback: u8) -> ExitCode {
+    let code = match err {
+        Error::AccessDenied(_) => 10,
+        Error::AlarmExists(_) | Error::TimerExists(_) => 11,
+        Error::AlarmNotFound(_) | Error::TimerNotFound(_) => 12,
+        Error::InvalidId(_) => 13,
+        Error::InvalidTime(_) => 14,
+        Error::QuotaExceeded(_) => 15,
+        Error::Rtc(_) => 16,
+        Error::InvalidRingDuration(_) => 17,
+        Error::InvalidDuration(_) => 18,
+        _ => fallback,
+    };
+    ExitCode::from(code)
+}
 
-        // Override date.
-        if let Some(date) = date {
-            let mut components = date.splitn(3, '-');
+/// Ring an alarm, snoozing it if requested through its notification.
+async fn ring_alarm(output: &OutputConfig, alarm: Alarm, duration: StdDuration) {
+    let actions = [Action::Stop, Action::Snooze];
+    if ring(output, "Alarm", &alarm.id, &actions, duration).await != Some(Action::Snooze) {
+        return;
+    }
 
-            let year =
-                components.next().ok_or_else(|| DateTimeError::InvalidFormat(date.into()))?;
-            now.replace_year(i32::from_str(year)?)?;
+    let id = alarm.id.clone();
+    match Alarms.snooze(alarm, notify::SNOOZE_DURATION).await {
+        Ok(()) => println!("Snoozed alarm with ID {id:?}"),
+        Err(err) => eprintln!("Could not snooze alarm: {err}"),
+    }
+}
+
+/// Play the alarm sound and vibrate for the specified duration.
+///
+/// A notification allows stopping the alarm early, returning the selected
+/// notification action. The notification is shown even if no sound or
+/// vibration could be started.
+async fn ring(
+    config: &OutputConfig,
+    summary: &str,
+    body: &str,
+    actions: &[Action],
+    duration: StdDuration,
+) -> Option<Action> {
+    let (output, errors) = AlarmOutput::start(config).await;
+    for err in errors {
+        eprintln!("Could not start alarm output: {err}");
+    }
 
-            let month =
-                components.next().ok_or_else(|| DateTimeError::InvalidFormat(date.into()))?;
-            let month_offset = u8::from_str(month)?.saturating_sub(1);
-            now.replace_month(Month::January.nth_next(month_offset))?;
+    // Always notify, since this might be the only indication of the alarm.
+    let mut notification = match RingNotification::show(summary, body, actions).await {
+        Ok(notification) => Some(notification),
+        Err(err) => {
+            eprintln!("Could not show notification: {err}");
+            None
+        },
+    };
+
+    // Skip waiting when nothing is ringing or shown.
+    if !output.is_active() && notification.is_none() {
+        return None;
+    }
+
+    // Ring until the duration elapsed or the user acted on the notification.
+    let action = match &mut notification {
+        Some(notification) => tokio::select! {
+            _ = tokio::time::sleep(duration) => None,
+            action = notification.action() => Some(action),
+        },
+        None => {
+            tokio::time::sleep(duration).await;
+            None
+        },
+    };
 
-            let day = components.next().ok_or_else(|| DateTimeError::InvalidFormat(date.into()))?;
-            now.replace_day(u8::from_str(day)?)?;
+    output.stop().await;
+    if let Some(notification) = notification {
+        notification.close().await;
+    }
+
+    action
+}
+
+/// Play the reminder chime and show a notification for an alarm.
+async fn remind(alarm: &Alarm) {
+    println!("Reminder for alarm with ID {:?}", alarm.id);
+
+    let errors = reminder::remind(alarm).await;
+    if let Some(err) = errors.notification {
+        eprintln!("Could not show notification: {err}");
+    }
+    if let Some(err) = errors.chime {
+        eprintln!("Could not play reminder sound: {err}");
+    }
+    if let Some(err) = errors.remove {
+        eprintln!("Could not remove reminder: {err}");
+    }
+}
+
+/// Enable or disable alarms.
+async fn set_enabled(args: EnableArgs, enabled: bool) -> ExitCode {
+    let (action, done, fallback) =
+        if enabled { ("enable", "Enabled", 8) } else { ("disable", "Disabled", 9) };
+
+    for id in args.id {
+        if let Err(err) = Alarms.set_enabled(id.clone(), enabled).await {
+            eprintln!("Could not {action} alarm: {err}");
+            return exit_code(&err, fallback);
         }
+        println!("{done} alarm with ID {id:?}");
+    }
 
-        // Override time.
+    ExitCode::SUCCESS
+}
+
+/// Skip the next occurrence of alarms.
+async fn skip_next(args: EnableArgs) -> ExitCode {
+    for id in args.id {
+        if let Err(err) = Alarms.skip_next(id.clone()).await {
+            eprintln!("Could not skip alarm: {err}");
+            return exit_code(&err, 50);
+        }
+        println!("Skipped next occurrence of alarm with ID {id:?}");
+    }
+
+    ExitCode::SUCCESS
+}
+
+/// Read the local dates of all holidays in an iCalendar file.
+///
+/// Holidays before today are ignored.
+fn read_holidays(path: &Path) -> Result<Vec<Date>, String> {
+    let ics = fs::read_to_string(path).map_err(|err| format!("Could not read {path:?}: {err}"))?;
+    let holidays =
+        ical::holidays(&ics).map_err(|err| format!("Could not parse {path:?}: {err}"))?;
+
+    let today = OffsetDateTime::now_local().unwrap_or_else(|_| OffsetDateTime::now_utc()).date();
+    Ok(holidays.into_iter().filter(|date| *date >= today).collect())
+}
+
+/// Print timers as a human-readable table.
+fn print_timers(timers: &[Timer]) {
+    // Early return without any timers.
+    if timers.is_empty() {
+        println!("No timers set");
+        return;
+    }
 
-        let (hour, rest) =
-            time.split_once(':').ok_or_else(|| DateTimeError::InvalidFormat(time.into()))?;
-        let (minute, second) = match rest.split_once(':') {
-            Some((minute, second)) => (minute, Some(second)),
-            None => (rest, None),
+    // Print header, only underlining it for terminals.
+    let header = format!("{: <36}  {: <10}  {: <10}  {}", "ID", "Duration", "Remaining", "State");
+    if io::stdout().is_terminal() {
+        println!("\x1b[4;1m{header}\x1b[0m");
+    } else {
+        println!("{header}");
+    }
+
+    // Print each timer.
+    for timer in timers {
+        let remaining = timer.remaining();
+        let state = if timer.paused {
+            "paused"
+        } else if remaining.is_zero() {
+            "ringing"
+        } else {
+            "running"
         };
 
-        let hour = u8::from_str(hour)?;
-        let minute = u8::from_str(minute)?;
-        let second = second.map_or(Ok(0), u8::from_str)?;
-        let time = Time::from_hms(hour, minute, second)?;
+        println!(
+            "{: <36}  {: <10}  {: <10}  {state}",
+            timer.id,
+            format_countdown(StdDuration::from_millis(timer.duration_ms)),
+            format_countdown(remaining),
+        );
+    }
+}
+
+/// Format a countdown duration as `HH:MM:SS`.
+fn format_countdown(duration: StdDuration) -> String {
+    // Round up, to avoid showing zero for running timers.
+    let seconds = duration.as_millis().div_ceil(1000);
+    format!("{:0>2}:{:0>2}:{:0>2}", seconds / 3600, seconds / 60 % 60, seconds % 60)
+}
+
+/// Alarm with computed fields for `alarm list`.
+#[derive(Serialize)]
+struct ListEntry<'a> {
+    #[serde(flatten)]
+    alarm: AlarmFields<'a>,
+    /// Local alarm time in RFC3339 format.
+    time: String,
+    /// Milliseconds until the alarm starts ringing.
+    ///
+    /// This is negative for alarms which started ringing already.
+    millis_until: i64,
+    #[serde(skip)]
+    local_time: OffsetDateTime,
+}
+
+impl<'a> ListEntry<'a> {
+    fn new(alarm: &'a Alarm) -> Self {
+        let now = OffsetDateTime::now_utc();
+
+        // Try to convert unix time to local time.
+        let mut local_time =
+            OffsetDateTime::UNIX_EPOCH + Duration::milliseconds(alarm.unix_time_ms());
+        if let Ok(offset) = UtcOffset::current_local_offset() {
+            local_time = local_time.to_offset(offset);
+        }
+
+        let millis_until = (local_time - now).whole_milliseconds() as i64;
+        let time = local_time.format(&Rfc3339).unwrap();
 
-        // Add one day if time has already passed.
-        if time < now.time() {
-            now += Duration::days(1);
+        Self { alarm: AlarmFields::new(alarm), time, millis_until, local_time }
+    }
+
+    /// Get human-readable time until the alarm starts ringing.
+    fn remaining_text(&self) -> String {
+        if !self.alarm.enabled {
+            return String::from("disabled");
+        }
+
+        if self.millis_until <= 0 {
+            return String::from("ringing");
         }
 
-        now = now.replace_time(time);
+        let duration = Duration::milliseconds(self.millis_until);
+        let days = duration.whole_days();
+        let hours = duration.whole_hours() - days * 24;
+        let minutes = duration.whole_minutes() - duration.whole_hours() * 60;
 
-        Ok(Self(now))
+        match (days, hours) {
+            (0, 0) => format!("{minutes}m"),
+            (0, _) => format!("{hours}h {minutes}m"),
+            _ => format!("{days}d {hours}h {minutes}m"),
+        }
     }
 }
 
-#[derive(thiserror::Error, Clone, Debug)]
-enum DateTimeError {
-    #[error("invalid format: {0}")]
-    ComponentRange(#[from] ComponentRange),
-    #[error("{0}")]
-    InvalidFormat(String),
-    #[error("{0}")]
-    ParseInt(#[from] ParseIntError),
+/// Alarm fields included in `alarm list`.
+#[derive(Serialize)]
+struct AlarmFields<'a> {
+    id: &'a str,
+    unix_time: i64,
+    unix_millis: u16,
+    ring_seconds: u32,
+    floating: bool,
+    /// Wall-clock time of floating alarms, like `2025-07-01T07:00:00`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    local_time: Option<String>,
+    enabled: bool,
+    owner: u32,
+    kind: AlarmKind,
+    late_policy: AlarmLatePolicy,
+    /// Weekdays on which the alarm repeats, like `mon`.
+    repeat: Vec<&'static str>,
+    /// Dates on which the alarm does not ring, like `2025-12-24`.
+    exceptions: Vec<String>,
+}
+
+impl<'a> AlarmFields<'a> {
+    fn new(alarm: &'a Alarm) -> Self {
+        let local_time = alarm.local_time().map(|time| {
+            format!(
+                "{}T{:02}:{:02}:{:02}",
+                format_date(time.date()),
+                time.hour(),
+                time.minute(),
+                time.second()
+            )
+        });
+
+        let weekdays = iter::successors(Some(Weekday::Monday), |day| Some(day.next())).take(7);
+        let repeat = weekdays.filter(|day| alarm.repeats_on(*day)).map(weekday_name).collect();
+
+        let exceptions = alarm
+            .exceptions
+            .iter()
+            .filter_map(|day| Date::from_julian_day(*day).ok())
+            .map(format_date)
+            .collect();
+
+        Self {
+            local_time,
+            id: &alarm.id,
+            unix_time: alarm.unix_time,
+            unix_millis: alarm.unix_millis,
+            ring_seconds: alarm.ring_seconds,
+            floating: alarm.floating,
+            enabled: alarm.enabled,
+            owner: alarm.owner,
+            kind: alarm.kind,
+            late_policy: alarm.late_policy,
+            repeat,
+            exceptions,
+        }
+    }
+}
+
+/// Format a date as `YYYY-MM-DD`.
+fn format_date(date: Date) -> String {
+    format!("{:04}-{:02}-{:02}", date.year(), date.month() as u8, date.day())
+}
+
+/// Get the short name of a weekday, like `mon`.
+fn weekday_name(weekday: Weekday) -> &'static str {
+    match weekday {
+        Weekday::Monday => "mon",
+        Weekday::Tuesday => "tue",
+        Weekday::Wednesday => "wed",
+        Weekday::Thursday => "thu",
+        Weekday::Friday => "fri",
+        Weekday::Saturday => "sat",
+        Weekday::Sunday => "sun",
+    }
+}
+
+/// Print alarms as a human-readable table.
+fn print_plain(entries: &[ListEntry]) {
+    // Early return without any alarms.
+    if entries.is_empty() {
+        println!("No alarms set");
+        return;
+    }
+
+    // Print header, only underlining it for terminals.
+    let header =
+        format!("{: <36}  {: <31}  {: <12}  {}", "ID", "Alarm Time", "Ring Seconds", "Remaining");
+    if io::stdout().is_terminal() {
+        println!("\x1b[4;1m{header}\x1b[0m");
+    } else {
+        println!("{header}");
+    }
+
+    // Print each alarm.
+    for entry in entries {
+        let time_str = entry.local_time.format(&Rfc2822).unwrap();
+
+        println!(
+            "{: <36}  {: <31}  {: <12}  {}",
+            entry.alarm.id,
+            time_str,
+            entry.alarm.ring_seconds,
+            entry.remaining_text(),
+        );
+    }
+}
+
+/// Print alarms as tab-separated values.
+fn print_tsv(entries: &[ListEntry]) {
+    // Avoid rustfmt splitting the header's escape sequences.
+    #[rustfmt::skip]
+    let header = "id\tunix_time\tunix_millis\tring_seconds\ttime\tmillis_until\tfloating\tlocal_time\tenabled\towner\tkind\tlate_policy\trepeat\texceptions";
+    println!("{header}");
+
+    for entry in entries {
+        // Tabs and newlines are not allowed inside of TSV fields.
+        let id = entry.alarm.id.replace(['\t', '\n'], " ");
+
+        println!(
+            "{id}\t{}\t{}\t{}\t{}\t{}\t{}\t{}\t{}\t{}\t{}\t{}\t{}\t{}",
+            entry.alarm.unix_time,
+            entry.alarm.unix_millis,
+            entry.alarm.ring_seconds,
+            entry.time,
+            entry.millis_until,
+            entry.alarm.floating,
+            entry.alarm.local_time.as_deref().unwrap_or_default(),
+            entry.alarm.enabled,
+            entry.alarm.owner,
+            entry.alarm.kind,
+            entry.alarm.late_policy,
+            entry.alarm.repeat.join(","),
+            entry.alarm.exceptions.join(","),
+        );
+    }
 }
@@ -0,0 +1,48 @@
+//! Alarm time resolution of `alarm add --dry-run`.
+
+use std::process::{Command, Output};
+
+use time::format_description::well_known::Rfc3339;
+use time::{Duration, OffsetDateTime};
+
+/// Run `alarm add --dry-run` with the specified time arguments.
+fn dry_run(time: &[&str]) -> Output {
+    let mut command = Command::new(env!("CARGO_BIN_EXE_alarm-cli"));
+    command.args(["add", "--dry-run"]).args(time).output().unwrap()
+}
+
+#[test]
+fn absolute_time() {
+    let output = dry_run(&["2099-06-30T07:00:00+02:00"]);
+
+    assert!(output.status.success());
+    assert_eq!(
+        String::from_utf8(output.stdout).unwrap(),
+        "Alarm time: 2099-06-30T07:00:00+02:00\n"
+    );
+}
+
+#[test]
+fn relative_time() {
+    let before = OffsetDateTime::now_utc();
+    let output = dry_run(&["in", "25m"]);
+    let after = OffsetDateTime::now_utc();
+
+    assert!(output.status.success());
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    let time = stdout.trim_end().strip_prefix("Alarm time: ").unwrap();
+    let time = OffsetDateTime::parse(time, &Rfc3339).unwrap();
+
+    // RFC3339 output drops sub-second precision.
+    let delay = Duration::minutes(25);
+    assert!(time >= before + delay - Duration::SECOND && time <= after + delay);
+}
+
+#[test]
+fn date_in_past() {
+    let output = dry_run(&["2001-01-01", "07:00"]);
+
+    assert_eq!(output.status.code(), Some(1));
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert_eq!(stderr, "Invalid alarm time: time is in the past: 2001-01-01 07:00\n");
+}
@@ -0,0 +1,130 @@
+//! Persistent application configuration.
+
+use std::io::Error as IoError;
+use std::path::PathBuf;
+use std::time::Duration;
+use std::{env, fs};
+
+use alarm::output::OutputConfig;
+use alarm::vibrate::{VibrationPattern, VibratorDevice};
+use serde::{Deserialize, Serialize};
+
+/// Config file location, relative to the XDG config directory.
+const CONFIG_PATH: &str = "alarm/alarm.toml";
+
+/// Application configuration.
+#[derive(Deserialize, Serialize, Default, Debug)]
+#[serde(default)]
+pub struct Config {
+    pub world_clock: WorldClock,
+    pub vibration: Vibration,
+    pub feedbackd: Feedbackd,
+}
+
+impl Config {
+    /// Load the configuration file.
+    ///
+    /// Missing or invalid configuration files will use the default config.
+    pub fn load() -> Self {
+        let content = match config_path().map(fs::read_to_string) {
+            Some(Ok(content)) => content,
+            _ => return Self::default(),
+        };
+
+        match toml::from_str(&content) {
+            Ok(config) => config,
+            Err(err) => {
+                eprintln!("Invalid config: {err}");
+                Self::default()
+            },
+        }
+    }
+
+    /// Write the configuration to disk.
+    pub fn save(&self) -> Result<(), ConfigError> {
+        let path = config_path().ok_or(ConfigError::MissingConfigDir)?;
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+
+        fs::write(path, toml::to_string(self)?)?;
+
+        Ok(())
+    }
+
+    /// Get the alarm output configuration.
+    pub fn output_config(&self) -> OutputConfig {
+        let vibrator = match &self.vibration.device {
+            _ if !self.vibration.enabled || self.vibration.on_ms == 0 => None,
+            Some(device) => Some(VibratorDevice::from_path(device)),
+            None => VibratorDevice::detect(),
+        };
+        let vibration_pattern = VibrationPattern {
+            on: Duration::from_millis(self.vibration.on_ms),
+            off: Duration::from_millis(self.vibration.off_ms),
+        };
+        OutputConfig { feedbackd: self.feedbackd.enabled, vibrator, vibration_pattern }
+    }
+}
+
+/// World clock configuration.
+#[derive(Deserialize, Serialize, Default, Debug)]
+#[serde(default)]
+pub struct WorldClock {
+    /// IANA names of all displayed timezones.
+    pub timezones: Vec<String>,
+}
+
+/// Vibration configuration for ringing alarms.
+#[derive(Deserialize, Serialize, Debug)]
+#[serde(default)]
+pub struct Vibration {
+    /// Vibrate while alarms are ringing.
+    pub enabled: bool,
+    /// Vibration motor, detected automatically when absent.
+    pub device: Option<PathBuf>,
+    /// Duration of each vibration in milliseconds.
+    pub on_ms: u64,
+    /// Pause between vibrations in milliseconds.
+    pub off_ms: u64,
+}
+
+impl Default for Vibration {
+    fn default() -> Self {
+        Self { enabled: true, device: None, on_ms: 1000, off_ms: 1000 }
+    }
+}
+
+/// Feedbackd configuration for ringing alarms.
+#[derive(Deserialize, Serialize, Debug)]
+#[serde(default)]
+pub struct Feedbackd {
+    /// Use feedbackd's themed feedback instead of sound and vibration, if it
+    /// is available.
+    pub enabled: bool,
+}
+
+impl Default for Feedbackd {
+    fn default() -> Self {
+        Self { enabled: true }
+    }
+}
+
+/// Get the configuration file path.
+fn config_path() -> Option<PathBuf> {
+    let config_dir = match env::var_os("XDG_CONFIG_HOME") {
+        Some(config_dir) if !config_dir.is_empty() => PathBuf::from(config_dir),
+        _ => PathBuf::from(env::var_os("HOME")?).join(".config"),
+    };
+    Some(config_dir.join(CONFIG_PATH))
+}
+
+#[derive(thiserror::Error, Debug)]
+pub enum ConfigError {
+    #[error("could not find config directory")]
+    MissingConfigDir,
+    #[error("could not serialize config: {0}")]
+    Serialize(#[from] toml::ser::Error),
+    #[error("could not write config: {0}")]
+    Io(#[from] IoError),
+}
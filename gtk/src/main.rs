@@ -1,17 +1,20 @@
-use std::cell::Cell;
+use std::cell::{Cell, RefCell};
 use std::collections::HashMap;
+use std::rc::Rc;
+use std::time::Duration as StdDuration;
 
-use alarm::{Alarms, Event, Subscriber};
+use alarm::error::Error;
+use alarm::{Alarms, Event, LatePolicy, Subscriber, reminder};
 use gtk4::gdk::Display;
 use gtk4::gio::ApplicationFlags;
 use gtk4::glib::char::Char;
-use gtk4::glib::{ExitCode, MainContext, OptionArg, OptionFlags};
+use gtk4::glib::{ExitCode, MainContext, OptionArg, OptionFlags, SignalHandlerId};
 use gtk4::prelude::*;
 use gtk4::{
     AlertDialog, Align, Application, ApplicationWindow, Button, CssProvider, Label, Orientation,
-    ScrolledWindow, Window,
+    ScrolledWindow, Switch, Window,
 };
-use rezz::Alarm;
+use rezz::{Alarm, AlarmKind, Timer};
 use time::macros::format_description;
 use time::{Duration, OffsetDateTime, UtcOffset};
 use tokio::sync::mpsc::{self, Receiver, Sender};
@@ -19,10 +22,17 @@ use tokio::sync::mpsc::{self, Receiver, Sender};
 use crate::navigation::{Navigator, Page};
 use crate::new_alarm::NewAlarmPage;
 use crate::ringing_alarm::RingingAlarmPage;
+use crate::stopwatch::{Stopwatch, StopwatchPage};
+use crate::timer::TimerPage;
+use crate::world_clock::WorldClockPage;
 
+mod config;
 pub mod navigation;
 mod new_alarm;
 mod ringing_alarm;
+mod stopwatch;
+mod timer;
+mod world_clock;
 
 /// Wayland application ID.
 const APP_ID: &str = "catacomb.Alarm";
@@ -44,6 +54,14 @@ async fn main() -> ExitCode {
         "Launch application in the background",
         None,
     );
+    application.add_main_option(
+        "late-policy",
+        Char::from(b'l'),
+        OptionFlags::NONE,
+        OptionArg::String,
+        "Handling of alarms noticed after their ring time [remaining, full, skip]",
+        Some("POLICY"),
+    );
 
     // Load CSS.
     application.connect_startup(|_| {
@@ -68,10 +86,24 @@ async fn main() -> ExitCode {
     // Handle CLI from any instance.
     let state = Cell::new(Some(state));
     application.connect_command_line(move |_app, cmdline| {
-        let daemon_mode = cmdline.options_dict().contains("daemon");
+        let options = cmdline.options_dict();
+        let daemon_mode = options.contains("daemon");
+
+        // Parse late alarm handling policy.
+        let late_policy = match options.lookup::<String>("late-policy") {
+            Ok(Some(late_policy)) => match late_policy.parse() {
+                Ok(late_policy) => late_policy,
+                Err(err) => {
+                    eprintln!("Error: {err}");
+                    return ExitCode::FAILURE;
+                },
+            },
+            _ => LatePolicy::default(),
+        };
+
         match state.take() {
             // Start event loop on first run.
-            Some(state) => state.start_master(daemon_mode),
+            Some(state) => state.start_master(daemon_mode, late_policy),
             // Only launch windows if daemon mode flag wasn't passed.
             None if !daemon_mode => {
                 let _ = new_window_tx.try_send(());
@@ -93,6 +125,8 @@ struct AlarmGtk {
     window_close_rx: Receiver<u32>,
     new_window_rx: Receiver<()>,
     app: Application,
+    stopwatch: Stopwatch,
+    connected: bool,
 }
 
 impl AlarmGtk {
@@ -103,6 +137,8 @@ impl AlarmGtk {
             window_close_rx,
             new_window_rx,
             app: app.clone(),
+            stopwatch: Stopwatch::new(),
+            connected: true,
             windows: Default::default(),
         }
     }
@@ -111,7 +147,7 @@ impl AlarmGtk {
     ///
     /// This will always start the event loop and open a new window if not
     /// launched in daemon mode.
-    fn start_master(mut self, daemon_mode: bool) {
+    fn start_master(mut self, daemon_mode: bool, late_policy: LatePolicy) {
         let mut daemon_guard = None;
         if daemon_mode {
             // Prevent automatic exit when created without any windows.
@@ -123,7 +159,7 @@ impl AlarmGtk {
 
         // Run main event loop.
         MainContext::default().spawn_local(async move {
-            self.listen().await;
+            self.listen(late_policy).await;
 
             // Release the GIO application guard, closing the application.
             daemon_guard.take();
@@ -131,7 +167,7 @@ impl AlarmGtk {
     }
 
     /// Handle events.
-    async fn listen(mut self) {
+    async fn listen(mut self, late_policy: LatePolicy) {
         // Subscribe to DBus events.
         let mut subscriber = match Subscriber::new().await {
             Ok(subscriber) => subscriber,
@@ -144,9 +180,11 @@ impl AlarmGtk {
                 return;
             },
         };
+        subscriber.set_late_policy(late_policy);
 
         // If we're not running in daemon mode, seed view with initial alarms.
-        self.update_alarms(subscriber.alarms());
+        self.update_alarms(&subscriber.alarms());
+        self.update_timers(&subscriber.timers());
 
         loop {
             tokio::select! {
@@ -156,9 +194,13 @@ impl AlarmGtk {
                 _ = self.new_window_rx.recv() => self.open_window(),
                 Some(event) = subscriber.next() => match event {
                     // Handle new/removed alarms.
-                    Event::AlarmsChanged(alarms) => self.update_alarms(alarms),
+                    Event::AlarmsChanged(alarms) => self.update_alarms(&alarms),
+                    // Chime without taking over the screen for reminders.
+                    Event::Ring { alarm, .. } if alarm.kind == AlarmKind::Reminder => {
+                        show_reminder(alarm)
+                    },
                     // Handle ringing alarms.
-                    Event::Ring(alarm) => {
+                    Event::Ring { alarm, duration } => {
                         // Ensure at least one window is open.
                         if self.windows.is_empty() {
                             self.open_window();
@@ -166,9 +208,28 @@ impl AlarmGtk {
 
                         // Ring any availabel window.
                         if let Some(window) = self.windows.values_mut().next() {
-                            window.ring(alarm).await;
+                            window.ring(alarm, duration).await;
+                        }
+                    },
+                    // Notify about skipped alarms.
+                    Event::Missed(alarm) => show_missed(&alarm),
+                    // Handle started/paused/removed timers.
+                    Event::TimersChanged(timers) => self.update_timers(&timers),
+                    // Handle elapsed timers.
+                    Event::TimerElapsed { timer, duration } => {
+                        // Ensure at least one window is open.
+                        if self.windows.is_empty() {
+                            self.open_window();
+                        }
+
+                        // Ring any available window.
+                        if let Some(window) = self.windows.values_mut().next() {
+                            window.ring_timer(timer, duration).await;
                         }
                     },
+                    // Handle rezz restarts.
+                    Event::Connected => self.set_connected(true),
+                    Event::Disconnected => self.set_connected(false),
                 }
             }
         }
@@ -181,6 +242,21 @@ impl AlarmGtk {
         }
     }
 
+    /// Update the UI's timers.
+    fn update_timers(&mut self, timers: &[Timer]) {
+        for window in self.windows.values_mut() {
+            window.update_timers(timers);
+        }
+    }
+
+    /// Update the UI's rezz daemon availability.
+    fn set_connected(&mut self, connected: bool) {
+        self.connected = connected;
+        for window in self.windows.values() {
+            window.set_connected(connected);
+        }
+    }
+
     /// Open the GTK4 UI.
     fn open_window(&mut self) {
         // Configure window settings.
@@ -198,8 +274,22 @@ impl AlarmGtk {
         let ringing_alarm_page = RingingAlarmPage::new(navigator.clone());
         navigator.add(&ringing_alarm_page);
 
+        // Add countdown timer page.
+        let timer_page = TimerPage::new(navigator.clone());
+        navigator.add(&timer_page);
+
+        // Add stopwatch page, sharing its state across windows.
+        let stopwatch_page = StopwatchPage::new(navigator.clone(), self.stopwatch.clone());
+        navigator.add(&stopwatch_page);
+
+        // Add world clock page.
+        let world_clock_page = WorldClockPage::new(navigator.clone());
+        navigator.add(&world_clock_page);
+
         // Add landing page.
-        let overview = Overview::new(navigator.clone(), new_alarm_page, ringing_alarm_page);
+        let overview =
+            Overview::new(navigator.clone(), new_alarm_page, ringing_alarm_page, timer_page);
+        overview.set_connected(self.connected);
         navigator.add(&overview);
 
         // Show window.
@@ -219,8 +309,10 @@ impl AlarmGtk {
 /// Alarm overview and landing page.
 pub struct Overview {
     ringing_alarm_page: RingingAlarmPage,
+    timer_page: TimerPage,
     alarms: ScrolledWindow,
     container: gtk4::Box,
+    banner: Label,
 }
 
 impl Overview {
@@ -228,21 +320,61 @@ impl Overview {
         navigator: Navigator,
         new_alarm_page: NewAlarmPage,
         ringing_alarm_page: RingingAlarmPage,
+        timer_page: TimerPage,
     ) -> Self {
         let container = gtk4::Box::new(Orientation::Vertical, 0);
         container.set_valign(Align::End);
 
+        // Create banner for unavailable rezz daemon.
+        let banner = Label::new(Some("Alarm daemon unavailable"));
+        banner.add_css_class("daemon-banner");
+        banner.set_visible(false);
+        container.append(&banner);
+
         // Create alarms container.
         let alarms = ScrolledWindow::new();
         container.append(&alarms);
 
+        // Create container for the page buttons.
+        let button_box = gtk4::Box::new(Orientation::Horizontal, 0);
+        button_box.set_margin_top(25);
+        button_box.set_margin_end(25);
+        button_box.set_margin_bottom(25);
+        button_box.set_margin_start(25);
+        container.append(&button_box);
+
+        // Button to open the countdown timers.
+        let timer_button = Button::with_label("Timers");
+        button_box.append(&timer_button);
+
+        // Button to open the stopwatch.
+        let stopwatch_button = Button::with_label("Stopwatch");
+        stopwatch_button.set_margin_start(10);
+        button_box.append(&stopwatch_button);
+
+        // Button to open the world clock.
+        let world_clock_button = Button::with_label("Clocks");
+        world_clock_button.set_margin_start(10);
+        button_box.append(&world_clock_button);
+
         // Button to create new alarms.
         let new_button = Button::with_label("Add Alarm");
-        new_button.set_margin_top(25);
-        new_button.set_margin_end(25);
-        new_button.set_margin_bottom(25);
-        new_button.set_margin_start(25);
-        container.append(&new_button);
+        new_button.set_halign(Align::End);
+        new_button.set_hexpand(true);
+        button_box.append(&new_button);
+
+        // Handle timer button press.
+        let timer_navigator = navigator.clone();
+        timer_button.connect_clicked(move |_| timer_navigator.show(TimerPage::id()));
+
+        // Handle stopwatch button press.
+        let stopwatch_navigator = navigator.clone();
+        stopwatch_button.connect_clicked(move |_| stopwatch_navigator.show(StopwatchPage::id()));
+
+        // Handle world clock button press.
+        let world_clock_navigator = navigator.clone();
+        world_clock_button
+            .connect_clicked(move |_| world_clock_navigator.show(WorldClockPage::id()));
 
         // Handle new alarm button press.
         new_button.connect_clicked(move |_| {
@@ -250,7 +382,7 @@ impl Overview {
             navigator.show(NewAlarmPage::id());
         });
 
-        Self { container, alarms, ringing_alarm_page }
+        Self { container, alarms, banner, ringing_alarm_page, timer_page }
     }
 
     /// Update the view with new alarms.
@@ -271,13 +403,28 @@ impl Overview {
 
         // Swap containers.
         self.container.remove(&self.alarms);
-        self.container.prepend(&scroll);
+        self.container.insert_child_after(&scroll, Some(&self.banner));
         self.alarms = scroll;
     }
 
+    /// Update the timer page with new timers.
+    fn update_timers(&mut self, timers: &[Timer]) {
+        self.timer_page.update(timers);
+    }
+
+    /// Show or hide the unavailable daemon banner.
+    fn set_connected(&self, connected: bool) {
+        self.banner.set_visible(!connected);
+    }
+
     /// Ring an alarm.
-    async fn ring(&mut self, alarm: Alarm) {
-        self.ringing_alarm_page.ring(alarm).await;
+    async fn ring(&mut self, alarm: Alarm, duration: StdDuration) {
+        self.ringing_alarm_page.ring(alarm, duration).await;
+    }
+
+    /// Ring an elapsed timer.
+    async fn ring_timer(&mut self, timer: Timer, duration: StdDuration) {
+        self.ringing_alarm_page.ring_timer(timer, duration).await;
     }
 
     /// Get the GTK components for an alarm.
@@ -314,6 +461,58 @@ impl Overview {
         date_label.set_halign(Align::Start);
         datetime_container.append(&date_label);
 
+        // Dim time and date of disabled alarms.
+        if !alarm.enabled {
+            datetime_container.add_css_class("dim-label");
+        }
+
+        // Add switch to enable or disable the alarm.
+        let switch = Switch::new();
+        switch.set_active(alarm.enabled);
+        switch.set_valign(Align::Center);
+        switch.set_margin_end(10);
+        container.append(&switch);
+
+        let id = alarm.id.clone();
+        let handler: Rc<RefCell<Option<SignalHandlerId>>> = Default::default();
+        let revert_handler = handler.clone();
+        let handler_id = switch.connect_active_notify(move |switch| {
+            let id = id.clone();
+            let enabled = switch.is_active();
+            let switch = switch.clone();
+            let handler = revert_handler.clone();
+            MainContext::default().spawn_local(async move {
+                if let Err(err) = Alarms.set_enabled(id, enabled).await {
+                    // Revert the switch, without updating the alarm again.
+                    if let Some(handler) = &*handler.borrow() {
+                        switch.block_signal(handler);
+                        switch.set_active(!enabled);
+                        switch.unblock_signal(handler);
+                    }
+
+                    show_rezz_error(&err);
+                }
+            });
+        });
+        *handler.borrow_mut() = Some(handler_id);
+
+        // Add button to skip the next occurrence of repeating alarms.
+        if alarm.repeats() {
+            let skip_button = Button::from_icon_name("media-skip-forward");
+            skip_button.add_css_class("overview-alarm-button");
+            container.append(&skip_button);
+
+            let id = alarm.id.clone();
+            skip_button.connect_clicked(move |_| {
+                let id = id.clone();
+                MainContext::default().spawn(async move {
+                    if let Err(err) = Alarms.skip_next(id).await {
+                        show_rezz_error(&err);
+                    }
+                });
+            });
+        }
+
         // Add button to dismiss alarm.
         let button = Button::from_icon_name("edit-delete");
         button.add_css_class("overview-alarm-button");
@@ -325,7 +524,7 @@ impl Overview {
             let id = id.clone();
             MainContext::default().spawn(async move {
                 if let Err(err) = Alarms.remove(id.clone()).await {
-                    show_error(err.to_string());
+                    show_rezz_error(&err);
                 }
             });
         });
@@ -344,8 +543,77 @@ impl Page<gtk4::Box> for Overview {
     }
 }
 
+/// Play the reminder chime and display a notification for an alarm.
+fn show_reminder(alarm: Alarm) {
+    MainContext::default().spawn_local(async move {
+        let errors = reminder::remind(&alarm).await;
+
+        // Fall back to a dialog without notification server.
+        if let Some(err) = errors.notification {
+            eprintln!("Could not show notification: {err}");
+
+            let alert = AlertDialog::builder().message(reminder::SUMMARY).detail(&alarm.id).build();
+            alert.show(None::<&Window>);
+        }
+        if let Some(err) = errors.chime {
+            eprintln!("Could not play reminder sound: {err}");
+        }
+        if let Some(err) = errors.remove {
+            eprintln!("Could not remove reminder: {err}");
+        }
+    });
+}
+
+/// Display a notification about a skipped alarm.
+fn show_missed(alarm: &Alarm) {
+    // Convert unix time to local time.
+    let utc_offset = UtcOffset::current_local_offset().unwrap_or(UtcOffset::UTC);
+    let time = OffsetDateTime::UNIX_EPOCH + Duration::seconds(alarm.unix_time);
+    let local_time = time.to_offset(utc_offset);
+
+    let time_format = format_description!("[hour]:[minute]");
+    let time_str = local_time.format(&time_format).unwrap();
+
+    let alert = AlertDialog::builder()
+        .message("Missed alarm")
+        .detail(format!("Alarm {:?} at {time_str} was skipped", alarm.id))
+        .build();
+    alert.show(None::<&Window>);
+}
+
 /// Display an error message in a new window.
 pub fn show_error(message: String) {
     let alert = AlertDialog::builder().message(message).build();
     alert.show(None::<&Window>);
 }
+
+/// Display a failed alarm or timer operation in a new window.
+pub fn show_rezz_error(err: &Error) {
+    let (message, detail): (&str, String) = match err {
+        Error::AccessDenied(_) => {
+            ("Permission denied", "You are not allowed to modify this alarm.".into())
+        },
+        Error::AlarmExists(_) => ("Alarm exists already", "Please try again.".into()),
+        Error::AlarmNotFound(_) => ("Alarm not found", "The alarm was removed already.".into()),
+        Error::TimerExists(_) => ("Timer exists already", "Please try again.".into()),
+        Error::TimerNotFound(_) => ("Timer not found", "The timer was removed already.".into()),
+        Error::InvalidId(msg) => ("Invalid alarm ID", msg.clone()),
+        Error::InvalidTime(_) => {
+            let year = |unix_time| {
+                OffsetDateTime::from_unix_timestamp(unix_time).map_or(0, |time| time.year())
+            };
+            let (min, max) = (year(rezz::MIN_UNIX_TIME), year(rezz::MAX_UNIX_TIME));
+            ("Invalid alarm time", format!("Alarms must be between the years {min} and {max}."))
+        },
+        Error::InvalidRingDuration(msg) => ("Invalid ring duration", msg.clone()),
+        Error::InvalidDuration(msg) => ("Invalid timer duration", msg.clone()),
+        Error::QuotaExceeded(_) => {
+            ("Too many alarms", "Remove existing alarms before adding new ones.".into())
+        },
+        Error::Rtc(msg) => ("Could not update system wakeup", msg.clone()),
+        err => return show_error(err.to_string()),
+    };
+
+    let alert = AlertDialog::builder().message(message).detail(detail).build();
+    alert.show(None::<&Window>);
+}
@@ -1,16 +1,21 @@
 //! UI for creating a new alarm.
 
+use std::cell::RefCell;
+use std::rc::Rc;
+
 use alarm::Alarms;
 use gtk4::glib::MainContext;
 use gtk4::prelude::*;
 use gtk4::{
     Adjustment, Align, Button, DropDown, Expression, Label, Orientation, PolicyType,
-    ScrolledWindow, StringList,
+    ScrolledWindow, StringList, Switch,
 };
-use rezz::Alarm;
-use time::{Duration, OffsetDateTime, Time};
+use rezz::tz::TimeZone;
+use rezz::{Alarm, AlarmKind};
+use time::{Duration, OffsetDateTime, PrimitiveDateTime, Time};
 use uuid::Uuid;
 
+use crate::config::Config;
 use crate::navigation::{Navigator, Page};
 
 /// Height of hour/minute labels.
@@ -26,6 +31,9 @@ const TIME_SLOT_COUNT: i32 = 3;
 pub struct NewAlarmPage {
     container: gtk4::Box,
     ring_duration_input: RingDurationInput,
+    timezone_input: TimeZoneInput,
+    floating_input: FloatingInput,
+    reminder_input: ReminderInput,
     time_input: TimeInput,
 }
 
@@ -34,10 +42,16 @@ impl NewAlarmPage {
     pub fn new(navigator: Navigator) -> Self {
         let ring_duration_input = RingDurationInput::new();
         let time_input = TimeInput::new();
+        let floating_input = FloatingInput::new();
+        let reminder_input = ReminderInput::new();
+        let timezone_input = TimeZoneInput::new(time_input.clone(), floating_input.clone());
         let menu_buttons = MenuButtons::new();
 
         let container = gtk4::Box::new(Orientation::Vertical, 0);
         container.append(ring_duration_input.widget());
+        container.append(timezone_input.widget());
+        container.append(floating_input.widget());
+        container.append(reminder_input.widget());
         container.append(time_input.widget());
         container.append(menu_buttons.widget());
         container.set_valign(Align::End);
@@ -49,18 +63,36 @@ impl NewAlarmPage {
         // Add confirm/cancel button handlers.
         let confirm_navigator = navigator.clone();
         let confirm_duration = ring_duration_input.clone();
+        let confirm_floating = floating_input.clone();
+        let confirm_reminder = reminder_input.clone();
         let confirm_time = time_input.clone();
         menu_buttons.on_confirm(move || {
-            Self::confirm(&confirm_navigator, &confirm_duration, &confirm_time)
+            Self::confirm(
+                &confirm_navigator,
+                &confirm_duration,
+                &confirm_floating,
+                &confirm_reminder,
+                &confirm_time,
+            )
         });
         menu_buttons.on_cancel(move || navigator.pop());
 
-        Self { container, ring_duration_input, time_input }
+        Self {
+            container,
+            ring_duration_input,
+            timezone_input,
+            floating_input,
+            reminder_input,
+            time_input,
+        }
     }
 
     /// Reset the page to its default content.
     pub fn reset(&self) {
         self.ring_duration_input.reset();
+        self.floating_input.reset();
+        self.reminder_input.reset();
+        self.timezone_input.reset();
         self.time_input.reset();
     }
 
@@ -68,17 +100,30 @@ impl NewAlarmPage {
     fn confirm(
         navigator: &Navigator,
         ring_duration_input: &RingDurationInput,
+        floating_input: &FloatingInput,
+        reminder_input: &ReminderInput,
         time_input: &TimeInput,
     ) {
         let ring_duration = ring_duration_input.duration().seconds();
-        let unix_time = time_input.unix_time();
         let id = Uuid::new_v4().to_string();
 
+        let mut alarm = if floating_input.floating() {
+            match time_input.floating_alarm(&id, ring_duration) {
+                Ok(alarm) => alarm,
+                Err(err) => {
+                    crate::show_error(format!("Could not load local timezone: {err}"));
+                    return;
+                },
+            }
+        } else {
+            Alarm::new(&id, time_input.unix_time(), ring_duration)
+        };
+        alarm.kind = reminder_input.kind();
+
         // Schedule the alarm.
         MainContext::default().spawn(async move {
-            let alarm = Alarm::new(&id, unix_time, ring_duration);
             if let Err(err) = Alarms.add(alarm).await {
-                crate::show_error(err.to_string());
+                crate::show_rezz_error(&err);
             }
         });
 
@@ -98,13 +143,13 @@ impl Page<gtk4::Box> for NewAlarmPage {
 
 /// Ring duration input.
 #[derive(Clone)]
-struct RingDurationInput {
+pub struct RingDurationInput {
     container: gtk4::Box,
     dropdown: DropDown,
 }
 
 impl RingDurationInput {
-    fn new() -> Self {
+    pub fn new() -> Self {
         let container = gtk4::Box::new(Orientation::Vertical, 10);
 
         let label = Label::new(Some("Ringing duration"));
@@ -125,12 +170,12 @@ impl RingDurationInput {
     }
 
     /// Get the GTK widget.
-    fn widget(&self) -> &gtk4::Box {
+    pub fn widget(&self) -> &gtk4::Box {
         &self.container
     }
 
     /// Get the selected duration.
-    fn duration(&self) -> RingDuration {
+    pub fn duration(&self) -> RingDuration {
         RingDuration::all()[self.dropdown.selected() as usize]
     }
 
@@ -175,23 +220,174 @@ impl RingDuration {
     }
 
     /// Get the ring duration in seconds.
-    fn seconds(&self) -> u32 {
+    pub fn seconds(&self) -> u32 {
         match self {
             Self::OneMinute => 60,
             Self::FiveMinutes => 60 * 5,
             Self::FifteenMinutes => 60 * 15,
             Self::ThirtyMinutes => 60 * 30,
-            Self::Forever => u32::MAX,
+            Self::Forever => rezz::RING_FOREVER,
         }
     }
 }
 
+/// Alarm timezone selection input.
+#[derive(Clone)]
+struct TimeZoneInput {
+    timezones: Rc<RefCell<Vec<TimeZone>>>,
+    container: gtk4::Box,
+    dropdown: DropDown,
+}
+
+impl TimeZoneInput {
+    /// Create the timezone input, updating `time_input` on change.
+    ///
+    /// The `floating_input` is only shown for the local timezone.
+    fn new(time_input: TimeInput, floating_input: FloatingInput) -> Self {
+        let container = gtk4::Box::new(Orientation::Vertical, 10);
+        container.set_margin_top(25);
+
+        let label = Label::new(Some("Timezone"));
+        label.set_halign(Align::Start);
+        container.append(&label);
+
+        let dropdown = DropDown::new(None::<StringList>, None::<Expression>);
+        container.append(&dropdown);
+
+        let input = Self { container, dropdown, timezones: Default::default() };
+
+        // Update the time input's timezone.
+        let change_input = input.clone();
+        input.dropdown.connect_selected_notify(move |_| {
+            let timezone = change_input.timezone();
+            floating_input.widget().set_visible(timezone.is_none());
+            time_input.set_timezone(timezone);
+        });
+
+        input
+    }
+
+    /// Get the GTK widget.
+    fn widget(&self) -> &gtk4::Box {
+        &self.container
+    }
+
+    /// Get the selected timezone.
+    ///
+    /// Returns `None` for the local timezone.
+    fn timezone(&self) -> Option<TimeZone> {
+        let index = (self.dropdown.selected() as usize).checked_sub(1)?;
+        self.timezones.borrow().get(index).cloned()
+    }
+
+    /// Reset this input to the local timezone.
+    ///
+    /// This also reloads the timezones configured for the world clock.
+    fn reset(&self) {
+        let config = Config::load();
+        let timezones: Vec<_> =
+            config.world_clock.timezones.iter().filter_map(|tz| TimeZone::load(tz).ok()).collect();
+
+        let mut options = vec!["Local"];
+        options.extend(timezones.iter().map(TimeZone::name));
+        let model = StringList::new(&options);
+
+        // Only show the selection with configured timezones.
+        self.container.set_visible(!timezones.is_empty());
+
+        *self.timezones.borrow_mut() = timezones;
+        self.dropdown.set_model(Some(&model));
+        self.dropdown.set_selected(0);
+    }
+}
+
+/// Input for following local wall-clock time across timezone changes.
+#[derive(Clone)]
+struct FloatingInput {
+    container: gtk4::Box,
+    switch: Switch,
+}
+
+impl FloatingInput {
+    fn new() -> Self {
+        let container = gtk4::Box::new(Orientation::Horizontal, 10);
+        container.set_margin_top(25);
+
+        let label = Label::new(Some("Follow local time"));
+        label.set_halign(Align::Start);
+        label.set_hexpand(true);
+        container.append(&label);
+
+        let switch = Switch::new();
+        container.append(&switch);
+
+        Self { container, switch }
+    }
+
+    /// Get the GTK widget.
+    fn widget(&self) -> &gtk4::Box {
+        &self.container
+    }
+
+    /// Check if the alarm should follow the local wall-clock time.
+    fn floating(&self) -> bool {
+        self.container.is_visible() && self.switch.is_active()
+    }
+
+    /// Reset this input to its defaults.
+    fn reset(&self) {
+        self.container.set_visible(true);
+        self.switch.set_active(false);
+    }
+}
+
+/// Input for choosing a reminder chime over a wake-up alarm.
+#[derive(Clone)]
+struct ReminderInput {
+    container: gtk4::Box,
+    switch: Switch,
+}
+
+impl ReminderInput {
+    fn new() -> Self {
+        let container = gtk4::Box::new(Orientation::Horizontal, 10);
+        container.set_margin_top(25);
+
+        let label = Label::new(Some("Reminder only"));
+        label.set_halign(Align::Start);
+        label.set_hexpand(true);
+        container.append(&label);
+
+        let switch = Switch::new();
+        container.append(&switch);
+
+        Self { container, switch }
+    }
+
+    /// Get the GTK widget.
+    fn widget(&self) -> &gtk4::Box {
+        &self.container
+    }
+
+    /// Get the selected alarm kind.
+    fn kind(&self) -> AlarmKind {
+        if self.switch.is_active() { AlarmKind::Reminder } else { AlarmKind::WakeUp }
+    }
+
+    /// Reset this input to its defaults.
+    fn reset(&self) {
+        self.switch.set_active(false);
+    }
+}
+
 /// Alarm time selection input.
 #[derive(Clone)]
 struct TimeInput {
+    timezone: Rc<RefCell<Option<TimeZone>>>,
     container: gtk4::Box,
     hours: ScrolledWindow,
     minutes: ScrolledWindow,
+    remaining_label: Label,
 }
 
 impl TimeInput {
@@ -231,24 +427,20 @@ impl TimeInput {
         remaining_label.set_margin_bottom(10);
         container.append(&remaining_label);
 
+        let input =
+            Self { container, hours, minutes, remaining_label, timezone: Default::default() };
+
         // Update label when time is changed.
-        let minutes_remaining_label = remaining_label.clone();
-        let hours_adjustment = hours.vadjustment();
-        minutes.vadjustment().connect_value_changed(move |minutes| {
-            let minute = Self::scroll_value(minutes);
-            let hour = Self::scroll_value(&hours_adjustment);
-            let remaining_text = Self::remaining_text(hour, minute);
-            minutes_remaining_label.set_label(&remaining_text);
+        let minutes_input = input.clone();
+        input.minutes.vadjustment().connect_value_changed(move |_| {
+            minutes_input.update_remaining_label();
         });
-        let minutes_adjustment = minutes.vadjustment();
-        hours.vadjustment().connect_value_changed(move |hours| {
-            let minute = Self::scroll_value(&minutes_adjustment);
-            let hour = Self::scroll_value(hours);
-            let remaining_text = Self::remaining_text(hour, minute);
-            remaining_label.set_label(&remaining_text);
+        let hours_input = input.clone();
+        input.hours.vadjustment().connect_value_changed(move |_| {
+            hours_input.update_remaining_label();
         });
 
-        Self { container, hours, minutes }
+        input
     }
 
     /// Get the GTK widget.
@@ -301,13 +493,24 @@ impl TimeInput {
 
     /// Get the selected minute.
     fn unix_time(&self) -> i64 {
-        // Translate scrolling position to time.
-        let minute = Self::scroll_value(&self.minutes.vadjustment());
-        let hour = Self::scroll_value(&self.hours.vadjustment());
-        let alarm_time = Self::alarm_time(hour, minute);
+        (self.alarm_time() - OffsetDateTime::UNIX_EPOCH).whole_seconds()
+    }
+
+    /// Create an alarm at the selected wall-clock time in the system's
+    /// timezone.
+    fn floating_alarm(&self, id: &str, ring_seconds: u32) -> Result<Alarm, rezz::Error> {
+        let timezone = TimeZone::local()?;
+        let local_time = timezone.to_local(self.alarm_time());
+        let local_time = PrimitiveDateTime::new(local_time.date(), local_time.time());
+        Ok(Alarm::floating(id, local_time, ring_seconds, &timezone))
+    }
 
-        // Convert time to unix time.
-        (alarm_time - OffsetDateTime::UNIX_EPOCH).whole_seconds()
+    /// Set the timezone of the selected time.
+    ///
+    /// Uses the local timezone if `timezone` is `None`.
+    fn set_timezone(&self, timezone: Option<TimeZone>) {
+        *self.timezone.borrow_mut() = timezone;
+        self.update_remaining_label();
     }
 
     /// Reset this input to its defaults.
@@ -331,11 +534,25 @@ impl TimeInput {
         (adjustment.value() / TIME_LABEL_HEIGHT as f64).round() as u8
     }
 
-    /// Get the alarm time from an hour and minute.
-    fn alarm_time(hour: u8, minute: u8) -> OffsetDateTime {
+    /// Get the alarm time from the selected hour and minute.
+    fn alarm_time(&self) -> OffsetDateTime {
+        // Translate scrolling position to time.
+        let minute = Self::scroll_value(&self.minutes.vadjustment());
+        let hour = Self::scroll_value(&self.hours.vadjustment());
         let time = Time::from_hms(hour, minute, 0).unwrap();
 
-        // Get next occurrence of the specified time.
+        // Get next occurrence of the specified time in the selected timezone.
+        let timezone = self.timezone.borrow().clone().or_else(|| TimeZone::local().ok());
+        if let Some(timezone) = timezone {
+            let now = timezone.to_local(OffsetDateTime::now_utc());
+            let mut date = now.date();
+            if time < now.time() {
+                date += Duration::days(1);
+            }
+            return timezone.resolve(PrimitiveDateTime::new(date, time));
+        }
+
+        // Get next occurrence of the specified time without timezone rules.
         let mut date_time =
             OffsetDateTime::now_local().unwrap_or_else(|_| OffsetDateTime::now_utc());
         if time < date_time.time() {
@@ -346,11 +563,16 @@ impl TimeInput {
         date_time
     }
 
+    /// Update the "remaining time until alarm" label.
+    fn update_remaining_label(&self) {
+        self.remaining_label.set_label(&self.remaining_text());
+    }
+
     /// Get the text for the "remaining time until alarm" label.
-    fn remaining_text(hour: u8, minute: u8) -> String {
+    fn remaining_text(&self) -> String {
         // Get current and alarm time.
-        let now = OffsetDateTime::now_local().unwrap_or_else(|_| OffsetDateTime::now_utc());
-        let alarm_time = Self::alarm_time(hour, minute);
+        let now = OffsetDateTime::now_utc();
+        let alarm_time = self.alarm_time();
 
         // Get hours/minutes until alarm.
         let delta = alarm_time - now;
@@ -1,18 +1,23 @@
 //! UI for an actively ringing alarm.
 
 use std::cell::Cell;
+use std::rc::Rc;
 use std::time::Duration as StdDuration;
 
-use alarm::Alarms;
-use alarm::audio::AlarmSound;
+use alarm::notify::{self, Action, RingNotification};
+use alarm::output::AlarmOutput;
+use alarm::{Alarms, Timers};
 use gtk4::glib::MainContext;
 use gtk4::pango::WrapMode;
 use gtk4::prelude::*;
 use gtk4::{Align, Button, Label, Orientation};
-use rezz::Alarm;
+use rezz::{Alarm, Timer};
 use time::{Duration, OffsetDateTime, UtcOffset};
+use tokio::sync::Notify;
 
+use crate::config::Config;
 use crate::navigation::{Navigator, Page};
+use crate::timer::format_countdown;
 
 pub struct RingingAlarmPage {
     navigator: Navigator,
@@ -57,7 +62,7 @@ impl RingingAlarmPage {
     }
 
     /// Ring the specified alarm.
-    pub async fn ring(&mut self, alarm: Alarm) {
+    pub async fn ring(&mut self, alarm: Alarm, duration: StdDuration) {
         // Get hour and minute from the alarm.
         let time = OffsetDateTime::UNIX_EPOCH + Duration::seconds(alarm.unix_time);
         let local_time =
@@ -65,18 +70,64 @@ impl RingingAlarmPage {
         let hour = local_time.time().hour();
         let minute = local_time.time().minute();
 
+        let time = format!("{hour:0>2}:{minute:0>2}");
+        let name = alarm.id.clone();
+        let snooze = Some(alarm.clone());
+        self.start_ringing(&name, &time, duration, snooze, move || {
+            MainContext::default().spawn_local(async move {
+                if let Err(err) = Alarms.dismiss(&alarm).await {
+                    eprintln!("Could not remove alarm: {err}");
+                }
+            });
+        })
+        .await;
+    }
+
+    /// Ring the specified timer.
+    pub async fn ring_timer(&mut self, timer: Timer, duration: StdDuration) {
+        let time = format_countdown(StdDuration::from_millis(timer.duration_ms));
+        let name = timer.id.clone();
+        self.start_ringing(&name, &time, duration, None, move || {
+            MainContext::default().spawn_local(async {
+                let _ = Timers.remove(timer.id).await;
+            });
+        })
+        .await;
+    }
+
+    /// Show the ringing page and start playing the alarm sound and vibrating.
+    ///
+    /// The `on_stop` callback is run once the alarm is stopped. If a `snooze`
+    /// alarm is passed, it can be snoozed from the desktop notification
+    /// instead.
+    async fn start_ringing<F>(
+        &mut self,
+        name: &str,
+        time: &str,
+        duration: StdDuration,
+        snooze: Option<Alarm>,
+        on_stop: F,
+    ) where
+        F: FnOnce() + 'static,
+    {
         // Update labels.
-        self.name_label.set_label(&alarm.id);
-        self.time_label.set_label(&format!("{hour:0>2}:{minute:0>2}"));
+        self.name_label.set_label(name);
+        self.time_label.set_label(time);
 
         // Start ringing alarm.
-        let sound = match AlarmSound::play() {
-            Ok(sound) => sound,
-            Err(err) => {
-                crate::show_error(err.to_string());
-                return;
-            },
-        };
+        //
+        // Keep going without any output, since the page and notification might
+        // be the only indication of the alarm.
+        let config = Config::load().output_config();
+        let (output, errors) = AlarmOutput::start(&config).await;
+        if output.is_active() {
+            for err in errors {
+                eprintln!("Could not start alarm output: {err}");
+            }
+        } else {
+            let errors: Vec<_> = errors.iter().map(|err| err.to_string()).collect();
+            crate::show_error(errors.join("\n"));
+        }
 
         // Switch view.
         self.navigator.show(Self::id());
@@ -87,26 +138,65 @@ impl RingingAlarmPage {
         self.container.append(&self.stop_button);
 
         // Add click listener for stopping the alarm.
-        let button_data = Cell::new(Some((alarm.id, sound)));
+        let button_data = Rc::new(Cell::new(Some((on_stop, output))));
+        let stopped = Rc::new(Notify::new());
         let stop_navigator = self.navigator.clone();
+        let stop_data = button_data.clone();
+        let stop_stopped = stopped.clone();
         self.stop_button.connect_clicked(move |_| {
             // Cancel alarm on first button press.
-            if let Some((id, sound)) = button_data.replace(None) {
-                MainContext::default().spawn_local(async {
-                    let _ = Alarms.remove(id).await;
-                });
-                sound.stop();
+            if let Some((on_stop, output)) = stop_data.replace(None) {
+                on_stop();
+                MainContext::default().spawn_local(output.stop());
             }
 
+            stop_stopped.notify_one();
             stop_navigator.pop();
         });
 
-        // Automatically stop alarm after `ring_seconds` elapsed.
+        // Allow stopping or snoozing the alarm through a desktop notification.
+        let actions = match snooze {
+            Some(_) => vec![Action::Stop, Action::Snooze],
+            None => vec![Action::Stop],
+        };
+        let (name, time) = (name.to_owned(), time.to_owned());
+        let stop_button = self.stop_button.clone();
+        MainContext::default().spawn_local(async move {
+            let mut notification = match RingNotification::show(&name, &time, &actions).await {
+                Ok(notification) => notification,
+                Err(err) => {
+                    eprintln!("Could not show notification: {err}");
+                    return;
+                },
+            };
+
+            let action = tokio::select! {
+                action = notification.action() => Some(action),
+                _ = stopped.notified() => None,
+            };
+            notification.close().await;
+
+            // Snooze without running the `on_stop` callback.
+            if let (Some(Action::Snooze), Some(alarm)) = (action, snooze) {
+                if let Some((_, output)) = button_data.replace(None) {
+                    output.stop().await;
+                    if let Err(err) = Alarms.snooze(alarm, notify::SNOOZE_DURATION).await {
+                        crate::show_rezz_error(&err);
+                    }
+                }
+            }
+
+            if action.is_some() {
+                stop_button.emit_clicked();
+            }
+        });
+
+        // Automatically stop alarm after the ring duration elapsed.
         //
         // This is spawned in the background to avoid blocking our event loop.
         let stop_button = self.stop_button.clone();
         MainContext::default().spawn_local(async move {
-            tokio::time::sleep(StdDuration::from_secs(alarm.ring_seconds as u64)).await;
+            tokio::time::sleep(duration).await;
             stop_button.emit_clicked();
         });
     }
@@ -0,0 +1,345 @@
+//! UI for the stopwatch.
+
+use std::cell::RefCell;
+use std::rc::Rc;
+use std::time::{Duration, SystemTime};
+
+use gtk4::glib::{self, ControlFlow, SourceId};
+use gtk4::prelude::*;
+use gtk4::{Align, Button, Label, Orientation, ScrolledWindow};
+
+use crate::navigation::{Navigator, Page};
+
+/// Interval between stopwatch label updates.
+const TICK_INTERVAL: Duration = Duration::from_millis(50);
+
+/// Callback for stopwatch state changes.
+type Listener = Box<dyn Fn() -> ControlFlow>;
+
+/// Stopwatch state shared across all windows.
+///
+/// Elapsed time is measured using the realtime clock, so the stopwatch keeps
+/// counting while the system is suspended.
+#[derive(Clone, Default)]
+pub struct Stopwatch {
+    state: Rc<RefCell<StopwatchState>>,
+    listeners: Rc<RefCell<Vec<Listener>>>,
+}
+
+impl Stopwatch {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Get the total elapsed time.
+    pub fn elapsed(&self) -> Duration {
+        self.state.borrow().elapsed()
+    }
+
+    /// Get the total elapsed time at each recorded lap.
+    pub fn laps(&self) -> Vec<Duration> {
+        self.state.borrow().laps.clone()
+    }
+
+    /// Check if the stopwatch is currently running.
+    pub fn is_running(&self) -> bool {
+        self.state.borrow().started.is_some()
+    }
+
+    /// Start or continue counting.
+    pub fn start(&self) {
+        {
+            let mut state = self.state.borrow_mut();
+            if state.started.is_none() {
+                state.started = Some(SystemTime::now());
+            }
+        }
+        self.changed();
+    }
+
+    /// Stop counting, preserving the elapsed time.
+    pub fn stop(&self) {
+        {
+            let mut state = self.state.borrow_mut();
+            state.accumulated = state.elapsed();
+            state.started = None;
+        }
+        self.changed();
+    }
+
+    /// Record a lap at the current elapsed time.
+    pub fn lap(&self) {
+        {
+            let mut state = self.state.borrow_mut();
+            let elapsed = state.elapsed();
+            state.laps.push(elapsed);
+        }
+        self.changed();
+    }
+
+    /// Stop the stopwatch and clear all laps.
+    pub fn reset(&self) {
+        *self.state.borrow_mut() = StopwatchState::default();
+        self.changed();
+    }
+
+    /// Register a callback for stopwatch state changes.
+    ///
+    /// The callback is removed once it returns [`ControlFlow::Break`].
+    pub fn connect_changed<F: Fn() -> ControlFlow + 'static>(&self, f: F) {
+        self.listeners.borrow_mut().push(Box::new(f));
+    }
+
+    /// Notify all listeners about a state change.
+    fn changed(&self) {
+        self.listeners.borrow_mut().retain(|listener| listener().is_continue());
+    }
+}
+
+/// Mutable state of a [`Stopwatch`].
+#[derive(Default)]
+struct StopwatchState {
+    /// Realtime clock at the last start, if running.
+    started: Option<SystemTime>,
+    /// Elapsed time before the last start.
+    accumulated: Duration,
+    laps: Vec<Duration>,
+}
+
+impl StopwatchState {
+    /// Get the total elapsed time.
+    fn elapsed(&self) -> Duration {
+        let running = match self.started {
+            // Ignore backwards clock changes, rather than going negative.
+            Some(started) => SystemTime::now().duration_since(started).unwrap_or_default(),
+            None => Duration::ZERO,
+        };
+        self.accumulated + running
+    }
+}
+
+/// UI for the stopwatch.
+pub struct StopwatchPage {
+    container: gtk4::Box,
+}
+
+impl StopwatchPage {
+    pub fn new(navigator: Navigator, stopwatch: Stopwatch) -> Self {
+        let container = gtk4::Box::new(Orientation::Vertical, 0);
+        container.set_valign(Align::End);
+        container.set_margin_top(25);
+        container.set_margin_end(25);
+        container.set_margin_bottom(25);
+        container.set_margin_start(25);
+
+        // Create lap list.
+        let laps = gtk4::Box::new(Orientation::Vertical, 0);
+        let scroll = ScrolledWindow::new();
+        scroll.set_propagate_natural_height(true);
+        scroll.set_child(Some(&laps));
+        container.append(&scroll);
+
+        // Add label for elapsed time.
+        let time_label = Label::new(None);
+        time_label.add_css_class("stopwatch-time");
+        time_label.set_margin_top(25);
+        time_label.set_margin_bottom(25);
+        container.append(&time_label);
+
+        // Add control buttons.
+        let back_button = Button::with_label("Back");
+        let lap_button = Button::new();
+        lap_button.set_halign(Align::Center);
+        lap_button.set_hexpand(true);
+        let start_button = Button::new();
+
+        let button_box = gtk4::Box::new(Orientation::Horizontal, 0);
+        button_box.append(&back_button);
+        button_box.append(&lap_button);
+        button_box.append(&start_button);
+        container.append(&button_box);
+
+        let view = StopwatchView {
+            lap_button: lap_button.clone(),
+            start_button: start_button.clone(),
+            time_label,
+            laps,
+            rendered_laps: Default::default(),
+        };
+        view.update(&stopwatch);
+
+        back_button.connect_clicked(move |_| navigator.pop());
+
+        // Start or stop the stopwatch.
+        let start_stopwatch = stopwatch.clone();
+        start_button.connect_clicked(move |_| {
+            if start_stopwatch.is_running() {
+                start_stopwatch.stop();
+            } else {
+                start_stopwatch.start();
+            }
+        });
+
+        // Record a lap while running, or reset while stopped.
+        let lap_stopwatch = stopwatch.clone();
+        lap_button.connect_clicked(move |_| {
+            if lap_stopwatch.is_running() {
+                lap_stopwatch.lap();
+            } else {
+                lap_stopwatch.reset();
+            }
+        });
+
+        let ticker = Ticker { view, stopwatch: stopwatch.clone(), source: Default::default() };
+
+        // Only tick while the page is visible.
+        let map_ticker = ticker.clone();
+        container.connect_map(move |_| map_ticker.sync(true));
+        let unmap_ticker = ticker.clone();
+        container.connect_unmap(move |_| unmap_ticker.sync(false));
+
+        // Update the view on changes, since other windows share the stopwatch.
+        let weak_container = container.downgrade();
+        stopwatch.connect_changed(move || {
+            // Stop updates once the window was closed.
+            let container = match weak_container.upgrade() {
+                Some(container) => container,
+                None => return ControlFlow::Break,
+            };
+
+            ticker.sync(container.is_mapped());
+
+            ControlFlow::Continue
+        });
+
+        Self { container }
+    }
+}
+
+/// Periodic view updates of a running stopwatch.
+#[derive(Clone)]
+struct Ticker {
+    source: Rc<RefCell<Option<SourceId>>>,
+    stopwatch: Stopwatch,
+    view: StopwatchView,
+}
+
+impl Ticker {
+    /// Update the view and only keep ticking while running and visible.
+    fn sync(&self, visible: bool) {
+        self.view.update(&self.stopwatch);
+
+        let active = visible && self.stopwatch.is_running();
+        let mut source = self.source.borrow_mut();
+        match source.take() {
+            Some(tick) if !active => tick.remove(),
+            None if active => {
+                let (view, stopwatch) = (self.view.clone(), self.stopwatch.clone());
+                *source = Some(glib::timeout_add_local(TICK_INTERVAL, move || {
+                    view.update(&stopwatch);
+                    ControlFlow::Continue
+                }));
+            },
+            tick => *source = tick,
+        }
+    }
+}
+
+impl Page<gtk4::Box> for StopwatchPage {
+    fn id() -> &'static str {
+        "stopwatch"
+    }
+
+    fn widget(&self) -> &gtk4::Box {
+        &self.container
+    }
+}
+
+/// Widgets reflecting the stopwatch state.
+#[derive(Clone)]
+struct StopwatchView {
+    rendered_laps: Rc<RefCell<Vec<Duration>>>,
+    start_button: Button,
+    lap_button: Button,
+    time_label: Label,
+    laps: gtk4::Box,
+}
+
+impl StopwatchView {
+    /// Update all widgets to the current stopwatch state.
+    fn update(&self, stopwatch: &Stopwatch) {
+        self.time_label.set_label(&format_elapsed(stopwatch.elapsed()));
+
+        // Update button labels.
+        if stopwatch.is_running() {
+            self.start_button.set_label("Stop");
+            self.lap_button.set_label("Lap");
+        } else {
+            self.start_button.set_label("Start");
+            self.lap_button.set_label("Reset");
+        }
+
+        // Rebuild the lap list only if it changed.
+        let laps = stopwatch.laps();
+        let mut rendered_laps = self.rendered_laps.borrow_mut();
+        if *rendered_laps == laps {
+            return;
+        }
+
+        while let Some(child) = self.laps.first_child() {
+            self.laps.remove(&child);
+        }
+
+        // Show the most recent lap first.
+        //
+        // Laps can be shorter than their predecessor after the system clock
+        // was moved backwards, so their duration is clamped to zero.
+        let mut previous = Duration::ZERO;
+        let mut rows = Vec::with_capacity(laps.len());
+        for (i, lap) in laps.iter().enumerate() {
+            rows.push(Self::lap_components(i + 1, lap.saturating_sub(previous), *lap));
+            previous = *lap;
+        }
+        for row in rows.iter().rev() {
+            self.laps.append(row);
+        }
+
+        *rendered_laps = laps;
+    }
+
+    /// Get the GTK components for a lap.
+    fn lap_components(number: usize, split: Duration, total: Duration) -> gtk4::Box {
+        let container = gtk4::Box::new(Orientation::Horizontal, 0);
+        container.add_css_class("stopwatch-lap");
+        container.set_margin_top(5);
+        container.set_margin_bottom(5);
+
+        let number_label = Label::new(Some(&format!("Lap {number}")));
+        number_label.set_halign(Align::Start);
+        number_label.set_hexpand(true);
+        container.append(&number_label);
+
+        let split_label = Label::new(Some(&format_elapsed(split)));
+        split_label.set_hexpand(true);
+        container.append(&split_label);
+
+        let total_label = Label::new(Some(&format_elapsed(total)));
+        total_label.set_halign(Align::End);
+        container.append(&total_label);
+
+        container
+    }
+}
+
+/// Format elapsed time as `MM:SS.cc`, with hours once necessary.
+fn format_elapsed(elapsed: Duration) -> String {
+    let centis = elapsed.as_millis() / 10;
+    let seconds = centis / 100;
+    let (hours, minutes) = (seconds / 3600, seconds / 60 % 60);
+
+    if hours > 0 {
+        format!("{hours}:{minutes:0>2}:{:0>2}.{:0>2}", seconds % 60, centis % 100)
+    } else {
+        format!("{minutes:0>2}:{:0>2}.{:0>2}", seconds % 60, centis % 100)
+    }
+}
@@ -0,0 +1,256 @@
+//! UI for countdown timers.
+
+use std::cell::RefCell;
+use std::rc::{Rc, Weak};
+use std::time::Duration as StdDuration;
+
+use alarm::Timers;
+use gtk4::glib::{self, ControlFlow, MainContext};
+use gtk4::prelude::*;
+use gtk4::{Align, Button, Label, Orientation, ScrolledWindow, SpinButton};
+use rezz::Timer;
+use uuid::Uuid;
+
+use crate::navigation::{Navigator, Page};
+use crate::new_alarm::RingDurationInput;
+
+/// UI for starting and managing countdown timers.
+pub struct TimerPage {
+    rows: Rc<RefCell<Vec<TimerRow>>>,
+    timers: ScrolledWindow,
+    container: gtk4::Box,
+}
+
+impl TimerPage {
+    pub fn new(navigator: Navigator) -> Self {
+        let container = gtk4::Box::new(Orientation::Vertical, 0);
+        container.set_valign(Align::End);
+        container.set_margin_top(25);
+        container.set_margin_end(25);
+        container.set_margin_bottom(25);
+        container.set_margin_start(25);
+
+        // Create timers container.
+        let timers = ScrolledWindow::new();
+        container.append(&timers);
+
+        let ring_duration_input = RingDurationInput::new();
+        ring_duration_input.widget().set_margin_top(25);
+        container.append(ring_duration_input.widget());
+
+        let duration_input = DurationInput::new();
+        container.append(duration_input.widget());
+
+        // Add back/start buttons.
+        let back_button = Button::with_label("Back");
+        let start_button = Button::with_label("Start");
+        start_button.set_halign(Align::End);
+        start_button.set_hexpand(true);
+
+        let button_box = gtk4::Box::new(Orientation::Horizontal, 0);
+        button_box.append(&back_button);
+        button_box.append(&start_button);
+        container.append(&button_box);
+
+        back_button.connect_clicked(move |_| navigator.pop());
+
+        let start_duration = duration_input.clone();
+        let start_ring_duration = ring_duration_input.clone();
+        start_button.connect_clicked(move |_| {
+            let duration = start_duration.duration();
+            if duration.is_zero() {
+                return;
+            }
+
+            let ring_seconds = start_ring_duration.duration().seconds();
+            let id = Uuid::new_v4().to_string();
+            MainContext::default().spawn(async move {
+                if let Err(err) = Timers.start(id, duration, ring_seconds).await {
+                    crate::show_rezz_error(&err);
+                }
+            });
+        });
+
+        // Periodically update the remaining time of all timers.
+        let rows: Rc<RefCell<Vec<TimerRow>>> = Default::default();
+        let weak_rows = Rc::downgrade(&rows);
+        glib::timeout_add_seconds_local(1, move || Self::refresh(&weak_rows));
+
+        Self { rows, timers, container }
+    }
+
+    /// Update the view with new timers.
+    pub fn update(&mut self, timers: &[Timer]) {
+        // Create new timers container.
+        let container = gtk4::Box::new(Orientation::Vertical, 0);
+        let rows: Vec<_> = timers.iter().map(TimerRow::new).collect();
+        for row in &rows {
+            container.append(&row.container);
+        }
+        *self.rows.borrow_mut() = rows;
+
+        // Create scroll box.
+        let scroll = ScrolledWindow::new();
+        scroll.set_propagate_natural_height(true);
+        scroll.set_child(Some(&container));
+
+        // Swap containers.
+        self.container.remove(&self.timers);
+        self.container.prepend(&scroll);
+        self.timers = scroll;
+    }
+
+    /// Update the remaining time labels.
+    ///
+    /// Stops the periodic updates once the page was dropped.
+    fn refresh(rows: &Weak<RefCell<Vec<TimerRow>>>) -> ControlFlow {
+        let rows = match rows.upgrade() {
+            Some(rows) => rows,
+            None => return ControlFlow::Break,
+        };
+
+        for row in rows.borrow().iter() {
+            row.remaining_label.set_label(&format_countdown(row.timer.remaining()));
+        }
+
+        ControlFlow::Continue
+    }
+}
+
+impl Page<gtk4::Box> for TimerPage {
+    fn id() -> &'static str {
+        "timer"
+    }
+
+    fn widget(&self) -> &gtk4::Box {
+        &self.container
+    }
+}
+
+/// GTK components for a single timer.
+struct TimerRow {
+    container: gtk4::Box,
+    remaining_label: Label,
+    timer: Timer,
+}
+
+impl TimerRow {
+    fn new(timer: &Timer) -> Self {
+        let container = gtk4::Box::new(Orientation::Horizontal, 0);
+        container.set_margin_start(25);
+        container.set_margin_top(10);
+        container.set_margin_end(25);
+        container.set_margin_bottom(10);
+
+        // Create vertical container to show total duration below remaining time.
+        let label_container = gtk4::Box::new(Orientation::Vertical, 0);
+        label_container.set_hexpand(true);
+        container.append(&label_container);
+
+        // Add timer's remaining time.
+        let remaining_label = Label::new(Some(&format_countdown(timer.remaining())));
+        remaining_label.add_css_class("overview-alarm-time");
+        remaining_label.set_halign(Align::Start);
+        label_container.append(&remaining_label);
+
+        // Add timer's total duration.
+        let duration = format_countdown(StdDuration::from_millis(timer.duration_ms));
+        let duration_label = Label::new(Some(&duration));
+        duration_label.add_css_class("overview-alarm-date");
+        duration_label.set_halign(Align::Start);
+        label_container.append(&duration_label);
+
+        // Add button to pause or resume the timer.
+        let icon = if timer.paused { "media-playback-start" } else { "media-playback-pause" };
+        let pause_button = Button::from_icon_name(icon);
+        pause_button.add_css_class("overview-alarm-button");
+        container.append(&pause_button);
+
+        let id = timer.id.clone();
+        let paused = timer.paused;
+        pause_button.connect_clicked(move |_| {
+            let id = id.clone();
+            MainContext::default().spawn(async move {
+                let result = if paused { Timers.resume(id).await } else { Timers.pause(id).await };
+                if let Err(err) = result {
+                    crate::show_rezz_error(&err);
+                }
+            });
+        });
+
+        // Add button to dismiss timer.
+        let remove_button = Button::from_icon_name("edit-delete");
+        remove_button.add_css_class("overview-alarm-button");
+        container.append(&remove_button);
+
+        let id = timer.id.clone();
+        remove_button.connect_clicked(move |_| {
+            let id = id.clone();
+            MainContext::default().spawn(async move {
+                if let Err(err) = Timers.remove(id).await {
+                    crate::show_rezz_error(&err);
+                }
+            });
+        });
+
+        Self { container, remaining_label, timer: timer.clone() }
+    }
+}
+
+/// Timer duration input.
+#[derive(Clone)]
+struct DurationInput {
+    container: gtk4::Box,
+    hours: SpinButton,
+    minutes: SpinButton,
+    seconds: SpinButton,
+}
+
+impl DurationInput {
+    fn new() -> Self {
+        let container = gtk4::Box::new(Orientation::Horizontal, 10);
+        container.set_halign(Align::Center);
+        container.set_margin_top(25);
+        container.set_margin_bottom(25);
+        container.add_css_class("time-box");
+
+        let hours = SpinButton::with_range(0., 99., 1.);
+        hours.set_orientation(Orientation::Vertical);
+        container.append(&hours);
+        container.append(&Label::new(Some(":")));
+
+        let minutes = SpinButton::with_range(0., 59., 1.);
+        minutes.set_orientation(Orientation::Vertical);
+        container.append(&minutes);
+        container.append(&Label::new(Some(":")));
+
+        let seconds = SpinButton::with_range(0., 59., 1.);
+        seconds.set_orientation(Orientation::Vertical);
+        container.append(&seconds);
+
+        // Default to a five minute timer.
+        minutes.set_value(5.);
+
+        Self { container, hours, minutes, seconds }
+    }
+
+    /// Get the GTK widget.
+    fn widget(&self) -> &gtk4::Box {
+        &self.container
+    }
+
+    /// Get the selected duration.
+    fn duration(&self) -> StdDuration {
+        let hours = self.hours.value_as_int() as u64;
+        let minutes = self.minutes.value_as_int() as u64;
+        let seconds = self.seconds.value_as_int() as u64;
+        StdDuration::from_secs(hours * 60 * 60 + minutes * 60 + seconds)
+    }
+}
+
+/// Format a countdown duration as `HH:MM:SS`.
+pub fn format_countdown(duration: StdDuration) -> String {
+    // Round up, to avoid showing zero for running timers.
+    let seconds = duration.as_millis().div_ceil(1000);
+    format!("{:0>2}:{:0>2}:{:0>2}", seconds / 3600, seconds / 60 % 60, seconds % 60)
+}
@@ -0,0 +1,231 @@
+//! UI for clocks in other timezones.
+
+use std::cell::RefCell;
+use std::rc::Rc;
+
+use gtk4::glib::{self, ControlFlow};
+use gtk4::prelude::*;
+use gtk4::{Align, Button, Entry, Label, Orientation, ScrolledWindow};
+use rezz::tz::TimeZone;
+use time::OffsetDateTime;
+use time::macros::format_description;
+
+use crate::config::Config;
+use crate::navigation::{Navigator, Page};
+
+/// UI for clocks in user-configured timezones.
+pub struct WorldClockPage {
+    container: gtk4::Box,
+}
+
+impl WorldClockPage {
+    pub fn new(navigator: Navigator) -> Self {
+        let container = gtk4::Box::new(Orientation::Vertical, 0);
+        container.set_valign(Align::End);
+        container.set_margin_top(25);
+        container.set_margin_end(25);
+        container.set_margin_bottom(25);
+        container.set_margin_start(25);
+
+        // Create clock list.
+        let clocks = ClockList::new();
+        let scroll = ScrolledWindow::new();
+        scroll.set_propagate_natural_height(true);
+        scroll.set_child(Some(&clocks.container));
+        container.append(&scroll);
+
+        // Add input for new timezones.
+        let entry = Entry::new();
+        entry.set_placeholder_text(Some("Timezone, like America/New_York"));
+        entry.set_hexpand(true);
+        let add_button = Button::with_label("Add");
+
+        let input_box = gtk4::Box::new(Orientation::Horizontal, 10);
+        input_box.set_margin_top(25);
+        input_box.set_margin_bottom(25);
+        input_box.append(&entry);
+        input_box.append(&add_button);
+        container.append(&input_box);
+
+        // Add back button.
+        let back_button = Button::with_label("Back");
+        back_button.set_halign(Align::Start);
+        container.append(&back_button);
+
+        back_button.connect_clicked(move |_| navigator.pop());
+
+        // Add timezone on button press or enter.
+        let add_clocks = clocks.clone();
+        let add_entry = entry.clone();
+        add_button.connect_clicked(move |_| {
+            add_clocks.add(add_entry.text().trim());
+            add_entry.set_text("");
+        });
+        entry.connect_activate(move |_| add_button.emit_clicked());
+
+        // Periodically update all clocks.
+        let weak_container = container.downgrade();
+        glib::timeout_add_seconds_local(1, move || {
+            // Stop updates once the window was closed.
+            if weak_container.upgrade().is_none() {
+                return ControlFlow::Break;
+            }
+
+            clocks.refresh();
+
+            ControlFlow::Continue
+        });
+
+        Self { container }
+    }
+}
+
+impl Page<gtk4::Box> for WorldClockPage {
+    fn id() -> &'static str {
+        "world_clock"
+    }
+
+    fn widget(&self) -> &gtk4::Box {
+        &self.container
+    }
+}
+
+/// List of timezone clocks.
+#[derive(Clone)]
+struct ClockList {
+    rows: Rc<RefCell<Vec<ClockRow>>>,
+    container: gtk4::Box,
+}
+
+impl ClockList {
+    fn new() -> Self {
+        let container = gtk4::Box::new(Orientation::Vertical, 0);
+        let list = Self { container, rows: Default::default() };
+        list.reload();
+        list
+    }
+
+    /// Add a timezone to the config.
+    fn add(&self, name: &str) {
+        if let Err(err) = TimeZone::load(name) {
+            crate::show_error(err.to_string());
+            return;
+        }
+
+        let mut config = Config::load();
+        if !config.world_clock.timezones.iter().any(|timezone| timezone == name) {
+            config.world_clock.timezones.push(name.into());
+        }
+        self.save(&config);
+    }
+
+    /// Remove a timezone from the config.
+    fn remove(&self, name: &str) {
+        let mut config = Config::load();
+        config.world_clock.timezones.retain(|timezone| timezone != name);
+        self.save(&config);
+    }
+
+    /// Persist config changes and update the clocks.
+    fn save(&self, config: &Config) {
+        if let Err(err) = config.save() {
+            crate::show_error(err.to_string());
+        }
+        self.reload();
+    }
+
+    /// Recreate all clocks from the config.
+    fn reload(&self) {
+        while let Some(child) = self.container.first_child() {
+            self.container.remove(&child);
+        }
+
+        let config = Config::load();
+        let mut rows = Vec::new();
+        for name in &config.world_clock.timezones {
+            // Skip timezones removed from the system's database.
+            let timezone = match TimeZone::load(name) {
+                Ok(timezone) => timezone,
+                Err(err) => {
+                    eprintln!("Ignoring world clock: {err}");
+                    continue;
+                },
+            };
+
+            let row = ClockRow::new(self, timezone);
+            self.container.append(&row.container);
+            rows.push(row);
+        }
+
+        *self.rows.borrow_mut() = rows;
+        self.refresh();
+    }
+
+    /// Update all clocks to the current time.
+    fn refresh(&self) {
+        let now = OffsetDateTime::now_utc();
+        for row in self.rows.borrow().iter() {
+            row.update(now);
+        }
+    }
+}
+
+/// GTK components for a single timezone clock.
+struct ClockRow {
+    container: gtk4::Box,
+    time_label: Label,
+    date_label: Label,
+    timezone: TimeZone,
+}
+
+impl ClockRow {
+    fn new(list: &ClockList, timezone: TimeZone) -> Self {
+        let container = gtk4::Box::new(Orientation::Horizontal, 0);
+        container.set_margin_top(10);
+        container.set_margin_bottom(10);
+
+        // Create vertical container to show date below timezone name.
+        let name_container = gtk4::Box::new(Orientation::Vertical, 0);
+        name_container.set_hexpand(true);
+        container.append(&name_container);
+
+        let name_label = Label::new(Some(timezone.name()));
+        name_label.set_halign(Align::Start);
+        name_container.append(&name_label);
+
+        let date_label = Label::new(None);
+        date_label.add_css_class("overview-alarm-date");
+        date_label.set_halign(Align::Start);
+        name_container.append(&date_label);
+
+        // Add timezone's current time.
+        let time_label = Label::new(None);
+        time_label.add_css_class("world-clock-time");
+        container.append(&time_label);
+
+        // Add button to remove the clock.
+        let button = Button::from_icon_name("edit-delete");
+        button.add_css_class("overview-alarm-button");
+        container.append(&button);
+
+        let remove_list = list.clone();
+        let name = timezone.name().to_owned();
+        button.connect_clicked(move |_| remove_list.remove(&name));
+
+        Self { container, time_label, date_label, timezone }
+    }
+
+    /// Update the clock's labels.
+    fn update(&self, now: OffsetDateTime) {
+        let local_time = self.timezone.to_local(now);
+
+        let time_format = format_description!("[hour]:[minute]");
+        self.time_label.set_label(&local_time.format(&time_format).unwrap());
+
+        let date_format = format_description!("[weekday repr:short], [year]-[month]-[day]");
+        let offset_format = format_description!("[offset_hour sign:mandatory]:[offset_minute]");
+        let date = local_time.format(&date_format).unwrap();
+        let offset = local_time.format(&offset_format).unwrap();
+        self.date_label.set_label(&format!("{date} (UTC{offset})"));
+    }
+}
@@ -1,28 +1,60 @@
 //! DBus RTC wakeup server.
 
+use std::collections::HashMap;
 use std::error::Error;
 use std::fs::{self, File};
 use std::io::{Error as IoError, ErrorKind as IoErrorKind, Read, Seek, Write};
+use std::os::fd::{AsFd, AsRawFd, RawFd};
 use std::path::Path;
 use std::sync::Arc;
 use std::time::Duration as StdDuration;
+use std::{future, mem, slice};
 
 use futures_util::stream::StreamExt;
-use rezz::Alarm;
+use nix::sys::inotify::{AddWatchFlags, InitFlags, Inotify};
+use rezz::tz::{self, TimeZone};
+use rezz::{Alarm, MAX_UNIX_TIME, MIN_UNIX_TIME, RING_FOREVER, ServiceError, Timer};
+use serde::Deserialize;
 use time::{Duration, OffsetDateTime};
+use tokio::io::Interest;
+use tokio::io::unix::AsyncFd;
 use tokio::sync::{RwLock, watch};
 use tokio::time as tokio_time;
 use tracing::{debug, error, info, warn};
 use zbus::Connection;
 use zbus::connection::Builder;
-use zbus::fdo::Error as ZBusError;
+use zbus::fdo::{DBusProxy, Error as ZBusError};
+use zbus::message::Header;
+use zbus::names::OwnedUniqueName;
 use zbus::zvariant::OwnedFd;
 
 use crate::logind::{ManagerProxy, PrepareForSleepStream};
+use crate::polkit::{self, AuthorityProxy, Subject};
 
 /// Database location.
 const DB_PATH: &str = "/var/lib/rezz/alarms.db";
 
+/// Polkit action for modifying the alarms of other users.
+const MANAGE_OTHERS_ACTION: &str = "org.catacombing.rezz.manage-others";
+
+/// Polkit action for removing the alarms of all users.
+const CLEAR_ALL_ACTION: &str = "org.catacombing.rezz.clear-all";
+
+/// Maximum length of alarm and timer IDs in bytes.
+const MAX_ID_LEN: usize = 256;
+
+/// Maximum number of alarms and timers per user.
+const MAX_ALARMS: usize = 512;
+
+/// Longest supported ring duration, except for [`RING_FOREVER`].
+const MAX_RING_SECONDS: u32 = 24 * 60 * 60;
+
+/// Longest supported timer duration, 365 days.
+const MAX_TIMER_DURATION_MS: u64 = 365 * 24 * 60 * 60 * 1000;
+
+/// Maximum number of exception dates per alarm.
+const MAX_EXCEPTIONS: usize = 1024;
+
 /// Update frequency on systems without logind.
 const MANUAL_UPDATE_INTERVAL: StdDuration = StdDuration::from_secs(60 * 5);
 
@@ -59,6 +91,15 @@ pub async fn launch() {
         },
     };
 
+    // Listen for system timezone changes.
+    let timezone_watcher = match TimeZoneWatcher::new() {
+        Ok(timezone_watcher) => Some(timezone_watcher),
+        Err(err) => {
+            warn!("Running without timezone change notifications: {err}");
+            None
+        },
+    };
+
     // Listen for db changes.
     let mut alarms_changed = rezz.alarms.read().await.subscribe();
 
@@ -72,7 +113,8 @@ pub async fn launch() {
 
                 let object_server = connection.object_server();
                 let iface = object_server.interface::<_, Rezz>("/org/catacombing/rezz").await.unwrap();
-                let _ = rezz.alarms_changed(iface.signal_emitter()).await;
+                let _ = rezz.alarms_invalidate(iface.signal_emitter()).await;
+                let _ = rezz.timers_invalidate(iface.signal_emitter()).await;
             },
             // Update expired alarms.
             _ = wait_alarm => debug!("Alarm expired"),
@@ -86,6 +128,15 @@ pub async fn launch() {
                     rezz.add_logind_inhibitor(&connection).await;
                 }
             }
+            // Handle system timezone changes.
+            _ = await_timezone_change(&timezone_watcher) => debug!("Timezone changed"),
+        }
+
+        // Move floating alarms to their wall-clock time in the current timezone.
+        let moved = rezz.alarms.write().await.update_timezone();
+        if !moved.is_empty() {
+            let _ = rezz.unschedule(&moved).await;
+            rezz.schedule_nearest().await;
         }
 
         // Ensure old alarms are cleaned up.
@@ -94,10 +145,9 @@ pub async fn launch() {
 
         // Update event loop alarm timeout.
         wait_alarm = match alarms.upcoming() {
-            Some(next_alarm) => {
-                let alarm_end = next_alarm.unix_time + next_alarm.ring_seconds as i64;
-                let seconds = alarm_end.saturating_sub(unix_now());
-                tokio_time::sleep(StdDuration::from_secs(seconds as u64))
+            Some(next_ring) => {
+                let millis = next_ring.end_ms().saturating_sub(unix_now() * 1000).max(0);
+                tokio_time::sleep(StdDuration::from_millis(millis as u64))
             },
             None => tokio_time::sleep(INFINITY),
         };
@@ -149,6 +199,16 @@ async fn await_suspend(logind_stream: &mut Option<PrepareForSleepStream>) -> boo
     }
 }
 
+/// Poll the system timezone watcher.
+///
+/// This will never resolve on systems without inotify support.
+async fn await_timezone_change(watcher: &Option<TimeZoneWatcher>) {
+    match watcher {
+        Some(watcher) => watcher.changed().await,
+        None => future::pending().await,
+    }
+}
+
 /// Register logind inhibitor.
 async fn inhibit(
     connection: &Connection,
@@ -207,13 +267,67 @@ impl Rezz {
         };
     }
 
-    /// Ensure the next wakeup is not after the closest alarm.
+    /// Clear the staged RTC alarm if it belongs to a removed alarm or timer.
+    async fn unschedule(&self, removed: &[RingWindow]) -> Result<(), ServiceError> {
+        // Get currently staged RTC alarms.
+        let wakeup = match rezz::get_wakeup() {
+            Ok(Some(wakeup)) => wakeup,
+            Ok(None) => return Ok(()),
+            Err(err) => {
+                let msg = format!("Could not read WKALM: {err}");
+                error!(msg);
+
+                return Err(ServiceError::Rtc(msg));
+            },
+        };
+
+        // Ignore if staged RTC alarm does not match any removed alarm.
+        let matches_wakeup = removed.iter().any(|window| window.wakeup_time() == Some(wakeup));
+        if !matches_wakeup {
+            return Ok(());
+        }
+
+        // Clear the staged RTC alarm.
+        if let Err(err) = rezz::clear_wakeup() {
+            error!("Could not clear WKALM: {err}");
+        }
+
+        // Ensure timely RTC clock updates without logind.
+        self.schedule_nearest().await;
+
+        Ok(())
+    }
+
+    /// Update the RTC wakeup after an alarm was moved.
+    ///
+    /// Takes the alarm's ringing window before it was moved, or `None` if the
+    /// alarm does not exist.
+    async fn reschedule(&self, moved: Option<RingWindow>, id: &str) -> Result<(), ServiceError> {
+        let window = match moved {
+            Some(window) => window,
+            None => {
+                let msg = format!("Cannot update alarm {id:?}: Invalid ID");
+                warn!(msg);
+
+                return Err(ServiceError::AlarmNotFound(id.into()));
+            },
+        };
+
+        self.unschedule(&[window]).await?;
+
+        // Ensure timely RTC clock updates without logind.
+        self.schedule_nearest().await;
+
+        Ok(())
+    }
+
+    /// Ensure the next wakeup is not after the closest alarm or timer.
     async fn schedule_nearest(&self) {
         let alarms = self.alarms.read().await;
 
         // Get nearest alarm.
-        let next_alarm = match alarms.upcoming() {
-            Some(next_alarm) => next_alarm,
+        let next_ring = match alarms.upcoming() {
+            Some(next_ring) => next_ring,
             None => return,
         };
 
@@ -228,7 +342,13 @@ impl Rezz {
 
         // Ignore alarms beyond the scheduled one.
         let current_time = OffsetDateTime::now_utc();
-        let time = OffsetDateTime::UNIX_EPOCH + Duration::seconds(next_alarm.unix_time);
+        let time = match next_ring.wakeup_time() {
+            Some(time) => time,
+            None => {
+                error!("Could not set WKALM: Time {}ms is out of range", next_ring.start_ms);
+                return;
+            },
+        };
         if wakeup.is_some_and(|wakeup| wakeup > current_time && time >= wakeup) {
             return;
         }
@@ -242,10 +362,24 @@ impl Rezz {
 
 #[zbus::interface(name = "org.catacombing.rezz")]
 impl Rezz {
-    async fn add_alarm(&mut self, alarm: Alarm) -> Result<(), ZBusError> {
+    async fn add_alarm(
+        &mut self,
+        mut alarm: Alarm,
+        #[zbus(header)] header: Header<'_>,
+        #[zbus(connection)] connection: &Connection,
+    ) -> Result<(), ServiceError> {
+        alarm.owner = Caller::new(connection, &header).await?.uid;
+        anchor(&mut alarm);
+
         let id = alarm.id.clone();
         let added = {
             let mut alarms = self.alarms.write().await;
+
+            if let Err(err) = alarms.validate(slice::from_ref(&alarm), alarm.owner) {
+                error!("Could not add alarm: {err}");
+                return Err(err);
+            }
+
             alarms.add(alarm)
         };
 
@@ -253,176 +387,1671 @@ impl Rezz {
             let msg = format!("ID {id:?} already exists");
             error!("Could not add alarm: {msg}");
 
-            return Err(ZBusError::InvalidArgs(msg));
+            return Err(ServiceError::AlarmExists(id));
+        }
+
+        // Ensure timely RTC clock updates without logind.
+        self.schedule_nearest().await;
+
+        Ok(())
+    }
+
+    async fn add_alarms(
+        &mut self,
+        mut new_alarms: Vec<Alarm>,
+        #[zbus(header)] header: Header<'_>,
+        #[zbus(connection)] connection: &Connection,
+    ) -> Result<(), ServiceError> {
+        let caller = Caller::new(connection, &header).await?;
+        for alarm in &mut new_alarms {
+            alarm.owner = caller.uid;
+            anchor(alarm);
+        }
+
+        let result = {
+            let mut alarms = self.alarms.write().await;
+
+            if let Err(err) = alarms.validate(&new_alarms, caller.uid) {
+                error!("Could not add alarms: {err}");
+                return Err(err);
+            }
+
+            alarms.add_all(new_alarms)
+        };
+
+        if let Err(id) = result {
+            let msg = format!("ID {id:?} already exists");
+            error!("Could not add alarms: {msg}");
+
+            return Err(ServiceError::AlarmExists(id));
+        }
+
+        // Ensure timely RTC clock updates without logind.
+        self.schedule_nearest().await;
+
+        Ok(())
+    }
+
+    /// Add alarms, replacing existing alarms of the caller with the same IDs.
+    ///
+    /// Either all alarms are replaced or added, or none of them are.
+    async fn replace_alarms(
+        &mut self,
+        mut new_alarms: Vec<Alarm>,
+        #[zbus(header)] header: Header<'_>,
+        #[zbus(connection)] connection: &Connection,
+    ) -> Result<(), ServiceError> {
+        let caller = Caller::new(connection, &header).await?;
+        for alarm in &mut new_alarms {
+            alarm.owner = caller.uid;
+            anchor(alarm);
         }
 
+        let result = {
+            let mut alarms = self.alarms.write().await;
+
+            if let Err(err) = alarms.validate_replace(&new_alarms, caller.uid) {
+                error!("Could not replace alarms: {err}");
+                return Err(err);
+            }
+
+            alarms.replace_all(new_alarms)
+        };
+
+        let removed = match result {
+            Ok(removed) => removed,
+            Err(id) => {
+                let msg = format!("ID {id:?} is duplicated");
+                error!("Could not replace alarms: {msg}");
+
+                return Err(ServiceError::AlarmExists(id));
+            },
+        };
+
+        let removed: Vec<_> = removed.iter().map(RingWindow::from).collect();
+        self.unschedule(&removed).await?;
+
         // Ensure timely RTC clock updates without logind.
         self.schedule_nearest().await;
 
         Ok(())
     }
 
-    async fn remove_alarm(&self, id: String) -> Result<(), ZBusError> {
+    async fn remove_alarm(
+        &self,
+        id: String,
+        #[zbus(header)] header: Header<'_>,
+        #[zbus(connection)] connection: &Connection,
+    ) -> Result<(), ServiceError> {
+        let caller = Caller::new(connection, &header).await?;
+
+        let ids = slice::from_ref(&id);
+        let access = caller.authorize_alarms(connection, &self.alarms, ids).await?;
+
         let removed = {
             let mut alarms = self.alarms.write().await;
+            access.check_alarms(&alarms, ids)?;
 
             // Remove alarm from internal cache.
-            match alarms.remove(&id) {
+            match alarms.remove(caller.uid, &id) {
                 Some(alarm) => alarm,
                 None => {
                     let msg = format!("Cannot remove alarm {id:?}: Invalid ID");
                     warn!(msg);
 
-                    return Err(ZBusError::InvalidArgs(msg));
+                    return Err(ServiceError::AlarmNotFound(id));
                 },
             }
         };
 
-        // Get currently staged RTC alarms.
-        let wakeup = match rezz::get_wakeup() {
-            Ok(Some(wakeup)) => wakeup,
-            Ok(None) => return Ok(()),
-            Err(err) => {
-                let msg = format!("Could not read WKALM: {err}");
-                error!(msg);
+        self.unschedule(&[RingWindow::from(&removed)]).await
+    }
+
+    async fn remove_alarms(
+        &self,
+        ids: Vec<String>,
+        #[zbus(header)] header: Header<'_>,
+        #[zbus(connection)] connection: &Connection,
+    ) -> Result<(), ServiceError> {
+        let caller = Caller::new(connection, &header).await?;
+
+        let access = caller.authorize_alarms(connection, &self.alarms, &ids).await?;
+
+        let result = {
+            let mut alarms = self.alarms.write().await;
+            access.check_alarms(&alarms, &ids)?;
+
+            alarms.remove_all(caller.uid, &ids)
+        };
+
+        let removed = match result {
+            Ok(removed) => removed,
+            Err(id) => {
+                let msg = format!("Cannot remove alarm {id:?}: Invalid ID");
+                warn!(msg);
 
-                return Err(ZBusError::Failed(msg));
+                return Err(ServiceError::AlarmNotFound(id));
             },
         };
 
-        // Ignore if staged RTC alarm does not match the alarm.
-        let time = OffsetDateTime::UNIX_EPOCH + Duration::seconds(removed.unix_time);
-        if time != wakeup {
-            return Ok(());
-        }
+        let removed: Vec<_> = removed.iter().map(RingWindow::from).collect();
+        self.unschedule(&removed).await
+    }
 
-        // Clear the staged RTC alarm.
-        if let Err(err) = rezz::clear_wakeup() {
-            error!("Could not clear WKALM: {err}");
-        }
+    /// Remove all alarms of the caller.
+    async fn clear_alarms(
+        &self,
+        #[zbus(header)] header: Header<'_>,
+        #[zbus(connection)] connection: &Connection,
+    ) -> Result<(), ServiceError> {
+        let caller = Caller::new(connection, &header).await?;
 
-        // Ensure timely RTC clock updates without logind.
-        self.schedule_nearest().await;
+        let removed = {
+            let mut alarms = self.alarms.write().await;
+            alarms.clear(|alarm| caller.owns(alarm.owner))
+        };
 
-        Ok(())
+        let removed: Vec<_> = removed.iter().map(RingWindow::from).collect();
+        self.unschedule(&removed).await
     }
 
-    #[zbus(property)]
-    async fn alarms(&self) -> Vec<Alarm> {
-        let alarms = self.alarms.read().await;
-        alarms.alarms.clone()
+    async fn set_enabled(
+        &self,
+        id: String,
+        enabled: bool,
+        #[zbus(header)] header: Header<'_>,
+        #[zbus(connection)] connection: &Connection,
+    ) -> Result<(), ServiceError> {
+        let caller = Caller::new(connection, &header).await?;
+
+        let ids = slice::from_ref(&id);
+        let access = caller.authorize_alarms(connection, &self.alarms, ids).await?;
+
+        let updated = {
+            let mut alarms = self.alarms.write().await;
+            access.check_alarms(&alarms, ids)?;
+
+            alarms.set_enabled(caller.uid, &id, enabled)
+        };
+
+        let window = match updated {
+            Some(window) => window,
+            None => {
+                let msg = format!("Cannot update alarm {id:?}: Invalid ID");
+                warn!(msg);
+
+                return Err(ServiceError::AlarmNotFound(id));
+            },
+        };
+
+        if enabled {
+            // Ensure timely RTC clock updates without logind.
+            self.schedule_nearest().await;
+            Ok(())
+        } else {
+            self.unschedule(&[window]).await
+        }
     }
-}
 
-/// Filesystem-based alarm store.
-struct Store {
-    alarms: Vec<Alarm>,
-    onchange_rx: watch::Receiver<()>,
-    onchange_tx: watch::Sender<()>,
-    db: File,
-}
+    /// Replace the local dates on which an alarm does not ring.
+    ///
+    /// Dates are Julian day numbers. Repeating alarms skip their occurrences
+    /// on these dates, while other alarms are disabled if they would ring on
+    /// one of them.
+    async fn set_exceptions(
+        &self,
+        id: String,
+        dates: Vec<i32>,
+        #[zbus(header)] header: Header<'_>,
+        #[zbus(connection)] connection: &Connection,
+    ) -> Result<(), ServiceError> {
+        if let Err(err) = validate_exceptions(dates.len(), &id) {
+            error!("Could not update alarm: {err}");
+            return Err(err);
+        }
 
-impl Store {
-    fn new(db_path: impl AsRef<Path>) -> Result<Self, IoError> {
-        // Create db if necessary and open it.
-        let db_path = db_path.as_ref();
-        let parent = db_path.parent().ok_or_else(|| {
-            let msg = format!("Invalid DB path: {db_path:?}");
-            IoError::new(IoErrorKind::InvalidInput, msg)
-        })?;
-        fs::create_dir_all(parent)?;
-        let mut db =
-            File::options().read(true).write(true).create(true).truncate(false).open(db_path)?;
+        let caller = Caller::new(connection, &header).await?;
 
-        // Parse existing alarms.
-        let mut content = String::new();
-        db.read_to_string(&mut content)?;
-        let alarms = serde_json::from_str(&content).unwrap_or_default();
+        let ids = slice::from_ref(&id);
+        let access = caller.authorize_alarms(connection, &self.alarms, ids).await?;
 
-        // Create update channel.
-        let (onchange_tx, onchange_rx) = watch::channel(());
+        let updated = {
+            let mut alarms = self.alarms.write().await;
+            access.check_alarms(&alarms, ids)?;
 
-        debug!("Alarms in DB {db_path:?}: {alarms:?}");
+            alarms.set_exceptions(caller.uid, &id, dates)
+        };
 
-        Ok(Self { db, alarms, onchange_rx, onchange_tx })
+        self.reschedule(updated, &id).await
     }
 
-    /// Subscribe to changes.
-    fn subscribe(&self) -> watch::Receiver<()> {
-        self.onchange_rx.clone()
+    /// Skip the next occurrence of an alarm.
+    ///
+    /// Repeating alarms move to their following occurrence, while other alarms
+    /// are disabled.
+    async fn skip_next(
+        &self,
+        id: String,
+        #[zbus(header)] header: Header<'_>,
+        #[zbus(connection)] connection: &Connection,
+    ) -> Result<(), ServiceError> {
+        let caller = Caller::new(connection, &header).await?;
+
+        let ids = slice::from_ref(&id);
+        let access = caller.authorize_alarms(connection, &self.alarms, ids).await?;
+
+        let updated = {
+            let mut alarms = self.alarms.write().await;
+            access.check_alarms(&alarms, ids)?;
+
+            if let Some(alarm) = alarms.alarm(caller.uid, &id) {
+                if let Err(err) = validate_exceptions(alarm.exceptions.len() + 1, &id) {
+                    error!("Could not update alarm: {err}");
+                    return Err(err);
+                }
+            }
+
+            alarms.skip_next(caller.uid, &id)
+        };
+
+        self.reschedule(updated, &id).await
     }
 
-    /// Get the next alarm.
-    fn upcoming(&self) -> Option<&Alarm> {
-        self.alarms.iter().min_by_key(|alarm| alarm.unix_time)
+    /// Remove the alarms of all users.
+    ///
+    /// This requires authorization for the `clear-all` polkit action.
+    async fn clear_all_alarms(
+        &self,
+        #[zbus(header)] header: Header<'_>,
+        #[zbus(connection)] connection: &Connection,
+    ) -> Result<(), ServiceError> {
+        let caller = Caller::new(connection, &header).await?;
+        caller.authorize(connection, CLEAR_ALL_ACTION).await?;
+
+        let removed = {
+            let mut alarms = self.alarms.write().await;
+            alarms.clear(|_| true)
+        };
+
+        let removed: Vec<_> = removed.iter().map(RingWindow::from).collect();
+        self.unschedule(&removed).await
     }
 
-    /// Add a new alarm.
+    /// Get the alarms of all users.
     ///
-    /// Returns `true` if the alarm was added and `false` if another alarm with
-    /// the
-    /// ID ID already exists.
-    fn add(&mut self, alarm: Alarm) -> bool {
-        if self.alarms.iter().any(|existing_alarm| existing_alarm.id == alarm.id) {
-            return false;
+    /// This requires authorization for the `manage-others` polkit action.
+    async fn all_alarms(
+        &self,
+        #[zbus(header)] header: Header<'_>,
+        #[zbus(connection)] connection: &Connection,
+    ) -> Result<Vec<Alarm>, ServiceError> {
+        let caller = Caller::new(connection, &header).await?;
+        caller.authorize(connection, MANAGE_OTHERS_ACTION).await?;
+
+        let alarms = self.alarms.read().await;
+        Ok(alarms.alarms.clone())
+    }
+
+    async fn start_timer(
+        &self,
+        id: String,
+        duration_ms: u64,
+        ring_seconds: u32,
+        #[zbus(header)] header: Header<'_>,
+        #[zbus(connection)] connection: &Connection,
+    ) -> Result<(), ServiceError> {
+        if duration_ms > MAX_TIMER_DURATION_MS {
+            let msg = format!(
+                "Duration {duration_ms}ms of timer {id:?} exceeds {MAX_TIMER_DURATION_MS}ms"
+            );
+            error!("Could not start timer: {msg}");
+            return Err(ServiceError::InvalidDuration(msg));
         }
 
-        self.alarms.push(alarm);
+        let mut timer = Timer::new(&id, duration_ms, ring_seconds);
+        timer.owner = Caller::new(connection, &header).await?.uid;
 
-        self.sync();
+        let added = {
+            let mut alarms = self.alarms.write().await;
 
-        true
+            if let Err(err) = alarms.validate_timer(&timer) {
+                error!("Could not start timer: {err}");
+                return Err(err);
+            }
+
+            alarms.add_timer(timer)
+        };
+
+        if !added {
+            let msg = format!("ID {id:?} already exists");
+            error!("Could not start timer: {msg}");
+
+            return Err(ServiceError::TimerExists(id));
+        }
+
+        // Ensure timely RTC clock updates without logind.
+        self.schedule_nearest().await;
+
+        Ok(())
     }
 
-    /// Remove an existing alarm.
-    fn remove(&mut self, id: &str) -> Option<Alarm> {
-        let matching = self.alarms.iter().position(|alarm| alarm.id == id)?;
-        let removed = self.alarms.remove(matching);
+    async fn pause_timer(
+        &self,
+        id: String,
+        #[zbus(header)] header: Header<'_>,
+        #[zbus(connection)] connection: &Connection,
+    ) -> Result<(), ServiceError> {
+        let caller = Caller::new(connection, &header).await?;
 
-        self.sync();
+        let access = caller.authorize_timer(connection, &self.alarms, &id).await?;
 
-        Some(removed)
+        let paused = {
+            let mut alarms = self.alarms.write().await;
+            access.check_timer(&alarms, &id)?;
+
+            alarms.update_timer(caller.uid, &id, Timer::pause)
+        };
+
+        match paused {
+            Some(window) => self.unschedule(window.as_slice()).await,
+            None => {
+                let msg = format!("Cannot pause timer {id:?}: Invalid ID");
+                warn!(msg);
+
+                Err(ServiceError::TimerNotFound(id))
+            },
+        }
     }
 
-    /// Remove all elapsed alarms.
-    ///
-    /// Returns the number of removed elements.
-    fn remove_elapsed(&mut self) -> usize {
-        let old_len = self.alarms.len();
+    async fn resume_timer(
+        &self,
+        id: String,
+        #[zbus(header)] header: Header<'_>,
+        #[zbus(connection)] connection: &Connection,
+    ) -> Result<(), ServiceError> {
+        let caller = Caller::new(connection, &header).await?;
 
-        self.alarms.retain(|alarm| alarm.unix_time + alarm.ring_seconds as i64 > unix_now());
+        let access = caller.authorize_timer(connection, &self.alarms, &id).await?;
 
-        // Update database if entries were deleted.
-        let removed_count = old_len - self.alarms.len();
-        if removed_count > 0 {
-            self.sync();
+        let resumed = {
+            let mut alarms = self.alarms.write().await;
+            access.check_timer(&alarms, &id)?;
+
+            alarms.update_timer(caller.uid, &id, Timer::resume)
+        };
+
+        if resumed.is_none() {
+            let msg = format!("Cannot resume timer {id:?}: Invalid ID");
+            warn!(msg);
+
+            return Err(ServiceError::TimerNotFound(id));
         }
 
-        removed_count
+        // Ensure timely RTC clock updates without logind.
+        self.schedule_nearest().await;
+
+        Ok(())
     }
 
-    /// Write all pending DB changes to the filesystem and signal changes.
-    fn sync(&mut self) {
-        // Signal changes.
-        let _ = self.onchange_tx.send(());
+    async fn remove_timer(
+        &self,
+        id: String,
+        #[zbus(header)] header: Header<'_>,
+        #[zbus(connection)] connection: &Connection,
+    ) -> Result<(), ServiceError> {
+        let caller = Caller::new(connection, &header).await?;
 
-        let json = serde_json::to_string(&self.alarms).unwrap();
+        let access = caller.authorize_timer(connection, &self.alarms, &id).await?;
 
-        // Overwrite the entire file.
-        let result = self
-            .db
-            .set_len(0)
-            .and_then(|_| self.db.rewind())
-            .and_then(|_| self.db.write_all(json.as_bytes()));
+        let removed = {
+            let mut alarms = self.alarms.write().await;
+            access.check_timer(&alarms, &id)?;
 
-        if let Err(err) = result {
-            error!("Failed DB sync: {err}");
-        }
+            // Remove timer from internal cache.
+            match alarms.remove_timer(caller.uid, &id) {
+                Some(timer) => timer,
+                None => {
+                    let msg = format!("Cannot remove timer {id:?}: Invalid ID");
+                    warn!(msg);
+
+                    return Err(ServiceError::TimerNotFound(id));
+                },
+            }
+        };
+
+        self.unschedule(RingWindow::from_timer(&removed).as_slice()).await
     }
-}
+
+    /// Alarms of the caller.
+    #[zbus(property(emits_changed_signal = "invalidates"))]
+    async fn alarms(
+        &self,
+        #[zbus(header)] header: Option<Header<'_>>,
+        #[zbus(connection)] connection: &Connection,
+    ) -> Result<Vec<Alarm>, ZBusError> {
+        let caller = Caller::from_property(connection, header).await?;
+
+        let alarms = self.alarms.read().await;
+        let alarms = alarms.alarms.iter().filter(|alarm| caller.owns(alarm.owner));
+        Ok(alarms.cloned().collect())
+    }
+
+    /// Timers of the caller.
+    #[zbus(property(emits_changed_signal = "invalidates"))]
+    async fn timers(
+        &self,
+        #[zbus(header)] header: Option<Header<'_>>,
+        #[zbus(connection)] connection: &Connection,
+    ) -> Result<Vec<Timer>, ZBusError> {
+        let caller = Caller::from_property(connection, header).await?;
+
+        let alarms = self.alarms.read().await;
+        let timers = alarms.timers.iter().filter(|timer| caller.owns(timer.owner));
+        Ok(timers.cloned().collect())
+    }
+}
+
+/// Credentials of a DBus method caller.
+struct Caller {
+    sender: OwnedUniqueName,
+    uid: u32,
+}
+
+impl Caller {
+    /// Get the credentials of a method call's sender.
+    async fn new(connection: &Connection, header: &Header<'_>) -> Result<Self, ServiceError> {
+        let sender = header
+            .sender()
+            .ok_or_else(|| ServiceError::AccessDenied("Cannot identify caller".into()))?;
+
+        let dbus = DBusProxy::new(connection).await?;
+        let uid = dbus.get_connection_unix_user(sender.clone().into()).await?;
+
+        Ok(Self { uid, sender: sender.to_owned().into() })
+    }
+
+    /// Get the credentials of a property getter's caller.
+    async fn from_property(
+        connection: &Connection,
+        header: Option<Header<'_>>,
+    ) -> Result<Self, ServiceError> {
+        match header {
+            Some(header) => Self::new(connection, &header).await,
+            None => Err(ServiceError::AccessDenied("Cannot identify caller".into())),
+        }
+    }
+
+    /// Check if an alarm or timer belongs to the caller.
+    ///
+    /// Entries without owner belong to root.
+    fn owns(&self, owner: u32) -> bool {
+        owned_by(owner, self.uid)
+    }
+
+    /// Authorize the caller to modify the alarms with the specified IDs.
+    ///
+    /// Polkit might wait for the user to authenticate, so the store is only
+    /// locked while looking up the alarms. Since the alarms can change during
+    /// authorization, the returned access must be verified with
+    /// [`Access::check_alarms`] after locking the store again.
+    ///
+    /// Unknown IDs are accepted, to let the caller report them as invalid.
+    async fn authorize_alarms(
+        &self,
+        connection: &Connection,
+        store: &RwLock<Store>,
+        ids: &[String],
+    ) -> Result<Access, ServiceError> {
+        let foreign = {
+            let store = store.read().await;
+            ids.iter().find_map(|id| {
+                let alarm = store.alarm(self.uid, id)?;
+                (!self.owns(alarm.owner)).then(|| (alarm.owner, id.clone()))
+            })
+        };
+
+        self.authorize_foreign(connection, foreign).await
+    }
+
+    /// Authorize the caller to modify the timer with the specified ID.
+    ///
+    /// See [`Self::authorize_alarms`] for locking the store during
+    /// authorization. The returned access must be verified with
+    /// [`Access::check_timer`].
+    async fn authorize_timer(
+        &self,
+        connection: &Connection,
+        store: &RwLock<Store>,
+        id: &str,
+    ) -> Result<Access, ServiceError> {
+        let foreign = {
+            let store = store.read().await;
+            store.timer(self.uid, id).map(|timer| timer.owner).filter(|owner| !self.owns(*owner))
+        };
+
+        self.authorize_foreign(connection, foreign.map(|owner| (owner, id.into()))).await
+    }
+
+    /// Authorize the caller to modify an entry of another user, if any.
+    async fn authorize_foreign(
+        &self,
+        connection: &Connection,
+        foreign: Option<(u32, String)>,
+    ) -> Result<Access, ServiceError> {
+        let manage_others = match foreign {
+            Some((owner, id)) => {
+                self.check_access(connection, owner, &id).await?;
+                true
+            },
+            None => false,
+        };
+
+        Ok(Access { uid: self.uid, manage_others })
+    }
+
+    /// Ensure the caller may modify an alarm or timer.
+    ///
+    /// Modifying the alarms of other users requires authorization for the
+    /// `manage-others` polkit action.
+    async fn check_access(
+        &self,
+        connection: &Connection,
+        owner: u32,
+        id: &str,
+    ) -> Result<(), ServiceError> {
+        if self.owns(owner) {
+            return Ok(());
+        }
+
+        self.authorize(connection, MANAGE_OTHERS_ACTION).await.map_err(|_| {
+            let msg = format!("UID {} cannot access {id:?} of UID {owner}", self.uid);
+            warn!(msg);
+
+            ServiceError::AccessDenied(msg)
+        })
+    }
+
+    /// Ensure the caller is authorized for a polkit action.
+    ///
+    /// Root is always authorized, without contacting polkit.
+    async fn authorize(&self, connection: &Connection, action: &str) -> Result<(), ServiceError> {
+        if self.uid == 0 {
+            return Ok(());
+        }
+
+        let authority = AuthorityProxy::new(connection).await?;
+        let subject = Subject::system_bus_name(&self.sender);
+        let result = authority
+            .check_authorization(
+                &subject,
+                action,
+                HashMap::new(),
+                polkit::ALLOW_USER_INTERACTION,
+                "",
+            )
+            .await;
+
+        match result {
+            Ok((true, ..)) => Ok(()),
+            Ok(_) => {
+                let msg = format!("UID {} is not authorized for {action}", self.uid);
+                warn!(msg);
+
+                Err(ServiceError::AccessDenied(msg))
+            },
+            Err(err) => {
+                let msg = format!("Could not check authorization for {action}: {err}");
+                error!(msg);
+
+                Err(ServiceError::AccessDenied(msg))
+            },
+        }
+    }
+}
+
+/// Store access authorized for a DBus method caller.
+struct Access {
+    uid: u32,
+    manage_others: bool,
+}
+
+impl Access {
+    /// Ensure the access still covers the alarms with the specified IDs.
+    ///
+    /// The store's lock must be held until the alarms are modified, to prevent
+    /// other callers from replacing them after the check.
+    fn check_alarms(&self, store: &Store, ids: &[String]) -> Result<(), ServiceError> {
+        for id in ids {
+            if let Some(alarm) = store.alarm(self.uid, id) {
+                self.check(alarm.owner, id)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Ensure the access still covers the timer with the specified ID.
+    ///
+    /// The store's lock must be held until the timer is modified, to prevent
+    /// other callers from replacing it after the check.
+    fn check_timer(&self, store: &Store, id: &str) -> Result<(), ServiceError> {
+        match store.timer(self.uid, id) {
+            Some(timer) => self.check(timer.owner, id),
+            None => Ok(()),
+        }
+    }
+
+    /// Ensure the access covers an entry of the specified owner.
+    fn check(&self, owner: u32, id: &str) -> Result<(), ServiceError> {
+        if self.manage_others || owned_by(owner, self.uid) {
+            return Ok(());
+        }
+
+        let msg = format!("UID {} cannot access {id:?} of UID {owner}", self.uid);
+        warn!(msg);
+
+        Err(ServiceError::AccessDenied(msg))
+    }
+}
+
+/// Inotify watcher for the system's local timezone.
+struct TimeZoneWatcher {
+    // Deregister the FD before closing it.
+    fd: AsyncFd<RawFd>,
+    inotify: Inotify,
+}
+
+impl TimeZoneWatcher {
+    fn new() -> Result<Self, Box<dyn Error>> {
+        let localtime = Path::new(tz::LOCALTIME_PATH);
+        let parent = localtime.parent().ok_or("invalid localtime path")?;
+
+        // Watch the parent directory, since the symlink is replaced on change.
+        let inotify = Inotify::init(InitFlags::IN_NONBLOCK | InitFlags::IN_CLOEXEC)?;
+        let flags =
+            AddWatchFlags::IN_CREATE | AddWatchFlags::IN_MOVED_TO | AddWatchFlags::IN_CLOSE_WRITE;
+        inotify.add_watch(parent, flags)?;
+
+        let fd = AsyncFd::with_interest(inotify.as_fd().as_raw_fd(), Interest::READABLE)?;
+
+        Ok(Self { fd, inotify })
+    }
+
+    /// Wait for the next change to the local timezone.
+    async fn changed(&self) {
+        let localtime = Path::new(tz::LOCALTIME_PATH).file_name();
+
+        loop {
+            let mut guard = match self.fd.readable().await {
+                Ok(guard) => guard,
+                Err(err) => {
+                    error!("Could not poll timezone watcher: {err}");
+                    return future::pending().await;
+                },
+            };
+
+            let events = match guard.try_io(|_| Ok(self.inotify.read_events()?)) {
+                Ok(Ok(events)) => events,
+                Ok(Err(err)) => {
+                    error!("Could not read timezone events: {err}");
+                    return future::pending().await;
+                },
+                Err(_would_block) => continue,
+            };
+
+            if events.iter().any(|event| event.name.as_deref() == localtime) {
+                return;
+            }
+        }
+    }
+}
+
+/// Ringing window of an alarm or running timer.
+#[derive(Copy, Clone, Debug)]
+struct RingWindow {
+    /// Ring time in milliseconds since the unix epoch.
+    start_ms: i64,
+    ring_seconds: u32,
+}
+
+impl RingWindow {
+    /// Get the ringing window of a timer, if it is running.
+    fn from_timer(timer: &Timer) -> Option<Self> {
+        let start_ms = timer.end_time_ms()?;
+        Some(Self { start_ms, ring_seconds: timer.ring_seconds })
+    }
+
+    /// End of the ringing window in milliseconds since the unix epoch.
+    fn end_ms(&self) -> i64 {
+        self.start_ms + self.ring_seconds as i64 * 1000
+    }
+
+    /// RTC wakeup time, truncated to the RTC's second precision.
+    ///
+    /// Returns `None` if the time is outside of the supported date range.
+    fn wakeup_time(&self) -> Option<OffsetDateTime> {
+        OffsetDateTime::UNIX_EPOCH.checked_add(Duration::seconds(self.start_ms.div_euclid(1000)))
+    }
+}
+
+impl From<&Alarm> for RingWindow {
+    fn from(alarm: &Alarm) -> Self {
+        let ring_seconds = (alarm.ring_end() - alarm.unix_time).clamp(0, u32::MAX as i64);
+        Self { start_ms: alarm.unix_time_ms(), ring_seconds: ring_seconds as u32 }
+    }
+}
+
+/// On-disk DB format.
+///
+/// Databases before `1.2.0` only contain the array of alarms.
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum Database {
+    Current {
+        alarms: Vec<Alarm>,
+        #[serde(default)]
+        timers: Vec<Timer>,
+    },
+    Legacy(Vec<Alarm>),
+}
+
+/// Filesystem-based alarm and timer store.
+struct Store {
+    alarms: Vec<Alarm>,
+    timers: Vec<Timer>,
+    onchange_rx: watch::Receiver<()>,
+    onchange_tx: watch::Sender<()>,
+    db: File,
+}
+
+impl Store {
+    fn new(db_path: impl AsRef<Path>) -> Result<Self, IoError> {
+        // Create db if necessary and open it.
+        let db_path = db_path.as_ref();
+        let parent = db_path.parent().ok_or_else(|| {
+            let msg = format!("Invalid DB path: {db_path:?}");
+            IoError::new(IoErrorKind::InvalidInput, msg)
+        })?;
+        fs::create_dir_all(parent)?;
+        let mut db =
+            File::options().read(true).write(true).create(true).truncate(false).open(db_path)?;
+
+        // Parse existing alarms and timers.
+        let mut content = String::new();
+        db.read_to_string(&mut content)?;
+        let (alarms, timers) = match serde_json::from_str(&content) {
+            Ok(Database::Current { alarms, timers }) => (alarms, timers),
+            Ok(Database::Legacy(alarms)) => (alarms, Vec::new()),
+            Err(_) => Default::default(),
+        };
+
+        // Create update channel.
+        let (onchange_tx, onchange_rx) = watch::channel(());
+
+        debug!("Alarms in DB {db_path:?}: {alarms:?}");
+        debug!("Timers in DB {db_path:?}: {timers:?}");
+
+        Ok(Self { db, alarms, timers, onchange_rx, onchange_tx })
+    }
+
+    /// Subscribe to changes.
+    fn subscribe(&self) -> watch::Receiver<()> {
+        self.onchange_rx.clone()
+    }
+
+    /// Get the ringing window of the next enabled alarm or running timer.
+    fn upcoming(&self) -> Option<RingWindow> {
+        let alarms = self.alarms.iter().filter(|alarm| alarm.enabled).map(RingWindow::from);
+        let timers = self.timers.iter().filter_map(RingWindow::from_timer);
+        alarms.chain(timers).min_by_key(|window| window.start_ms)
+    }
+
+    /// Get an alarm by its ID.
+    ///
+    /// Since IDs are only unique per user, alarms owned by `uid` take
+    /// precedence over alarms of other users with the same ID.
+    fn alarm(&self, uid: u32, id: &str) -> Option<&Alarm> {
+        self.alarm_index(uid, id).map(|index| &self.alarms[index])
+    }
+
+    /// Get the index of an alarm by its ID.
+    ///
+    /// See [`Self::alarm`] for alarms of multiple users with the same ID.
+    fn alarm_index(&self, uid: u32, id: &str) -> Option<usize> {
+        let mut matching = self.alarms.iter().enumerate().filter(|(_, alarm)| alarm.id == id);
+        let own = matching.clone().find(|(_, alarm)| owned_by(alarm.owner, uid));
+        own.or_else(|| matching.next()).map(|(index, _)| index)
+    }
+
+    /// Get a timer by its ID.
+    ///
+    /// Since IDs are only unique per user, timers owned by `uid` take
+    /// precedence over timers of other users with the same ID.
+    fn timer(&self, uid: u32, id: &str) -> Option<&Timer> {
+        self.timer_index(uid, id).map(|index| &self.timers[index])
+    }
+
+    /// Get the index of a timer by its ID.
+    ///
+    /// See [`Self::timer`] for timers of multiple users with the same ID.
+    fn timer_index(&self, uid: u32, id: &str) -> Option<usize> {
+        let mut matching = self.timers.iter().enumerate().filter(|(_, timer)| timer.id == id);
+        let own = matching.clone().find(|(_, timer)| owned_by(timer.owner, uid));
+        own.or_else(|| matching.next()).map(|(index, _)| index)
+    }
+
+    /// Add a new alarm.
+    ///
+    /// Returns `true` if the alarm was added and `false` if another alarm of
+    /// the same owner with the same ID already exists.
+    fn add(&mut self, alarm: Alarm) -> bool {
+        if self.alarms.iter().any(|existing| same_alarm(existing, &alarm)) {
+            return false;
+        }
+
+        self.alarms.push(alarm);
+
+        self.sync();
+
+        true
+    }
+
+    /// Add multiple new alarms.
+    ///
+    /// Either all alarms are added, or none of them are. Returns the first
+    /// conflicting ID if any alarm's ID exists already for its owner or is
+    /// duplicated.
+    fn add_all(&mut self, alarms: Vec<Alarm>) -> Result<(), String> {
+        for (i, alarm) in alarms.iter().enumerate() {
+            let mut existing = self.alarms.iter().chain(&alarms[..i]);
+            if existing.any(|existing| same_alarm(existing, alarm)) {
+                return Err(alarm.id.clone());
+            }
+        }
+
+        self.alarms.extend(alarms);
+
+        self.sync();
+
+        Ok(())
+    }
+
+    /// Ensure new alarms are within the supported limits.
+    ///
+    /// This checks the alarms' IDs, times and ring durations, and whether
+    /// adding them would exceed the owner's quota.
+    fn validate(&self, alarms: &[Alarm], owner: u32) -> Result<(), ServiceError> {
+        alarms.iter().try_for_each(validate_alarm)?;
+        self.validate_quota(owner, alarms.len())
+    }
+
+    /// Ensure replacement alarms are within the supported limits.
+    ///
+    /// Unlike [`Self::validate`], the owner's alarms which are replaced do not
+    /// count towards their quota.
+    fn validate_replace(&self, alarms: &[Alarm], owner: u32) -> Result<(), ServiceError> {
+        alarms.iter().try_for_each(validate_alarm)?;
+
+        let replaced = self
+            .alarms
+            .iter()
+            .filter(|existing| alarms.iter().any(|alarm| same_alarm(existing, alarm)))
+            .count();
+        self.validate_quota(owner, alarms.len().saturating_sub(replaced))
+    }
+
+    /// Ensure a new timer is within the supported limits.
+    ///
+    /// This checks the timer's ID and end time, and whether adding it would
+    /// exceed the owner's quota.
+    fn validate_timer(&self, timer: &Timer) -> Result<(), ServiceError> {
+        validate_id(&timer.id)?;
+        validate_ring_seconds(timer.ring_seconds, "timer", &timer.id)?;
+
+        if let Some(end_time_ms) = timer.end_time_ms() {
+            validate_time(end_time_ms.div_euclid(1000), "timer", &timer.id)?;
+        }
+
+        self.validate_quota(timer.owner, 1)
+    }
+
+    /// Ensure adding new entries does not exceed the owner's quota.
+    ///
+    /// Alarms and timers share a single quota.
+    fn validate_quota(&self, owner: u32, new: usize) -> Result<(), ServiceError> {
+        let alarms = self.alarms.iter().filter(|alarm| owned_by(alarm.owner, owner)).count();
+        let timers = self.timers.iter().filter(|timer| owned_by(timer.owner, owner)).count();
+        if alarms + timers + new > MAX_ALARMS {
+            return Err(ServiceError::QuotaExceeded(format!(
+                "UID {owner} cannot own more than {MAX_ALARMS} alarms and timers"
+            )));
+        }
+
+        Ok(())
+    }
+
+    /// Add multiple alarms, replacing existing alarms of the same owner with
+    /// the same IDs.
+    ///
+    /// Either all alarms are added, or none of them are. Returns the replaced
+    /// alarms, or the first duplicated ID.
+    fn replace_all(&mut self, alarms: Vec<Alarm>) -> Result<Vec<Alarm>, String> {
+        for (i, alarm) in alarms.iter().enumerate() {
+            if alarms[..i].iter().any(|previous| same_alarm(previous, alarm)) {
+                return Err(alarm.id.clone());
+            }
+        }
+
+        let (removed, kept) = mem::take(&mut self.alarms)
+            .into_iter()
+            .partition(|existing| alarms.iter().any(|alarm| same_alarm(existing, alarm)));
+        self.alarms = kept;
+
+        self.alarms.extend(alarms);
+
+        self.sync();
+
+        Ok(removed)
+    }
+
+    /// Remove an existing alarm.
+    ///
+    /// See [`Self::alarm`] for alarms of multiple users with the same ID.
+    fn remove(&mut self, uid: u32, id: &str) -> Option<Alarm> {
+        let matching = self.alarm_index(uid, id)?;
+        let removed = self.alarms.remove(matching);
+
+        self.sync();
+
+        Some(removed)
+    }
+
+    /// Remove multiple existing alarms.
+    ///
+    /// Either all alarms are removed, or none of them are. Returns the first
+    /// unknown ID if any alarm does not exist.
+    ///
+    /// See [`Self::alarm`] for alarms of multiple users with the same ID.
+    fn remove_all(&mut self, uid: u32, ids: &[String]) -> Result<Vec<Alarm>, String> {
+        let mut indices = Vec::with_capacity(ids.len());
+        for id in ids {
+            match self.alarm_index(uid, id) {
+                Some(index) => indices.push(index),
+                None => return Err(id.clone()),
+            }
+        }
+
+        let (removed, alarms): (Vec<_>, Vec<_>) =
+            self.alarms.drain(..).enumerate().partition(|(index, _)| indices.contains(index));
+        self.alarms = alarms.into_iter().map(|(_, alarm)| alarm).collect();
+        let removed = removed.into_iter().map(|(_, alarm)| alarm).collect();
+
+        self.sync();
+
+        Ok(removed)
+    }
+
+    /// Remove all alarms matching a predicate.
+    fn clear(&mut self, f: impl Fn(&Alarm) -> bool) -> Vec<Alarm> {
+        let (removed, alarms) = mem::take(&mut self.alarms).into_iter().partition(f);
+        self.alarms = alarms;
+
+        self.sync();
+
+        removed
+    }
+
+    /// Enable or disable an existing alarm.
+    ///
+    /// Returns the alarm's ringing window, or `None` if no alarm with this ID
+    /// exists.
+    ///
+    /// See [`Self::alarm`] for alarms of multiple users with the same ID.
+    fn set_enabled(&mut self, uid: u32, id: &str, enabled: bool) -> Option<RingWindow> {
+        let index = self.alarm_index(uid, id)?;
+        let alarm = &mut self.alarms[index];
+        let window = RingWindow::from(&*alarm);
+
+        if alarm.enabled != enabled {
+            alarm.enabled = enabled;
+
+            // Move repeating alarms past occurrences missed while disabled.
+            if enabled && alarm.repeats() {
+                alarm.reschedule(unix_now(), &local_timezone());
+            }
+
+            self.sync();
+        }
+
+        Some(window)
+    }
+
+    /// Replace the exception dates of an existing alarm.
+    ///
+    /// Returns the alarm's ringing window before the change, or `None` if no
+    /// alarm with this ID exists.
+    ///
+    /// See [`Self::alarm`] for alarms of multiple users with the same ID.
+    fn set_exceptions(&mut self, uid: u32, id: &str, exceptions: Vec<i32>) -> Option<RingWindow> {
+        let index = self.alarm_index(uid, id)?;
+        let alarm = &mut self.alarms[index];
+        let window = RingWindow::from(&*alarm);
+
+        alarm.exceptions = exceptions;
+        alarm.exceptions.sort_unstable();
+        alarm.exceptions.dedup();
+        alarm.reschedule(unix_now(), &local_timezone());
+
+        self.sync();
+
+        Some(window)
+    }
+
+    /// Skip the next occurrence of an existing alarm.
+    ///
+    /// The occurrence's date is added to the alarm's exceptions, moving
+    /// repeating alarms to their following occurrence and disabling all other
+    /// alarms.
+    ///
+    /// Returns the alarm's ringing window before the change, or `None` if no
+    /// alarm with this ID exists.
+    ///
+    /// See [`Self::alarm`] for alarms of multiple users with the same ID.
+    fn skip_next(&mut self, uid: u32, id: &str) -> Option<RingWindow> {
+        let index = self.alarm_index(uid, id)?;
+        let alarm = &mut self.alarms[index];
+        let window = RingWindow::from(&*alarm);
+
+        // Ensure elapsed occurrences are not skipped instead of the next one.
+        let timezone = local_timezone();
+        let now = unix_now();
+        alarm.reschedule(now, &timezone);
+
+        if let Some(wall_time) = alarm.wall_time(&timezone) {
+            let date = wall_time.date().to_julian_day();
+            if let Err(index) = alarm.exceptions.binary_search(&date) {
+                alarm.exceptions.insert(index, date);
+            }
+        }
+        alarm.reschedule(now, &timezone);
+
+        self.sync();
+
+        Some(window)
+    }
+
+    /// Start a new timer.
+    ///
+    /// Returns `true` if the timer was added and `false` if another timer of
+    /// the same owner with the same ID already exists.
+    fn add_timer(&mut self, timer: Timer) -> bool {
+        let conflicts =
+            |existing: &Timer| existing.id == timer.id && owned_by(existing.owner, timer.owner);
+        if self.timers.iter().any(conflicts) {
+            return false;
+        }
+
+        self.timers.push(timer);
+
+        self.sync();
+
+        true
+    }
+
+    /// Modify an existing timer.
+    ///
+    /// Returns the timer's ringing window before the modification, or `None`
+    /// if no timer with this ID exists.
+    ///
+    /// See [`Self::timer`] for timers of multiple users with the same ID.
+    fn update_timer(
+        &mut self,
+        uid: u32,
+        id: &str,
+        f: impl FnOnce(&mut Timer),
+    ) -> Option<Option<RingWindow>> {
+        let index = self.timer_index(uid, id)?;
+        let timer = &mut self.timers[index];
+        let window = RingWindow::from_timer(timer);
+        f(timer);
+
+        self.sync();
+
+        Some(window)
+    }
+
+    /// Remove an existing timer.
+    ///
+    /// See [`Self::timer`] for timers of multiple users with the same ID.
+    fn remove_timer(&mut self, uid: u32, id: &str) -> Option<Timer> {
+        let matching = self.timer_index(uid, id)?;
+        let removed = self.timers.remove(matching);
+
+        self.sync();
+
+        Some(removed)
+    }
+
+    /// Recompute floating alarms for the system's current timezone.
+    ///
+    /// Returns the previous ringing windows of all moved alarms.
+    fn update_timezone(&mut self) -> Vec<RingWindow> {
+        // Avoid reading the timezone without any floating alarms.
+        if self.alarms.iter().all(|alarm| !alarm.floating) {
+            return Vec::new();
+        }
+
+        let timezone = match TimeZone::local() {
+            Ok(timezone) => timezone,
+            Err(err) => {
+                error!("Could not load local timezone: {err}");
+                return Vec::new();
+            },
+        };
+
+        let mut moved = Vec::new();
+        for alarm in &mut self.alarms {
+            let window = RingWindow::from(&*alarm);
+            if alarm.update_timezone(&timezone) {
+                info!("Moved alarm {:?} to {} in {}", alarm.id, alarm.unix_time, timezone.name());
+                moved.push(window);
+            }
+        }
+
+        // Update database if alarms were moved.
+        if !moved.is_empty() {
+            self.sync();
+        }
+
+        moved
+    }
+
+    /// Remove all elapsed alarms and timers.
+    ///
+    /// Repeating alarms are moved to their next occurrence instead, while
+    /// disabled alarms are kept until they are removed explicitly.
+    ///
+    /// Returns the number of removed or moved elements.
+    fn remove_elapsed(&mut self) -> usize {
+        let old_len = self.alarms.len() + self.timers.len();
+
+        let now = unix_now();
+        let elapsed = |alarm: &Alarm| alarm.enabled && alarm.ring_end() <= now;
+
+        // Avoid reading the timezone without any elapsed repeating alarms.
+        let repeating = self.alarms.iter().any(|alarm| alarm.repeats() && elapsed(alarm));
+        let timezone = repeating.then(local_timezone);
+
+        let mut moved_count = 0;
+        self.alarms.retain_mut(|alarm| match &timezone {
+            _ if !elapsed(alarm) => true,
+            Some(timezone) if alarm.repeats() => {
+                moved_count += alarm.reschedule(now, timezone) as usize;
+                true
+            },
+            _ => false,
+        });
+        self.timers.retain(|timer| {
+            RingWindow::from_timer(timer).is_none_or(|window| window.end_ms() > now * 1000)
+        });
+
+        // Update database if entries were deleted or moved.
+        let changed_count = old_len - self.alarms.len() - self.timers.len() + moved_count;
+        if changed_count > 0 {
+            self.sync();
+        }
+
+        changed_count
+    }
+
+    /// Write all pending DB changes to the filesystem and signal changes.
+    fn sync(&mut self) {
+        // Signal changes.
+        let _ = self.onchange_tx.send(());
+
+        let json = serde_json::json!({ "alarms": self.alarms, "timers": self.timers });
+        let json = json.to_string();
+
+        // Overwrite the entire file.
+        let result = self
+            .db
+            .set_len(0)
+            .and_then(|_| self.db.rewind())
+            .and_then(|_| self.db.write_all(json.as_bytes()));
+
+        if let Err(err) = result {
+            error!("Failed DB sync: {err}");
+        }
+    }
+}
+
+/// Check if an alarm or timer belongs to a user.
+///
+/// Entries without owner belong to root.
+fn owned_by(owner: u32, uid: u32) -> bool {
+    owner == uid || (owner == rezz::UNOWNED && uid == 0)
+}
+
+/// Check if two alarms share the same ID and owner.
+///
+/// Alarm IDs only need to be unique for each user.
+fn same_alarm(alarm: &Alarm, other: &Alarm) -> bool {
+    alarm.id == other.id && owned_by(alarm.owner, other.owner)
+}
+
+/// Resolve a new alarm's time in the system's timezone.
+///
+/// Floating alarms are moved to their wall-clock time, and repeating alarms
+/// to their next occurrence which is not an exception. The client's alarm
+/// time is kept if the local timezone is unavailable.
+fn anchor(alarm: &mut Alarm) {
+    if !alarm.floating && !alarm.repeats() && alarm.exceptions.is_empty() {
+        return;
+    }
+
+    match TimeZone::local() {
+        Ok(timezone) => {
+            alarm.update_timezone(&timezone);
+            alarm.reschedule(unix_now(), &timezone);
+        },
+        Err(err) => warn!("Could not load local timezone: {err}"),
+    }
+}
+
+/// Load the system's local timezone, falling back to UTC.
+fn local_timezone() -> TimeZone {
+    TimeZone::local().unwrap_or_else(|err| {
+        warn!("Could not load local timezone: {err}");
+        TimeZone::utc()
+    })
+}
+
+/// Ensure a new alarm's ID, time and ring duration are within the supported
+/// limits.
+///
+/// Floating alarms must be anchored first, to validate their final time.
+fn validate_alarm(alarm: &Alarm) -> Result<(), ServiceError> {
+    validate_id(&alarm.id)?;
+    validate_time(alarm.unix_time, "alarm", &alarm.id)?;
+    validate_ring_seconds(alarm.ring_seconds, "alarm", &alarm.id)?;
+    validate_exceptions(alarm.exceptions.len(), &alarm.id)
+}
+
+/// Ensure an alarm does not exceed the maximum number of exception dates.
+fn validate_exceptions(count: usize, id: &str) -> Result<(), ServiceError> {
+    if count > MAX_EXCEPTIONS {
+        return Err(ServiceError::QuotaExceeded(format!(
+            "Alarm {id:?} cannot have more than {MAX_EXCEPTIONS} exceptions"
+        )));
+    }
+
+    Ok(())
+}
+
+/// Ensure an alarm or timer rings for a supported duration.
+fn validate_ring_seconds(ring_seconds: u32, kind: &str, id: &str) -> Result<(), ServiceError> {
+    if ring_seconds == 0 || (ring_seconds > MAX_RING_SECONDS && ring_seconds != RING_FOREVER) {
+        return Err(ServiceError::InvalidRingDuration(format!(
+            "Ring duration {ring_seconds}s of {kind} {id:?} is not between 1s and \
+             {MAX_RING_SECONDS}s"
+        )));
+    }
+
+    Ok(())
+}
+
+/// Ensure an alarm or timer time is within the RTC's supported range.
+fn validate_time(unix_time: i64, kind: &str, id: &str) -> Result<(), ServiceError> {
+    if !(MIN_UNIX_TIME..MAX_UNIX_TIME).contains(&unix_time) {
+        return Err(ServiceError::InvalidTime(format!(
+            "Time {unix_time} of {kind} {id:?} is not between {MIN_UNIX_TIME} and {MAX_UNIX_TIME}"
+        )));
+    }
+
+    Ok(())
+}
+
+/// Ensure an alarm or timer ID is within the supported length.
+fn validate_id(id: &str) -> Result<(), ServiceError> {
+    if id.is_empty() || id.len() > MAX_ID_LEN {
+        let msg = format!("ID must be between 1 and {MAX_ID_LEN} bytes, got {}", id.len());
+        return Err(ServiceError::InvalidId(msg));
+    }
+
+    Ok(())
+}
 
 /// Current unix time.
 fn unix_now() -> i64 {
     let now = OffsetDateTime::now_local().unwrap_or_else(|_| OffsetDateTime::now_utc());
     (now - OffsetDateTime::UNIX_EPOCH).whole_seconds()
 }
+
+#[cfg(test)]
+mod tests {
+    use rezz::testing::MockCalls;
+    use zbus::zvariant::OwnedValue;
+
+    use super::*;
+
+    /// Polkit authorization result returned by [`MockAuthority`].
+    #[derive(Copy, Clone)]
+    enum Reply {
+        Allow,
+        Deny,
+        Fail,
+    }
+
+    /// Subject names and actions of all authorization checks.
+    type MockChecks = MockCalls<(String, String)>;
+
+    /// Mock `org.freedesktop.PolicyKit1.Authority` service.
+    struct MockAuthority {
+        reply: Reply,
+        checks: MockChecks,
+    }
+
+    #[zbus::interface(name = "org.freedesktop.PolicyKit1.Authority")]
+    impl MockAuthority {
+        async fn check_authorization(
+            &self,
+            subject: (String, HashMap<String, OwnedValue>),
+            action_id: String,
+            _details: HashMap<String, String>,
+            flags: u32,
+            _cancellation_id: String,
+        ) -> zbus::fdo::Result<(bool, bool, HashMap<String, String>)> {
+            assert_eq!(subject.0, "system-bus-name");
+            assert_eq!(flags, polkit::ALLOW_USER_INTERACTION);
+
+            let name = String::try_from(subject.1["name"].try_clone().unwrap()).unwrap();
+            self.checks.lock().unwrap().push((name, action_id));
+
+            match self.reply {
+                Reply::Allow => Ok((true, false, HashMap::new())),
+                Reply::Deny => Ok((false, true, HashMap::new())),
+                Reply::Fail => Err(zbus::fdo::Error::Failed("mock failure".into())),
+            }
+        }
+    }
+
+    /// Connect to a mock polkit authority.
+    ///
+    /// Returns the client and server connections, and the checks received by
+    /// the mock.
+    async fn mock_authority(reply: Reply) -> (Connection, Connection, MockChecks) {
+        let checks = MockChecks::default();
+        let authority = MockAuthority { reply, checks: checks.clone() };
+        let path = "/org/freedesktop/PolicyKit1/Authority";
+        let (client, server) = rezz::testing::mock_service(path, authority).await;
+        (client, server, checks)
+    }
+
+    fn caller(uid: u32) -> Caller {
+        Caller { sender: OwnedUniqueName::try_from(":1.42").unwrap(), uid }
+    }
+
+    /// Create an empty store in a new temporary directory.
+    fn store(name: &str) -> Store {
+        let dir = std::env::temp_dir().join(format!("rezz-{}-{name}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        Store::new(dir.join("alarms.json")).unwrap()
+    }
+
+    #[tokio::test]
+    async fn authorize_allowed() {
+        let (connection, _server, checks) = mock_authority(Reply::Allow).await;
+
+        let result = caller(1000).authorize(&connection, CLEAR_ALL_ACTION).await;
+
+        assert!(result.is_ok());
+        let checks = checks.lock().unwrap();
+        assert_eq!(*checks, [(":1.42".into(), CLEAR_ALL_ACTION.into())]);
+    }
+
+    #[tokio::test]
+    async fn authorize_denied() {
+        let (connection, _server, checks) = mock_authority(Reply::Deny).await;
+
+        let result = caller(1000).authorize(&connection, MANAGE_OTHERS_ACTION).await;
+
+        assert!(matches!(result, Err(ServiceError::AccessDenied(_))));
+        assert_eq!(checks.lock().unwrap().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn authorize_error() {
+        let (connection, _server, checks) = mock_authority(Reply::Fail).await;
+
+        let result = caller(1000).authorize(&connection, MANAGE_OTHERS_ACTION).await;
+
+        assert!(matches!(result, Err(ServiceError::AccessDenied(_))));
+        assert_eq!(checks.lock().unwrap().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn authorize_root() {
+        let (connection, _server, checks) = mock_authority(Reply::Fail).await;
+
+        let result = caller(0).authorize(&connection, CLEAR_ALL_ACTION).await;
+
+        assert!(result.is_ok());
+        assert!(checks.lock().unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn check_access_own_entry() {
+        let (connection, _server, checks) = mock_authority(Reply::Deny).await;
+
+        assert!(caller(1000).check_access(&connection, 1000, "own").await.is_ok());
+        assert!(caller(0).check_access(&connection, rezz::UNOWNED, "legacy").await.is_ok());
+        assert!(checks.lock().unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn check_access_legacy_entry() {
+        let (connection, _server, checks) = mock_authority(Reply::Deny).await;
+        let result = caller(1000).check_access(&connection, rezz::UNOWNED, "legacy").await;
+        assert!(matches!(result, Err(ServiceError::AccessDenied(_))));
+
+        let (connection, _server, _) = mock_authority(Reply::Allow).await;
+        assert!(caller(1000).check_access(&connection, rezz::UNOWNED, "legacy").await.is_ok());
+
+        let checks = checks.lock().unwrap();
+        assert_eq!(*checks, [(":1.42".into(), MANAGE_OTHERS_ACTION.into())]);
+    }
+
+    #[test]
+    fn owns_legacy_entry() {
+        assert!(caller(0).owns(rezz::UNOWNED));
+        assert!(!caller(1000).owns(rezz::UNOWNED));
+    }
+
+    #[tokio::test]
+    async fn check_access_other_user() {
+        let (connection, _server, checks) = mock_authority(Reply::Deny).await;
+        let result = caller(1000).check_access(&connection, 1001, "other").await;
+        assert!(matches!(result, Err(ServiceError::AccessDenied(_))));
+
+        let (connection, _server, _) = mock_authority(Reply::Allow).await;
+        assert!(caller(1000).check_access(&connection, 1001, "other").await.is_ok());
+
+        let checks = checks.lock().unwrap();
+        assert_eq!(*checks, [(":1.42".into(), MANAGE_OTHERS_ACTION.into())]);
+    }
+
+    #[tokio::test]
+    async fn authorize_store_access() {
+        let (connection, _server, checks) = mock_authority(Reply::Deny).await;
+
+        let mut store = store("access");
+        let mut alarm = Alarm::new("alarm", unix_now() + 60, 60);
+        alarm.owner = 1001;
+        store.add(alarm);
+        let mut timer = Timer::new("timer", 60_000, 60);
+        timer.owner = 1000;
+        store.add_timer(timer);
+        let store = RwLock::new(store);
+
+        let caller = caller(1000);
+        let result = caller.authorize_alarms(&connection, &store, &["alarm".into()]).await;
+        assert!(matches!(result, Err(ServiceError::AccessDenied(_))));
+        assert_eq!(checks.lock().unwrap().len(), 1);
+
+        // Own and unknown entries do not need polkit authorization.
+        let access = caller.authorize_alarms(&connection, &store, &["missing".into()]).await;
+        assert!(!access.unwrap().manage_others);
+        let access = caller.authorize_timer(&connection, &store, "timer").await;
+        assert!(!access.unwrap().manage_others);
+        assert_eq!(checks.lock().unwrap().len(), 1);
+    }
+
+    #[test]
+    fn recheck_store_access() {
+        let mut store = store("recheck");
+        let ids = ["alarm".to_string()];
+
+        // Alarms added by other users during authorization are rejected.
+        let access = Access { uid: 1000, manage_others: false };
+        assert!(access.check_alarms(&store, &ids).is_ok());
+        let mut alarm = Alarm::new("alarm", unix_now() + 60, 60);
+        alarm.owner = 1001;
+        store.add(alarm);
+        let result = access.check_alarms(&store, &ids);
+        assert!(matches!(result, Err(ServiceError::AccessDenied(_))));
+
+        let access = Access { uid: 1000, manage_others: true };
+        assert!(access.check_alarms(&store, &ids).is_ok());
+
+        let mut timer = Timer::new("timer", 60_000, 60);
+        timer.owner = 1001;
+        store.add_timer(timer);
+        let access = Access { uid: 1000, manage_others: false };
+        let result = access.check_timer(&store, "timer");
+        assert!(matches!(result, Err(ServiceError::AccessDenied(_))));
+    }
+
+    #[test]
+    fn replace_all() {
+        let mut store = store("replace");
+        let time = unix_now() + 60;
+        store.add(Alarm::new("a", time, 60));
+        store.add(Alarm::new("b", time, 60));
+
+        // Duplicated IDs leave the existing alarms untouched.
+        let duplicated = vec![Alarm::new("b", time, 30), Alarm::new("b", time, 30)];
+        assert_eq!(store.replace_all(duplicated), Err("b".into()));
+        assert_eq!(store.alarms.len(), 2);
+
+        let removed = store.replace_all(vec![Alarm::new("b", time, 30), Alarm::new("c", time, 30)]);
+        assert_eq!(removed, Ok(vec![Alarm::new("b", time, 60)]));
+
+        let alarms: Vec<_> =
+            store.alarms.iter().map(|alarm| (&*alarm.id, alarm.ring_seconds)).collect();
+        assert_eq!(alarms, [("a", 60), ("b", 30), ("c", 30)]);
+    }
+
+    #[test]
+    fn legacy_quota() {
+        let mut store = store("quota");
+        let time = unix_now() + 60;
+        let alarms = (1..MAX_ALARMS).map(|i| Alarm::new(i.to_string(), time, 60));
+        store.alarms.extend(alarms);
+        store.timers.push(Timer::new("timer", 60_000, 60));
+
+        // Unowned alarms and timers count towards root's quota.
+        assert!(matches!(store.validate_quota(0, 1), Err(ServiceError::QuotaExceeded(_))));
+        assert!(store.validate_quota(1000, MAX_ALARMS).is_ok());
+    }
+
+    #[test]
+    fn per_owner_ids() {
+        let mut store = store("owners");
+        let time = unix_now() + 60;
+        let owned = |uid, ring_seconds| {
+            let mut alarm = Alarm::new("alarm", time, ring_seconds);
+            alarm.owner = uid;
+            alarm
+        };
+
+        // IDs only conflict with alarms of the same owner.
+        assert!(store.add(owned(1000, 60)));
+        assert!(store.add(owned(1001, 30)));
+        assert!(!store.add(owned(1001, 30)));
+
+        // Lookups prefer the caller's own alarm.
+        assert_eq!(store.alarm(1001, "alarm").map(|alarm| alarm.ring_seconds), Some(30));
+        assert_eq!(store.remove(1000, "alarm"), Some(owned(1000, 60)));
+        assert_eq!(store.alarm(1000, "alarm").map(|alarm| alarm.owner), Some(1001));
+
+        // Replacing alarms leaves other users' alarms untouched.
+        assert_eq!(store.replace_all(vec![owned(1000, 10)]), Ok(Vec::new()));
+        assert_eq!(store.replace_all(vec![owned(1000, 20)]), Ok(vec![owned(1000, 10)]));
+        assert_eq!(store.alarms.len(), 2);
+    }
+
+    #[test]
+    fn skip_next() {
+        let mut store = store("skip");
+        let timezone = local_timezone();
+
+        let mut alarm = Alarm::new("daily", unix_now() + 60 * 60, 60);
+        alarm.repeat_days = 0b111_1111;
+        let date = alarm.wall_time(&timezone).unwrap().date();
+        store.add(alarm);
+        store.add(Alarm::new("once", unix_now() + 60 * 60, 60));
+
+        // Repeating alarms move to their following occurrence.
+        assert!(store.skip_next(0, "daily").is_some());
+        let alarm = store.alarm(0, "daily").unwrap();
+        assert!(alarm.is_exception(date));
+        assert_eq!(alarm.wall_time(&timezone).unwrap().date(), date.next_day().unwrap());
+        assert!(alarm.enabled);
+
+        // Other alarms are disabled.
+        assert!(store.skip_next(0, "once").is_some());
+        assert!(!store.alarm(0, "once").unwrap().enabled);
+
+        assert!(store.skip_next(0, "missing").is_none());
+    }
+
+    #[test]
+    fn remove_elapsed_repeating() {
+        let mut store = store("elapsed");
+        let mut alarm = Alarm::new("daily", unix_now() - 60 * 60, 60);
+        alarm.repeat_days = 0b111_1111;
+        store.add(alarm);
+        store.add(Alarm::new("once", unix_now() - 60 * 60, 60));
+
+        assert_eq!(store.remove_elapsed(), 2);
+
+        let alarms: Vec<_> = store.alarms.iter().map(|alarm| &*alarm.id).collect();
+        assert_eq!(alarms, ["daily"]);
+        assert!(store.alarms[0].unix_time > unix_now());
+    }
+
+    #[test]
+    fn validate_anchored_alarm() {
+        let zone = TimeZone::local().unwrap();
+        let tomorrow = OffsetDateTime::now_utc().date().next_day().unwrap();
+        let local_time = time::PrimitiveDateTime::new(tomorrow, time::Time::MIDNIGHT);
+
+        // Clients might send floating alarms with an outdated alarm time.
+        let mut alarm = Alarm::floating("floating", local_time, 60, &zone);
+        alarm.unix_time = 0;
+        assert!(matches!(validate_alarm(&alarm), Err(ServiceError::InvalidTime(_))));
+
+        anchor(&mut alarm);
+        assert!(validate_alarm(&alarm).is_ok());
+    }
+
+    #[test]
+    fn validate_ring_duration() {
+        let valid = |ring_seconds| validate_ring_seconds(ring_seconds, "alarm", "id").is_ok();
+
+        assert!(!valid(0));
+        assert!(valid(1));
+        assert!(valid(MAX_RING_SECONDS));
+        assert!(!valid(MAX_RING_SECONDS + 1));
+        assert!(valid(RING_FOREVER));
+    }
+}
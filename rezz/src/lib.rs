@@ -1,26 +1,108 @@
 use std::cmp::Ordering;
+use std::fmt::{self, Display, Formatter};
 use std::fs::File;
 use std::io;
 use std::mem::MaybeUninit;
 use std::os::fd::AsRawFd;
+use std::time::{Duration, SystemTime};
 
 use serde::{Deserialize, Serialize};
-use time::OffsetDateTime;
+use time::{Date, OffsetDateTime, PrimitiveDateTime, Weekday};
 use zbus::zvariant::{OwnedValue, Type, Value};
 
 use crate::ioctl::RtcWkalm;
+use crate::tz::TimeZone;
 
 mod ioctl;
+#[cfg(any(test, feature = "testing"))]
+#[doc(hidden)]
+pub mod testing;
+pub mod tz;
 
 /// Primary RTC path, should always exist for systems with RTC.
 const RTC_PATH: &str = "/dev/rtc";
 
+/// Owner of alarms and timers created before ownership was recorded.
+///
+/// These are treated as root's, so other users can only access them with
+/// authorization for rezz's `manage-others` polkit action.
+pub const UNOWNED: u32 = u32::MAX;
+
+/// Ring duration of alarms and timers which ring until they are stopped.
+pub const RING_FOREVER: u32 = u32::MAX;
+
+/// Earliest supported alarm time, 2000-01-01T00:00:00Z.
+///
+/// Common RTC hardware cannot represent times outside of the 21st century.
+pub const MIN_UNIX_TIME: i64 = 946_684_800;
+
+/// Latest supported alarm time, 2100-01-01T00:00:00Z.
+pub const MAX_UNIX_TIME: i64 = 4_102_444_800;
+
+/// Maximum ringing window of repeating alarms in seconds.
+///
+/// This ensures repeating alarms which ring until they are stopped still move
+/// on to their next occurrence.
+const MAX_REPEAT_RING_SECONDS: u32 = 24 * 60 * 60;
+
+/// Maximum number of days searched for the next occurrence of repeating
+/// alarms.
+const MAX_REPEAT_DAYS: usize = 10 * 366 + 7;
+
 #[derive(thiserror::Error, Debug)]
 pub enum Error {
     #[error("{0}")]
     Nix(#[from] nix::Error),
     #[error("{0}")]
     Io(#[from] io::Error),
+    #[error("invalid timezone {0:?}")]
+    InvalidTimeZone(String),
+}
+
+/// Errors returned by Rezz's DBus interface.
+#[derive(zbus::DBusError, Debug)]
+#[zbus(prefix = "org.catacombing.rezz.Error")]
+pub enum ServiceError {
+    #[zbus(error)]
+    ZBus(zbus::Error),
+    /// Caller is not allowed to access an alarm or timer.
+    AccessDenied(String),
+    /// Alarm with this ID exists already.
+    AlarmExists(String),
+    /// No alarm with this ID exists.
+    AlarmNotFound(String),
+    /// Timer with this ID exists already.
+    TimerExists(String),
+    /// No timer with this ID exists.
+    TimerNotFound(String),
+    /// Alarm or timer ID is empty or too long.
+    InvalidId(String),
+    /// Alarm time is outside of the supported range.
+    InvalidTime(String),
+    /// Timer duration is too long.
+    InvalidDuration(String),
+    /// Ring duration is zero or too long.
+    InvalidRingDuration(String),
+    /// Caller has reached the maximum number of alarms.
+    QuotaExceeded(String),
+    /// RTC wakeup could not be updated.
+    Rtc(String),
+}
+
+impl From<zbus::fdo::Error> for ServiceError {
+    fn from(err: zbus::fdo::Error) -> Self {
+        Self::ZBus(err.into())
+    }
+}
+
+impl From<ServiceError> for zbus::fdo::Error {
+    fn from(err: ServiceError) -> Self {
+        match err {
+            ServiceError::ZBus(err) => err.into(),
+            ServiceError::AccessDenied(msg) => Self::AccessDenied(msg),
+            err => Self::Failed(err.to_string()),
+        }
+    }
 }
 
 /// Set a new RTC wakeup time.
@@ -53,11 +135,226 @@ pub struct Alarm {
     pub id: String,
     pub unix_time: i64,
     pub ring_seconds: u32,
+    /// Milliseconds past `unix_time`, missing from alarms before `1.2.0`.
+    #[serde(default)]
+    pub unix_millis: u16,
+    /// Keep the alarm at the same wall-clock time when the system's timezone
+    /// or its UTC offset changes, instead of ringing at a fixed instant.
+    #[serde(default)]
+    pub floating: bool,
+    /// Wall-clock time of floating alarms, as seconds since `1970-01-01 00:00`
+    /// local time.
+    #[serde(default)]
+    pub local_time: i64,
+    /// Disabled alarms are kept, but never ring.
+    #[serde(default = "default_enabled")]
+    pub enabled: bool,
+    /// UID of the alarm's creator, assigned by Rezz.
+    #[serde(default = "default_owner")]
+    pub owner: u32,
+    /// Alert style used by clients when the alarm rings.
+    #[serde(default)]
+    pub kind: AlarmKind,
+    /// Handling of the alarm when clients discover it after its ring time.
+    #[serde(default)]
+    pub late_policy: LatePolicy,
+    /// Weekdays on which the alarm repeats, with Monday as the lowest bit.
+    ///
+    /// Repeating alarms move to their next occurrence once they have rung,
+    /// instead of being removed.
+    #[serde(default)]
+    pub repeat_days: u8,
+    /// Local dates on which the alarm does not ring, as Julian day numbers.
+    #[serde(default)]
+    pub exceptions: Vec<i32>,
 }
 
 impl Alarm {
     pub fn new(id: impl Into<String>, unix_time: i64, ring_seconds: u32) -> Self {
-        Self { id: id.into(), unix_time, ring_seconds }
+        Self {
+            id: id.into(),
+            unix_time,
+            ring_seconds,
+            unix_millis: 0,
+            floating: false,
+            local_time: 0,
+            enabled: true,
+            owner: UNOWNED,
+            kind: AlarmKind::WakeUp,
+            late_policy: LatePolicy::Client,
+            repeat_days: 0,
+            exceptions: Vec::new(),
+        }
+    }
+
+    /// Create an alarm at a wall-clock time following the system's timezone.
+    pub fn floating(
+        id: impl Into<String>,
+        local_time: PrimitiveDateTime,
+        ring_seconds: u32,
+        timezone: &TimeZone,
+    ) -> Self {
+        let mut alarm = Self::new(id, 0, ring_seconds);
+        alarm.local_time = local_time.assume_utc().unix_timestamp();
+        alarm.unix_millis = local_time.millisecond();
+        alarm.floating = true;
+        alarm.update_timezone(timezone);
+        alarm
+    }
+
+    /// Create an alarm from a timestamp in milliseconds since the unix epoch.
+    pub fn from_unix_time_ms(id: impl Into<String>, unix_time_ms: i64, ring_seconds: u32) -> Self {
+        Self {
+            ring_seconds,
+            id: id.into(),
+            unix_time: unix_time_ms.div_euclid(1000),
+            unix_millis: unix_time_ms.rem_euclid(1000) as u16,
+            floating: false,
+            local_time: 0,
+            enabled: true,
+            owner: UNOWNED,
+            kind: AlarmKind::WakeUp,
+            late_policy: LatePolicy::Client,
+            repeat_days: 0,
+            exceptions: Vec::new(),
+        }
+    }
+
+    /// Wall-clock time of floating alarms.
+    pub fn local_time(&self) -> Option<PrimitiveDateTime> {
+        if !self.floating {
+            return None;
+        }
+
+        let local_time = OffsetDateTime::from_unix_timestamp(self.local_time).ok()?;
+        Some(PrimitiveDateTime::new(local_time.date(), local_time.time()))
+    }
+
+    /// Recompute the alarm time of floating alarms in a new timezone.
+    ///
+    /// Returns `true` if the alarm time was changed.
+    pub fn update_timezone(&mut self, timezone: &TimeZone) -> bool {
+        let local_time = match self.local_time() {
+            Some(local_time) => local_time,
+            None => return false,
+        };
+
+        let unix_time = timezone.resolve(local_time).unix_timestamp();
+        let changed = unix_time != self.unix_time;
+        self.unix_time = unix_time;
+
+        changed
+    }
+
+    /// Alarm time in milliseconds since the unix epoch.
+    pub fn unix_time_ms(&self) -> i64 {
+        self.unix_time * 1000 + self.unix_millis as i64
+    }
+
+    /// Check if the alarm repeats on any weekday.
+    pub fn repeats(&self) -> bool {
+        self.repeat_days != 0
+    }
+
+    /// Check if the alarm repeats on a weekday.
+    pub fn repeats_on(&self, weekday: Weekday) -> bool {
+        self.repeat_days & (1 << weekday.number_days_from_monday()) != 0
+    }
+
+    /// Set the weekdays on which the alarm repeats.
+    pub fn set_repeat_days(&mut self, weekdays: &[Weekday]) {
+        self.repeat_days =
+            weekdays.iter().fold(0, |days, weekday| days | 1 << weekday.number_days_from_monday());
+    }
+
+    /// Check if the alarm does not ring on a local date.
+    pub fn is_exception(&self, date: Date) -> bool {
+        self.exceptions.contains(&date.to_julian_day())
+    }
+
+    /// Set the local dates on which the alarm does not ring.
+    pub fn set_exceptions(&mut self, dates: impl IntoIterator<Item = Date>) {
+        self.exceptions = dates.into_iter().map(Date::to_julian_day).collect();
+        self.exceptions.sort_unstable();
+        self.exceptions.dedup();
+    }
+
+    /// Wall-clock time of the alarm in a timezone.
+    pub fn wall_time(&self, timezone: &TimeZone) -> Option<PrimitiveDateTime> {
+        if let Some(local_time) = self.local_time() {
+            return Some(local_time);
+        }
+
+        let time = timezone.to_local(OffsetDateTime::from_unix_timestamp(self.unix_time).ok()?);
+        Some(PrimitiveDateTime::new(time.date(), time.time()))
+    }
+
+    /// Unix time at which the alarm's ringing window ends.
+    ///
+    /// Repeating alarms ring for at most one day.
+    pub fn ring_end(&self) -> i64 {
+        let ring_seconds = if self.repeats() {
+            self.ring_seconds.min(MAX_REPEAT_RING_SECONDS)
+        } else {
+            self.ring_seconds
+        };
+        self.unix_time.saturating_add(ring_seconds as i64)
+    }
+
+    /// Move the alarm to its next occurrence which is not an exception.
+    ///
+    /// Repeating alarms move to the first repeat day at their wall-clock time
+    /// whose ringing window has not ended at `now`. Alarms which do not repeat
+    /// are disabled if they would ring on an exception date, as are repeating
+    /// alarms without any remaining occurrences.
+    ///
+    /// Returns `true` if the alarm was changed.
+    pub fn reschedule(&mut self, now: i64, timezone: &TimeZone) -> bool {
+        let Some(wall_time) = self.wall_time(timezone) else { return false };
+
+        if !self.repeats() {
+            let excluded = self.enabled && self.is_exception(wall_time.date());
+            if excluded {
+                self.enabled = false;
+            }
+            return excluded;
+        }
+
+        let Ok(now_time) = OffsetDateTime::from_unix_timestamp(now) else { return false };
+
+        // Start at the previous day, since its occurrence might still be ringing.
+        let today = timezone.to_local(now_time).date();
+        let mut date = today.previous_day().unwrap_or(today);
+
+        // Forget exceptions which can no longer affect any occurrence.
+        self.exceptions.retain(|day| *day >= date.to_julian_day());
+
+        for _ in 0..MAX_REPEAT_DAYS {
+            if self.repeats_on(date.weekday()) && !self.is_exception(date) {
+                let local_time = PrimitiveDateTime::new(date, wall_time.time());
+                let mut occurrence = self.clone();
+                occurrence.unix_time = timezone.resolve(local_time).unix_timestamp();
+                if occurrence.floating {
+                    occurrence.local_time = local_time.assume_utc().unix_timestamp();
+                }
+
+                if occurrence.ring_end() > now {
+                    let changed = occurrence != *self;
+                    *self = occurrence;
+                    return changed;
+                }
+            }
+
+            date = match date.next_day() {
+                Some(date) => date,
+                None => break,
+            };
+        }
+
+        // Disable alarms without any remaining occurrences.
+        let changed = self.enabled;
+        self.enabled = false;
+        changed
     }
 }
 
@@ -69,7 +366,7 @@ impl PartialOrd for Alarm {
 
 impl Ord for Alarm {
     fn cmp(&self, other: &Self) -> Ordering {
-        match self.unix_time.cmp(&other.unix_time) {
+        match self.unix_time_ms().cmp(&other.unix_time_ms()) {
             Ordering::Equal => match self.id.cmp(&other.id) {
                 Ordering::Equal => self.ring_seconds.cmp(&other.ring_seconds),
                 ordering => ordering,
@@ -78,3 +375,214 @@ impl Ord for Alarm {
         }
     }
 }
+
+/// Alert style of an alarm.
+#[derive(
+    Deserialize, Serialize, Type, Value, OwnedValue, Copy, Clone, PartialEq, Eq, Debug, Default,
+)]
+#[serde(rename_all = "kebab-case")]
+#[zvariant(signature = "s", rename_all = "kebab-case")]
+pub enum AlarmKind {
+    /// Ring with a looping sound until dismissed or the ringing window ends.
+    #[default]
+    WakeUp,
+    /// Play a short chime and show a notification.
+    Reminder,
+}
+
+impl Display for AlarmKind {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::WakeUp => write!(f, "wake-up"),
+            Self::Reminder => write!(f, "reminder"),
+        }
+    }
+}
+
+/// Handling of an alarm discovered after its ring time.
+#[derive(
+    Deserialize, Serialize, Type, Value, OwnedValue, Copy, Clone, PartialEq, Eq, Debug, Default,
+)]
+#[serde(rename_all = "kebab-case")]
+#[zvariant(signature = "s", rename_all = "kebab-case")]
+pub enum LatePolicy {
+    /// Use the late policy of the client ringing the alarm.
+    #[default]
+    Client,
+    /// Ring for the remainder of the alarm's ringing window.
+    Remaining,
+    /// Ring for the alarm's full ringing duration.
+    Full,
+    /// Do not ring, notifying about the missed alarm instead.
+    Skip,
+}
+
+impl Display for LatePolicy {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Client => write!(f, "client"),
+            Self::Remaining => write!(f, "remaining"),
+            Self::Full => write!(f, "full"),
+            Self::Skip => write!(f, "skip"),
+        }
+    }
+}
+
+/// Countdown timer.
+#[derive(Deserialize, Serialize, Type, Value, OwnedValue, Clone, PartialEq, Eq, Debug)]
+pub struct Timer {
+    pub id: String,
+    /// Total timer duration in milliseconds.
+    pub duration_ms: u64,
+    /// Milliseconds remaining at `updated_ms`.
+    pub remaining_ms: u64,
+    /// Unix time in milliseconds of the last start, pause or resume.
+    pub updated_ms: i64,
+    pub paused: bool,
+    pub ring_seconds: u32,
+    /// UID of the timer's creator, assigned by Rezz.
+    #[serde(default = "default_owner")]
+    pub owner: u32,
+}
+
+impl Timer {
+    /// Create a new running timer.
+    pub fn new(id: impl Into<String>, duration_ms: u64, ring_seconds: u32) -> Self {
+        Self {
+            duration_ms,
+            ring_seconds,
+            id: id.into(),
+            remaining_ms: duration_ms,
+            updated_ms: unix_now_ms(),
+            paused: false,
+            owner: UNOWNED,
+        }
+    }
+
+    /// Time left until the timer elapses.
+    pub fn remaining(&self) -> Duration {
+        if self.paused {
+            return Duration::from_millis(self.remaining_ms);
+        }
+
+        let elapsed = unix_now_ms().saturating_sub(self.updated_ms).max(0) as u64;
+        Duration::from_millis(self.remaining_ms.saturating_sub(elapsed))
+    }
+
+    /// Unix time in milliseconds at which the timer elapses.
+    ///
+    /// Returns `None` while the timer is paused.
+    pub fn end_time_ms(&self) -> Option<i64> {
+        (!self.paused).then(|| self.updated_ms.saturating_add(self.remaining_ms as i64))
+    }
+
+    /// Stop the countdown, preserving the remaining time.
+    pub fn pause(&mut self) {
+        if !self.paused {
+            self.remaining_ms = self.remaining().as_millis() as u64;
+            self.updated_ms = unix_now_ms();
+            self.paused = true;
+        }
+    }
+
+    /// Continue the countdown of a paused timer.
+    pub fn resume(&mut self) {
+        if self.paused {
+            self.updated_ms = unix_now_ms();
+            self.paused = false;
+        }
+    }
+}
+
+/// Default for alarms stored before the `enabled` flag was added.
+fn default_enabled() -> bool {
+    true
+}
+
+/// Default for alarms and timers stored before ownership was recorded.
+fn default_owner() -> u32 {
+    UNOWNED
+}
+
+/// Current unix time in milliseconds.
+fn unix_now_ms() -> i64 {
+    SystemTime::now().duration_since(SystemTime::UNIX_EPOCH).unwrap_or_default().as_millis() as i64
+}
+
+#[cfg(test)]
+mod tests {
+    use time::macros::{date, datetime};
+
+    use super::*;
+
+    /// Create a weekday alarm at 07:00 UTC, starting on a Wednesday.
+    fn weekday_alarm() -> Alarm {
+        let mut alarm =
+            Alarm::new("weekdays", datetime!(2025-01-01 07:00 UTC).unix_timestamp(), 60);
+        alarm.set_repeat_days(&[
+            Weekday::Monday,
+            Weekday::Tuesday,
+            Weekday::Wednesday,
+            Weekday::Thursday,
+            Weekday::Friday,
+        ]);
+        alarm
+    }
+
+    #[test]
+    fn reschedule_repeating() {
+        let utc = TimeZone::utc();
+        let mut alarm = weekday_alarm();
+
+        // Alarms stay in place until their ringing window has ended.
+        let now = datetime!(2025-01-01 07:00:30 UTC).unix_timestamp();
+        assert!(!alarm.reschedule(now, &utc));
+
+        // Friday's alarm moves to Monday.
+        let now = datetime!(2025-01-03 08:00 UTC).unix_timestamp();
+        assert!(alarm.reschedule(now, &utc));
+        assert_eq!(alarm.unix_time, datetime!(2025-01-06 07:00 UTC).unix_timestamp());
+    }
+
+    #[test]
+    fn reschedule_exceptions() {
+        let utc = TimeZone::utc();
+        let mut alarm = weekday_alarm();
+        alarm.set_exceptions([date!(2025 - 01 - 01), date!(2025 - 01 - 02), date!(2024 - 12 - 01)]);
+
+        let now = datetime!(2025-01-01 06:00 UTC).unix_timestamp();
+        assert!(alarm.reschedule(now, &utc));
+        assert_eq!(alarm.unix_time, datetime!(2025-01-03 07:00 UTC).unix_timestamp());
+        assert!(alarm.enabled);
+
+        // Outdated exceptions are removed.
+        assert!(!alarm.is_exception(date!(2024 - 12 - 01)));
+        assert!(alarm.is_exception(date!(2025 - 01 - 02)));
+    }
+
+    #[test]
+    fn reschedule_one_shot() {
+        let utc = TimeZone::utc();
+        let now = datetime!(2025-01-01 06:00 UTC).unix_timestamp();
+        let mut alarm = Alarm::new("once", datetime!(2025-01-01 07:00 UTC).unix_timestamp(), 60);
+
+        assert!(!alarm.reschedule(now, &utc));
+        assert!(alarm.enabled);
+
+        // One-shot alarms on an exception date are disabled, without moving them.
+        alarm.set_exceptions([date!(2025 - 01 - 01)]);
+        assert!(alarm.reschedule(now, &utc));
+        assert!(!alarm.enabled);
+        assert_eq!(alarm.unix_time, datetime!(2025-01-01 07:00 UTC).unix_timestamp());
+    }
+
+    #[test]
+    fn repeating_ring_end() {
+        let mut alarm = weekday_alarm();
+        alarm.ring_seconds = RING_FOREVER;
+        assert_eq!(alarm.ring_end(), alarm.unix_time + 24 * 60 * 60);
+
+        alarm.repeat_days = 0;
+        assert_eq!(alarm.ring_end(), alarm.unix_time + RING_FOREVER as i64);
+    }
+}
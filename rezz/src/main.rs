@@ -3,6 +3,7 @@ use tracing_subscriber::FmtSubscriber;
 
 mod dbus;
 mod logind;
+mod polkit;
 
 #[tokio::main(flavor = "current_thread")]
 async fn main() {
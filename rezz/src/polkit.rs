@@ -0,0 +1,49 @@
+//! # DBus interface proxy for: `org.freedesktop.PolicyKit1.Authority`
+//!
+//! Only the authorization check used by Rezz is included. See the [polkit
+//! documentation](https://www.freedesktop.org/software/polkit/docs/latest/eggdbus-interface-org.freedesktop.PolicyKit1.Authority.html)
+//! for details.
+
+use std::collections::HashMap;
+
+use serde::Serialize;
+use zbus::proxy;
+use zbus::zvariant::{Type, Value};
+
+/// Allow polkit to interactively authenticate the subject.
+pub const ALLOW_USER_INTERACTION: u32 = 1;
+
+/// Entity requesting authorization.
+#[derive(Serialize, Type, Debug)]
+pub struct Subject<'a> {
+    kind: &'a str,
+    details: HashMap<&'a str, Value<'a>>,
+}
+
+impl<'a> Subject<'a> {
+    /// Create a subject for the peer of a DBus connection.
+    pub fn system_bus_name(name: &'a str) -> Self {
+        let details = HashMap::from([("name", Value::from(name))]);
+        Self { kind: "system-bus-name", details }
+    }
+}
+
+#[proxy(
+    interface = "org.freedesktop.PolicyKit1.Authority",
+    default_service = "org.freedesktop.PolicyKit1",
+    default_path = "/org/freedesktop/PolicyKit1/Authority"
+)]
+pub trait Authority {
+    /// CheckAuthorization method
+    ///
+    /// Returns whether the subject is authorized, whether it could be
+    /// authorized through authentication, and additional details.
+    fn check_authorization(
+        &self,
+        subject: &Subject<'_>,
+        action_id: &str,
+        details: HashMap<&str, &str>,
+        flags: u32,
+        cancellation_id: &str,
+    ) -> zbus::Result<(bool, bool, HashMap<String, String>)>;
+}
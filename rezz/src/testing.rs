@@ -0,0 +1,30 @@
+//! Helpers for testing DBus clients against mock services.
+
+use std::sync::{Arc, Mutex};
+
+use tokio::net::UnixStream;
+use zbus::connection::Builder;
+use zbus::object_server::Interface;
+use zbus::{Connection, Guid};
+
+/// Method calls recorded by a mock service.
+pub type MockCalls<T> = Arc<Mutex<Vec<T>>>;
+
+/// Serve a mock DBus interface over a peer-to-peer connection.
+///
+/// Returns the client and server connections. The server must be kept alive
+/// for as long as the client is used.
+pub async fn mock_service<I: Interface>(path: &str, interface: I) -> (Connection, Connection) {
+    let (client, server) = UnixStream::pair().unwrap();
+
+    let server = Builder::unix_stream(server)
+        .server(Guid::generate())
+        .unwrap()
+        .p2p()
+        .serve_at(path, interface)
+        .unwrap()
+        .build();
+    let client = Builder::unix_stream(client).p2p().build();
+
+    tokio::try_join!(client, server).unwrap()
+}
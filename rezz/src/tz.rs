@@ -0,0 +1,647 @@
+//! IANA timezone database lookup.
+//!
+//! This parses the compiled TZif files shipped by the system's tzdata, see
+//! RFC 8536 for details on the format.
+
+use std::fs;
+use std::io::ErrorKind as IoErrorKind;
+use std::path::Path;
+
+use time::{Date, Month, OffsetDateTime, PrimitiveDateTime, UtcOffset, Weekday, util};
+
+use crate::Error;
+
+/// Location of the system's timezone database.
+const ZONEINFO_PATH: &str = "/usr/share/zoneinfo";
+
+/// Location of the system's local timezone.
+pub const LOCALTIME_PATH: &str = "/etc/localtime";
+
+/// TZif file magic bytes.
+const TZIF_MAGIC: &[u8] = b"TZif";
+
+/// Size of the TZif header.
+const HEADER_LEN: usize = 44;
+
+/// Seconds per day.
+const DAY_SECONDS: i64 = 60 * 60 * 24;
+
+/// Timezone with all its historic and future UTC offsets.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct TimeZone {
+    name: String,
+    /// Transition times with the UTC offset in effect after them.
+    transitions: Vec<(i64, i32)>,
+    /// UTC offset before the first transition.
+    initial_offset: i32,
+    /// Rule for times after the last transition.
+    rule: Option<PosixRule>,
+}
+
+impl TimeZone {
+    /// Load a timezone by its IANA name, like `America/New_York`.
+    pub fn load(name: &str) -> Result<Self, Error> {
+        // Prevent escaping the zoneinfo directory.
+        let invalid = || Error::InvalidTimeZone(name.into());
+        if name.is_empty() || name.starts_with('/') || name.split('/').any(|part| part == "..") {
+            return Err(invalid());
+        }
+
+        let data = match fs::read(Path::new(ZONEINFO_PATH).join(name)) {
+            Ok(data) => data,
+            Err(err) if err.kind() == IoErrorKind::NotFound => return Err(invalid()),
+            Err(err) => return Err(err.into()),
+        };
+
+        Self::parse(name, &data).ok_or_else(invalid)
+    }
+
+    /// Load the system's local timezone.
+    ///
+    /// This ignores the `TZ` environment variable, to match the timezone used
+    /// by Rezz.
+    pub fn local() -> Result<Self, Error> {
+        let data = fs::read(LOCALTIME_PATH)?;
+
+        // Get the IANA name from the zoneinfo symlink's target.
+        let target = fs::read_link(LOCALTIME_PATH).ok();
+        let target = target.as_ref().and_then(|target| target.to_str());
+        let name = match target.and_then(|target| target.split_once("zoneinfo/")) {
+            Some((_, name)) => name,
+            None => "localtime",
+        };
+
+        Self::parse(name, &data).ok_or_else(|| Error::InvalidTimeZone(name.into()))
+    }
+
+    /// Coordinated Universal Time, without any UTC offset changes.
+    pub fn utc() -> Self {
+        Self { name: "UTC".into(), transitions: Vec::new(), initial_offset: 0, rule: None }
+    }
+
+    /// IANA name of the timezone.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Get the UTC offset in effect at a unix timestamp.
+    pub fn offset_at(&self, unix_time: i64) -> UtcOffset {
+        let seconds = self.offset_seconds_at(unix_time);
+        UtcOffset::from_whole_seconds(seconds).unwrap_or(UtcOffset::UTC)
+    }
+
+    /// Convert a time to this timezone.
+    pub fn to_local(&self, time: OffsetDateTime) -> OffsetDateTime {
+        time.to_offset(self.offset_at(time.unix_timestamp()))
+    }
+
+    /// Convert a wall-clock time in this timezone to an absolute time.
+    ///
+    /// Wall-clock times skipped by a forward transition are shifted forward
+    /// by the transition's length, while ambiguous times use the offset in
+    /// effect before the transition.
+    pub fn resolve(&self, local: PrimitiveDateTime) -> OffsetDateTime {
+        let local_unix = local.assume_utc().unix_timestamp();
+
+        // Get offsets on both sides of a potential transition.
+        let before = self.offset_seconds_at(local_unix - DAY_SECONDS);
+        let after = self.offset_seconds_at(local_unix + DAY_SECONDS);
+
+        // Find an offset which maps back to the same wall-clock time.
+        let is_valid = |offset: i32| self.offset_seconds_at(local_unix - offset as i64) == offset;
+        let offset = if is_valid(before) || !is_valid(after) { before } else { after };
+
+        let offset = UtcOffset::from_whole_seconds(offset).unwrap_or(UtcOffset::UTC);
+        self.to_local(local.assume_offset(offset))
+    }
+
+    /// Get the UTC offset in seconds at a unix timestamp.
+    fn offset_seconds_at(&self, unix_time: i64) -> i32 {
+        let index = self.transitions.partition_point(|(time, _)| *time <= unix_time);
+
+        // Use POSIX rule beyond the last transition.
+        if index == self.transitions.len() {
+            if let Some(rule) = &self.rule {
+                return rule.offset_at(unix_time);
+            }
+        }
+
+        match index.checked_sub(1) {
+            Some(index) => self.transitions[index].1,
+            None => self.initial_offset,
+        }
+    }
+
+    /// Parse a TZif file.
+    fn parse(name: &str, data: &[u8]) -> Option<Self> {
+        let header = Header::parse(data)?;
+
+        // Skip the legacy 32-bit data block for version 2+ files.
+        let (header, body, time_size) = if header.version >= b'2' {
+            let data = data.get(HEADER_LEN + header.data_len(4)..)?;
+            (Header::parse(data)?, &data[HEADER_LEN..], 8)
+        } else {
+            (header, &data[HEADER_LEN..], 4)
+        };
+
+        let mut reader = Reader(body);
+
+        // Read transition times.
+        let mut times = Vec::with_capacity(header.timecnt);
+        for _ in 0..header.timecnt {
+            let time = match time_size {
+                8 => i64::from_be_bytes(reader.take(8)?.try_into().ok()?),
+                _ => i32::from_be_bytes(reader.take(4)?.try_into().ok()?) as i64,
+            };
+            times.push(time);
+        }
+
+        // Read transition type indices.
+        let type_indices = reader.take(header.timecnt)?;
+
+        // Read local time type offsets.
+        let mut offsets = Vec::with_capacity(header.typecnt);
+        for _ in 0..header.typecnt {
+            let record = reader.take(6)?;
+            offsets.push(i32::from_be_bytes(record[..4].try_into().ok()?));
+        }
+
+        let mut transitions = Vec::with_capacity(header.timecnt);
+        for (time, index) in times.into_iter().zip(type_indices) {
+            transitions.push((time, *offsets.get(*index as usize)?));
+        }
+
+        // Parse the POSIX TZ footer of version 2+ files.
+        let rule = if time_size == 8 {
+            reader.take(header.charcnt + header.leapcnt * 12)?;
+            reader.take(header.isstdcnt + header.isutcnt)?;
+            let footer = std::str::from_utf8(reader.0).ok()?;
+            let footer = footer.trim_matches('\n');
+            PosixRule::parse(footer)
+        } else {
+            None
+        };
+
+        Some(Self { transitions, rule, name: name.into(), initial_offset: *offsets.first()? })
+    }
+}
+
+/// TZif file header.
+struct Header {
+    version: u8,
+    isutcnt: usize,
+    isstdcnt: usize,
+    leapcnt: usize,
+    timecnt: usize,
+    typecnt: usize,
+    charcnt: usize,
+}
+
+impl Header {
+    fn parse(data: &[u8]) -> Option<Self> {
+        if data.get(..4)? != TZIF_MAGIC || data.len() < HEADER_LEN {
+            return None;
+        }
+
+        let count = |index: usize| {
+            let start = 20 + index * 4;
+            u32::from_be_bytes(data[start..start + 4].try_into().unwrap()) as usize
+        };
+
+        Some(Self {
+            version: data[4],
+            isutcnt: count(0),
+            isstdcnt: count(1),
+            leapcnt: count(2),
+            timecnt: count(3),
+            typecnt: count(4),
+            charcnt: count(5),
+        })
+    }
+
+    /// Length of the data block following the header.
+    fn data_len(&self, time_size: usize) -> usize {
+        self.timecnt * time_size
+            + self.timecnt
+            + self.typecnt * 6
+            + self.charcnt
+            + self.leapcnt * (time_size + 4)
+            + self.isstdcnt
+            + self.isutcnt
+    }
+}
+
+/// Sequential byte reader.
+struct Reader<'a>(&'a [u8]);
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, len: usize) -> Option<&'a [u8]> {
+        let (head, tail) = self.0.split_at_checked(len)?;
+        self.0 = tail;
+        Some(head)
+    }
+}
+
+/// POSIX TZ string, like `CET-1CEST,M3.5.0,M10.5.0/3`.
+#[derive(Clone, PartialEq, Eq, Debug)]
+struct PosixRule {
+    std_offset: i32,
+    dst: Option<DstRule>,
+}
+
+impl PosixRule {
+    fn parse(s: &str) -> Option<Self> {
+        let mut parser = PosixParser(s);
+
+        parser.name()?;
+        let std_offset = -parser.time()?;
+
+        // Zones without DST only have a standard time.
+        if parser.0.is_empty() {
+            return Some(Self { std_offset, dst: None });
+        }
+
+        parser.name()?;
+        let dst_offset = match parser.0.starts_with(',') {
+            true => std_offset + 60 * 60,
+            false => -parser.time()?,
+        };
+
+        parser.expect(',')?;
+        let start = parser.transition()?;
+        parser.expect(',')?;
+        let end = parser.transition()?;
+
+        let dst = DstRule { dst_offset, start, end };
+        Some(Self { std_offset, dst: Some(dst) })
+    }
+
+    /// Get the UTC offset in seconds at a unix timestamp.
+    fn offset_at(&self, unix_time: i64) -> i32 {
+        let dst = match &self.dst {
+            Some(dst) => dst,
+            None => return self.std_offset,
+        };
+
+        // Get the local year, to find the year's transitions.
+        let local = OffsetDateTime::from_unix_timestamp(unix_time + self.std_offset as i64);
+        let year = match local {
+            Ok(local) => local.year(),
+            Err(_) => return self.std_offset,
+        };
+
+        // Transition times are specified in the local time before the transition.
+        let start = dst.start.local_time(year) - self.std_offset as i64;
+        let end = dst.end.local_time(year) - dst.dst_offset as i64;
+
+        // Handle DST periods spanning the new year on the southern hemisphere.
+        let is_dst = if start < end {
+            start <= unix_time && unix_time < end
+        } else {
+            !(end <= unix_time && unix_time < start)
+        };
+
+        if is_dst { dst.dst_offset } else { self.std_offset }
+    }
+}
+
+/// Daylight saving time part of a POSIX TZ string.
+#[derive(Clone, PartialEq, Eq, Debug)]
+struct DstRule {
+    dst_offset: i32,
+    start: Transition,
+    end: Transition,
+}
+
+/// Yearly DST transition.
+#[derive(Clone, PartialEq, Eq, Debug)]
+struct Transition {
+    day: TransitionDay,
+    /// Seconds past local midnight.
+    time: i32,
+}
+
+impl Transition {
+    /// Local time of this transition in the specified year, as unix timestamp.
+    fn local_time(&self, year: i32) -> i64 {
+        let midnight = self.day.date(year).midnight().assume_utc().unix_timestamp();
+        midnight + self.time as i64
+    }
+}
+
+/// Day of a yearly DST transition.
+#[derive(Clone, PartialEq, Eq, Debug)]
+enum TransitionDay {
+    /// Julian day `1..=365`, never counting February 29th.
+    Julian(u16),
+    /// Zero-based day of the year `0..=365`.
+    Ordinal(u16),
+    /// Day `d` (0 = Sunday) of week `w` (5 = last) in month `m`.
+    MonthWeekDay(Month, u8, Weekday),
+}
+
+impl TransitionDay {
+    fn date(&self, year: i32) -> Date {
+        let fallback = Date::from_ordinal_date(year, 1).unwrap_or(Date::MIN);
+        match self {
+            Self::Julian(day) => {
+                let leap_day = util::is_leap_year(year) && *day >= 60;
+                let ordinal = *day + leap_day as u16;
+                Date::from_ordinal_date(year, ordinal).unwrap_or(fallback)
+            },
+            Self::Ordinal(day) => Date::from_ordinal_date(year, day + 1).unwrap_or(fallback),
+            Self::MonthWeekDay(month, week, weekday) => {
+                let first = match Date::from_calendar_date(year, *month, 1) {
+                    Ok(first) => first,
+                    Err(_) => return fallback,
+                };
+
+                // Find the first matching weekday of the month.
+                let offset = weekday.number_days_from_sunday() as i16
+                    - first.weekday().number_days_from_sunday() as i16;
+                let mut day = 1 + offset.rem_euclid(7) as u8 + (week - 1) * 7;
+
+                // Use the last matching weekday for the fifth week.
+                let days_in_month = util::days_in_month(*month, year);
+                while day > days_in_month {
+                    day -= 7;
+                }
+
+                first.replace_day(day).unwrap_or(first)
+            },
+        }
+    }
+}
+
+/// Parser for POSIX TZ strings.
+struct PosixParser<'a>(&'a str);
+
+impl PosixParser<'_> {
+    /// Parse a timezone abbreviation.
+    fn name(&mut self) -> Option<&str> {
+        let (name, rest) = match self.0.strip_prefix('<') {
+            Some(quoted) => {
+                let end = quoted.find('>')?;
+                (&quoted[..end], &quoted[end + 1..])
+            },
+            None => {
+                let end = self.0.find(|c: char| !c.is_ascii_alphabetic()).unwrap_or(self.0.len());
+                self.0.split_at(end)
+            },
+        };
+
+        if name.is_empty() {
+            return None;
+        }
+
+        self.0 = rest;
+        Some(name)
+    }
+
+    /// Parse a signed `[+-]hh[:mm[:ss]]` time in seconds.
+    fn time(&mut self) -> Option<i32> {
+        let sign = match self.0.as_bytes().first()? {
+            b'-' => -1,
+            b'+' => 1,
+            _ => 1,
+        };
+        self.0 = self.0.trim_start_matches(['+', '-']);
+
+        let mut seconds = 0;
+        for (i, multiplier) in [3600, 60, 1].into_iter().enumerate() {
+            if i > 0 && self.expect(':').is_none() {
+                break;
+            }
+            seconds += self.number()? * multiplier;
+        }
+
+        Some(sign * seconds)
+    }
+
+    /// Parse a transition like `M3.2.0/2`.
+    fn transition(&mut self) -> Option<Transition> {
+        let day = if self.expect('M').is_some() {
+            let month = Month::try_from(self.number()? as u8).ok()?;
+            self.expect('.')?;
+            let week = self.number()? as u8;
+            self.expect('.')?;
+            let weekday = Weekday::Sunday.nth_next(self.number()? as u8);
+
+            if !(1..=5).contains(&week) {
+                return None;
+            }
+
+            TransitionDay::MonthWeekDay(month, week, weekday)
+        } else if self.expect('J').is_some() {
+            TransitionDay::Julian(self.number()?.clamp(1, 365) as u16)
+        } else {
+            TransitionDay::Ordinal(self.number()?.clamp(0, 365) as u16)
+        };
+
+        // Transitions default to 02:00 local time.
+        let time = match self.expect('/') {
+            Some(()) => self.time()?,
+            None => 2 * 60 * 60,
+        };
+
+        Some(Transition { day, time })
+    }
+
+    /// Parse an unsigned decimal number.
+    fn number(&mut self) -> Option<i32> {
+        let end = self.0.find(|c: char| !c.is_ascii_digit()).unwrap_or(self.0.len());
+        let (number, rest) = self.0.split_at(end);
+        self.0 = rest;
+        number.parse().ok()
+    }
+
+    /// Consume an expected character.
+    fn expect(&mut self, c: char) -> Option<()> {
+        self.0 = self.0.strip_prefix(c)?;
+        Some(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use time::macros::datetime;
+
+    use super::*;
+
+    /// 2024-03-31T01:00:00Z, start of CEST in 2024.
+    const CEST_2024: i64 = 1_711_846_800;
+
+    /// 2024-10-27T01:00:00Z, end of CEST in 2024.
+    const CET_2024: i64 = 1_729_990_800;
+
+    /// Build a version 2 TZif file.
+    fn tzif(transitions: &[(i64, u8)], offsets: &[i32], footer: &str) -> Vec<u8> {
+        let header = |timecnt: usize, typecnt: usize| {
+            let mut header = b"TZif2".to_vec();
+            header.extend([0; 15]);
+            for count in [0, 0, 0, timecnt, typecnt, 1] {
+                header.extend((count as u32).to_be_bytes());
+            }
+            header
+        };
+
+        // Add a minimal legacy block, which is skipped for version 2+ files.
+        let mut data = header(0, 1);
+        data.extend([0; 7]);
+
+        data.extend(header(transitions.len(), offsets.len()));
+        for (time, _) in transitions {
+            data.extend(time.to_be_bytes());
+        }
+        data.extend(transitions.iter().map(|(_, index)| *index));
+        for offset in offsets {
+            data.extend(offset.to_be_bytes());
+            data.extend([0, 0]);
+        }
+        data.push(0);
+
+        data.extend(format!("\n{footer}\n").bytes());
+        data
+    }
+
+    /// Central European timezone with transitions for 2024.
+    fn berlin() -> TimeZone {
+        let data =
+            tzif(&[(CEST_2024, 1), (CET_2024, 0)], &[3600, 7200], "CET-1CEST,M3.5.0,M10.5.0/3");
+        TimeZone::parse("Europe/Berlin", &data).unwrap()
+    }
+
+    /// Get a timezone with only a POSIX TZ rule.
+    fn posix(rule: &str) -> TimeZone {
+        TimeZone::parse("posix", &tzif(&[], &[0], rule)).unwrap()
+    }
+
+    fn offset_at(zone: &TimeZone, time: OffsetDateTime) -> i32 {
+        zone.offset_at(time.unix_timestamp()).whole_seconds()
+    }
+
+    #[test]
+    fn parse_tzif() {
+        let berlin = berlin();
+        assert_eq!(berlin.name(), "Europe/Berlin");
+        assert_eq!(berlin.transitions, [(CEST_2024, 7200), (CET_2024, 3600)]);
+        assert_eq!(berlin.initial_offset, 3600);
+        assert!(berlin.rule.as_ref().is_some_and(|rule| rule.dst.is_some()));
+    }
+
+    #[test]
+    fn parse_invalid_tzif() {
+        assert_eq!(TimeZone::parse("invalid", b"TZif"), None);
+        assert_eq!(TimeZone::parse("invalid", &[0; HEADER_LEN]), None);
+
+        // Truncated data block.
+        let data = tzif(&[(CEST_2024, 1)], &[3600, 7200], "CET-1");
+        assert_eq!(TimeZone::parse("invalid", &data[..data.len() - 20]), None);
+    }
+
+    #[test]
+    fn load_rejects_paths() {
+        for name in ["", "/etc/localtime", "../../etc/passwd", "Europe/../../etc/passwd"] {
+            assert!(matches!(TimeZone::load(name), Err(Error::InvalidTimeZone(_))), "{name:?}");
+        }
+    }
+
+    #[test]
+    fn tzif_offsets() {
+        let berlin = berlin();
+
+        assert_eq!(offset_at(&berlin, datetime!(2024-01-15 12:00 UTC)), 3600);
+        assert_eq!(berlin.offset_at(CEST_2024 - 1).whole_seconds(), 3600);
+        assert_eq!(berlin.offset_at(CEST_2024).whole_seconds(), 7200);
+        assert_eq!(offset_at(&berlin, datetime!(2024-07-01 12:00 UTC)), 7200);
+        assert_eq!(berlin.offset_at(CET_2024 - 1).whole_seconds(), 7200);
+        assert_eq!(berlin.offset_at(CET_2024).whole_seconds(), 3600);
+    }
+
+    #[test]
+    fn footer_offsets() {
+        let berlin = berlin();
+
+        // Times beyond the last transition use the POSIX TZ footer.
+        assert_eq!(offset_at(&berlin, datetime!(2024-12-01 12:00 UTC)), 3600);
+        assert_eq!(offset_at(&berlin, datetime!(2025-03-30 00:59:59 UTC)), 3600);
+        assert_eq!(offset_at(&berlin, datetime!(2025-03-30 01:00 UTC)), 7200);
+        assert_eq!(offset_at(&berlin, datetime!(2025-10-26 00:59:59 UTC)), 7200);
+        assert_eq!(offset_at(&berlin, datetime!(2025-10-26 01:00 UTC)), 3600);
+        assert_eq!(offset_at(&berlin, datetime!(2099-07-01 12:00 UTC)), 7200);
+    }
+
+    #[test]
+    fn posix_offsets() {
+        // Fixed offset without DST.
+        let kolkata = posix("IST-5:30");
+        assert_eq!(offset_at(&kolkata, datetime!(2025-01-01 00:00 UTC)), 19800);
+        assert_eq!(offset_at(&kolkata, datetime!(2025-07-01 00:00 UTC)), 19800);
+
+        // Southern hemisphere DST spanning the new year.
+        let sydney = posix("AEST-10AEDT,M10.1.0,M4.1.0/3");
+        assert_eq!(offset_at(&sydney, datetime!(2025-01-15 00:00 UTC)), 39600);
+        assert_eq!(offset_at(&sydney, datetime!(2025-07-15 00:00 UTC)), 36000);
+        assert_eq!(offset_at(&sydney, datetime!(2025-12-15 00:00 UTC)), 39600);
+
+        // Half-hour DST shift with quoted names.
+        let lord_howe = posix("<+1030>-10:30<+11>-11,M10.1.0,M4.1.0");
+        assert_eq!(offset_at(&lord_howe, datetime!(2025-01-15 00:00 UTC)), 39600);
+        assert_eq!(offset_at(&lord_howe, datetime!(2025-07-15 00:00 UTC)), 37800);
+
+        // Negative offsets with DST one hour ahead by default.
+        let new_york = posix("EST5EDT,M3.2.0,M11.1.0");
+        assert_eq!(offset_at(&new_york, datetime!(2025-01-15 12:00 UTC)), -18000);
+        assert_eq!(offset_at(&new_york, datetime!(2025-07-15 12:00 UTC)), -14400);
+    }
+
+    #[test]
+    fn resolve_unambiguous() {
+        let berlin = berlin();
+
+        let summer = berlin.resolve(datetime!(2025-07-01 12:00));
+        assert_eq!(summer, datetime!(2025-07-01 10:00 UTC));
+        assert_eq!(summer.offset().whole_seconds(), 7200);
+
+        let winter = berlin.resolve(datetime!(2024-01-15 12:00));
+        assert_eq!(winter, datetime!(2024-01-15 11:00 UTC));
+        assert_eq!(winter.offset().whole_seconds(), 3600);
+    }
+
+    #[test]
+    fn resolve_gap() {
+        // Skipped times are shifted forward by the transition's length.
+        let berlin = berlin();
+        for (local, utc) in [
+            (datetime!(2024-03-31 02:30), datetime!(2024-03-31 01:30 UTC)),
+            (datetime!(2025-03-30 02:30), datetime!(2025-03-30 01:30 UTC)),
+        ] {
+            let resolved = berlin.resolve(local);
+            assert_eq!(resolved, utc);
+            assert_eq!((resolved.hour(), resolved.minute()), (3, 30));
+        }
+
+        let lord_howe = posix("<+1030>-10:30<+11>-11,M10.1.0,M4.1.0");
+        let resolved = lord_howe.resolve(datetime!(2025-10-05 02:15));
+        assert_eq!(resolved, datetime!(2025-10-04 15:45 UTC));
+        assert_eq!((resolved.hour(), resolved.minute()), (2, 45));
+    }
+
+    #[test]
+    fn resolve_overlap() {
+        // Ambiguous times use the offset before the transition.
+        let berlin = berlin();
+        for (local, utc) in [
+            (datetime!(2024-10-27 02:30), datetime!(2024-10-27 00:30 UTC)),
+            (datetime!(2025-10-26 02:30), datetime!(2025-10-26 00:30 UTC)),
+        ] {
+            let resolved = berlin.resolve(local);
+            assert_eq!(resolved, utc);
+            assert_eq!(resolved.offset().whole_seconds(), 7200);
+        }
+
+        let sydney = posix("AEST-10AEDT,M10.1.0,M4.1.0/3");
+        let resolved = sydney.resolve(datetime!(2025-04-06 02:30));
+        assert_eq!(resolved, datetime!(2025-04-05 15:30 UTC));
+    }
+}
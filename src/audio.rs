@@ -19,6 +19,9 @@ const ALARM_AUDIO: &[u8] = include_bytes!("../alarm.flac");
 /// alarm, so we shorten it by 680ms.
 const ALARM_AUDIO_LENGTH: Duration = Duration::from_millis(1500);
 
+/// Duration of the reminder chime played by [`AlarmSound::chime`].
+pub const CHIME_DURATION: Duration = ALARM_AUDIO_LENGTH;
+
 /// Alarm audio playback.
 pub struct AlarmSound {
     _stream: OutputStream,
@@ -32,17 +35,34 @@ impl AlarmSound {
     /// the returned [`AlarmSound`] is dropped or [`AlarmSound::stop`] is called
     /// on it.
     pub fn play() -> Result<Self, Error> {
+        Self::start(true)
+    }
+
+    /// Play the alarm sound once, as a short reminder chime.
+    ///
+    /// Playback ends after [`CHIME_DURATION`], but the returned [`AlarmSound`]
+    /// must be kept alive until then.
+    pub fn chime() -> Result<Self, Error> {
+        Self::start(false)
+    }
+
+    /// Start playing the alarm sound.
+    fn start(repeat: bool) -> Result<Self, Error> {
         // Parse the audio source file.
         let stream = OutputStreamBuilder::open_default_stream()?;
         let audio_buffer = Cursor::new(ALARM_AUDIO);
         let source = Decoder::new(audio_buffer).unwrap();
 
-        // Adjust length and repeat infinitely.
-        let source = source.take_duration(ALARM_AUDIO_LENGTH).repeat_infinite();
-
         // Create a sink to allow playback control.
         let sink = Sink::connect_new(stream.mixer());
-        sink.append(source);
+
+        // Adjust length and optionally repeat infinitely.
+        let source = source.take_duration(ALARM_AUDIO_LENGTH);
+        if repeat {
+            sink.append(source.repeat_infinite());
+        } else {
+            sink.append(source);
+        }
 
         Ok(Self { _stream: stream, sink })
     }
@@ -1,6 +1,6 @@
 //! Rezz DBus interface.
 
-use rezz::Alarm;
+use rezz::{Alarm, Timer};
 use zbus::proxy;
 
 #[proxy(
@@ -11,8 +11,42 @@ use zbus::proxy;
 pub trait Rezz {
     async fn add_alarm(&self, alarm: Alarm) -> zbus::Result<()>;
 
+    async fn add_alarms(&self, alarms: Vec<Alarm>) -> zbus::Result<()>;
+
+    async fn replace_alarms(&self, alarms: Vec<Alarm>) -> zbus::Result<()>;
+
     async fn remove_alarm(&self, id: String) -> zbus::Result<()>;
 
+    async fn remove_alarms(&self, ids: Vec<String>) -> zbus::Result<()>;
+
+    async fn clear_alarms(&self) -> zbus::Result<()>;
+
+    async fn clear_all_alarms(&self) -> zbus::Result<()>;
+
+    async fn set_enabled(&self, id: String, enabled: bool) -> zbus::Result<()>;
+
+    async fn set_exceptions(&self, id: String, dates: Vec<i32>) -> zbus::Result<()>;
+
+    async fn skip_next(&self, id: String) -> zbus::Result<()>;
+
+    async fn all_alarms(&self) -> zbus::Result<Vec<Alarm>>;
+
+    async fn start_timer(
+        &self,
+        id: String,
+        duration_ms: u64,
+        ring_seconds: u32,
+    ) -> zbus::Result<()>;
+
+    async fn pause_timer(&self, id: String) -> zbus::Result<()>;
+
+    async fn resume_timer(&self, id: String) -> zbus::Result<()>;
+
+    async fn remove_timer(&self, id: String) -> zbus::Result<()>;
+
     #[zbus(property)]
     fn alarms(&self) -> zbus::Result<Vec<Alarm>>;
+
+    #[zbus(property)]
+    fn timers(&self) -> zbus::Result<Vec<Timer>>;
 }
@@ -2,9 +2,13 @@
 
 use std::io::Error as IoError;
 
+use rezz::ServiceError;
+
 /// User-facing errors.
 #[derive(thiserror::Error, Debug)]
 pub enum Error {
+    #[error("access denied: {0}")]
+    AccessDenied(String),
     #[error("alarm with id {0:?} exists already")]
     AlarmExists(String),
     #[error("no alarm found with id {0:?}")]
@@ -14,7 +18,52 @@ pub enum Error {
     #[error("audio stream error: {0}")]
     AudioStream(#[from] rodio::StreamError),
     #[error("dbus error: {0}")]
-    DBus(#[from] zbus::Error),
+    DBus(zbus::Error),
+    #[error("invalid timer duration: {0}")]
+    InvalidDuration(String),
+    #[error("invalid alarm ID: {0}")]
+    InvalidId(String),
+    #[error("invalid ring duration: {0}")]
+    InvalidRingDuration(String),
+    #[error("invalid late policy {0:?}, expected remaining, full or skip")]
+    InvalidLatePolicy(String),
+    #[error("invalid alarm time: {0}")]
+    InvalidTime(String),
+    #[error("invalid vibration pattern {0:?}, expected ON_MS:OFF_MS")]
+    InvalidVibrationPattern(String),
     #[error("io error: {0}")]
     Io(#[from] IoError),
+    #[error("alarm quota exceeded: {0}")]
+    QuotaExceeded(String),
+    #[error("rtc error: {0}")]
+    Rtc(String),
+    #[error("timer with id {0:?} exists already")]
+    TimerExists(String),
+    #[error("no timer found with id {0:?}")]
+    TimerNotFound(String),
+}
+
+impl From<zbus::Error> for Error {
+    fn from(err: zbus::Error) -> Self {
+        match ServiceError::from(err) {
+            ServiceError::AccessDenied(msg) => Self::AccessDenied(msg),
+            ServiceError::AlarmExists(id) => Self::AlarmExists(id),
+            ServiceError::AlarmNotFound(id) => Self::AlarmNotFound(id),
+            ServiceError::TimerExists(id) => Self::TimerExists(id),
+            ServiceError::TimerNotFound(id) => Self::TimerNotFound(id),
+            ServiceError::InvalidId(msg) => Self::InvalidId(msg),
+            ServiceError::InvalidTime(msg) => Self::InvalidTime(msg),
+            ServiceError::InvalidDuration(msg) => Self::InvalidDuration(msg),
+            ServiceError::InvalidRingDuration(msg) => Self::InvalidRingDuration(msg),
+            ServiceError::QuotaExceeded(msg) => Self::QuotaExceeded(msg),
+            ServiceError::Rtc(msg) => Self::Rtc(msg),
+            // Map access denied errors of the DBus daemon or older rezz versions.
+            ServiceError::ZBus(zbus::Error::MethodError(name, msg, _))
+                if name.as_str() == "org.freedesktop.DBus.Error.AccessDenied" =>
+            {
+                Self::AccessDenied(msg.unwrap_or_default())
+            },
+            ServiceError::ZBus(err) => Self::DBus(err),
+        }
+    }
 }
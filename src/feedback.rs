@@ -0,0 +1,161 @@
+//! Themed event feedback through feedbackd.
+
+use std::collections::HashMap;
+
+use tokio::runtime::Handle;
+use zbus::zvariant::Value;
+use zbus::{Connection, proxy};
+
+use crate::error::Error;
+
+/// Feedback theme event for ringing alarms.
+pub const ALARM_EVENT: &str = "alarm-clock-elapsed";
+
+/// Application ID used for per-application feedback settings.
+const APP_ID: &str = "catacomb.Alarm";
+
+/// Feedback timeout for looping the event until it is ended.
+const LOOP_TIMEOUT: i32 = 0;
+
+/// DBus error for calls to services which are not running or activatable.
+const SERVICE_UNKNOWN_ERROR: &str = "org.freedesktop.DBus.Error.ServiceUnknown";
+
+#[proxy(
+    interface = "org.sigxcpu.Feedback",
+    default_service = "org.sigxcpu.Feedback",
+    default_path = "/org/sigxcpu/Feedback"
+)]
+trait Feedback {
+    fn trigger_feedback(
+        &self,
+        app_id: &str,
+        event: &str,
+        hints: HashMap<&str, Value<'_>>,
+        timeout: i32,
+    ) -> zbus::Result<u32>;
+
+    fn end_feedback(&self, id: u32) -> zbus::Result<()>;
+}
+
+/// Running feedbackd event.
+///
+/// This uses the `org.sigxcpu.Feedback` service on the session bus, which
+/// plays sound, vibration and LED feedback according to the active profile.
+///
+/// The feedback is ended once this is dropped or [`EventFeedback::end`] is
+/// called on it.
+pub struct EventFeedback {
+    proxy: FeedbackProxy<'static>,
+    id: Option<u32>,
+}
+
+impl EventFeedback {
+    /// Start looping the feedback for a theme event.
+    ///
+    /// Returns `None` if feedbackd is not available on the session bus.
+    pub async fn trigger(event: &str) -> Result<Option<Self>, Error> {
+        let connection = Connection::session().await?;
+        Self::trigger_on(&connection, event).await
+    }
+
+    /// Start looping the feedback for a theme event on a specific connection.
+    pub(crate) async fn trigger_on(
+        connection: &Connection,
+        event: &str,
+    ) -> Result<Option<Self>, Error> {
+        let proxy = FeedbackProxy::new(connection).await?;
+
+        let id = match proxy.trigger_feedback(APP_ID, event, HashMap::new(), LOOP_TIMEOUT).await {
+            Ok(id) => id,
+            Err(zbus::Error::MethodError(name, ..)) if name.as_str() == SERVICE_UNKNOWN_ERROR => {
+                return Ok(None);
+            },
+            Err(err) => return Err(err.into()),
+        };
+
+        Ok(Some(Self { proxy, id: Some(id) }))
+    }
+
+    /// Stop the feedback.
+    pub async fn end(mut self) {
+        if let Some(id) = self.id.take() {
+            let _ = self.proxy.end_feedback(id).await;
+        }
+    }
+}
+
+impl Drop for EventFeedback {
+    fn drop(&mut self) {
+        let id = match self.id.take() {
+            Some(id) => id,
+            None => return,
+        };
+
+        // End the feedback in the background, since feedbackd would otherwise
+        // loop it forever.
+        if let Ok(runtime) = Handle::try_current() {
+            let proxy = self.proxy.clone();
+            runtime.spawn(async move {
+                let _ = proxy.end_feedback(id).await;
+            });
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+    use crate::testing::{self, FEEDBACK_ID as ID, FeedbackCall};
+
+    #[tokio::test]
+    async fn trigger() {
+        let (client, _server, calls) = testing::mock_feedback(false).await;
+
+        let feedback = EventFeedback::trigger_on(&client, ALARM_EVENT).await.unwrap();
+        assert_eq!(feedback.unwrap().id, Some(ID));
+
+        let expected = FeedbackCall::Trigger {
+            app_id: APP_ID.into(),
+            event: ALARM_EVENT.into(),
+            timeout: LOOP_TIMEOUT,
+        };
+        assert_eq!(calls.lock().unwrap().first(), Some(&expected));
+    }
+
+    #[tokio::test]
+    async fn trigger_missing() {
+        let (client, _server, calls) = testing::mock_feedback(true).await;
+
+        let feedback = EventFeedback::trigger_on(&client, ALARM_EVENT).await.unwrap();
+        assert!(feedback.is_none());
+        assert!(calls.lock().unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn end() {
+        let (client, _server, calls) = testing::mock_feedback(false).await;
+        let feedback = EventFeedback::trigger_on(&client, ALARM_EVENT).await.unwrap().unwrap();
+
+        feedback.end().await;
+
+        assert_eq!(calls.lock().unwrap().last(), Some(&FeedbackCall::End(ID)));
+    }
+
+    #[tokio::test]
+    async fn end_on_drop() {
+        let (client, _server, calls) = testing::mock_feedback(false).await;
+        let feedback = EventFeedback::trigger_on(&client, ALARM_EVENT).await.unwrap().unwrap();
+
+        drop(feedback);
+
+        // Wait for the background call to reach the server.
+        let ended = async {
+            while calls.lock().unwrap().last() != Some(&FeedbackCall::End(ID)) {
+                tokio::time::sleep(Duration::from_millis(10)).await;
+            }
+        };
+        tokio::time::timeout(Duration::from_secs(5), ended).await.expect("feedback not ended");
+    }
+}
@@ -1,9 +1,19 @@
+use std::collections::VecDeque;
+use std::future;
+use std::ops::Deref;
+use std::pin::Pin;
+use std::str::FromStr;
+use std::sync::{Arc, LazyLock, Mutex, MutexGuard, PoisonError};
+use std::task::{Context, Poll};
 use std::time::{Duration, SystemTime};
 
-use rezz::Alarm;
-use tokio_stream::StreamExt;
+use futures_util::stream::{self, Stream, StreamExt};
+use rezz::{Alarm, LatePolicy as AlarmLatePolicy, Timer};
+use time::Date;
+use tokio::sync::Mutex as AsyncMutex;
+use tokio::time::Instant;
 use zbus::Connection;
-use zbus::proxy::PropertyStream;
+use zbus::proxy::{CacheProperties, OwnerChangedStream, PropertyStream};
 
 use crate::dbus::RezzProxy;
 use crate::error::Error;
@@ -11,46 +21,408 @@ use crate::error::Error;
 pub mod audio;
 mod dbus;
 pub mod error;
+pub mod feedback;
+pub mod notify;
+pub mod output;
+pub mod reminder;
+#[cfg(test)]
+mod testing;
 mod timer;
+pub mod vibrate;
+
+/// Delay after which an alarm is considered late.
+///
+/// Since waking up from suspend can take a while, alarms are only considered
+/// late once they're discovered this long after their ring time.
+const LATE_THRESHOLD: Duration = Duration::from_secs(30);
+
+/// Delay before retrying to subscribe to a restarted rezz daemon.
+///
+/// The delay is doubled after every failed attempt, up to
+/// [`MAX_RECONNECT_DELAY`].
+const RECONNECT_DELAY: Duration = Duration::from_secs(1);
+
+/// Maximum delay between attempts to subscribe to a restarted rezz daemon.
+const MAX_RECONNECT_DELAY: Duration = Duration::from_secs(60);
+
+/// ID suffix of snoozed copies of repeating alarms.
+pub const SNOOZE_ID_SUFFIX: &str = "#snooze";
+
+/// Alarm interface shared by all [`Alarms`].
+static ALARMS: LazyLock<AlarmClient> = LazyLock::new(AlarmClient::new);
+
+/// Timer interface shared by all [`Timers`].
+static TIMERS: LazyLock<TimerClient> = LazyLock::new(|| ALARMS.timers());
 
 /// Primary alarm interface.
+///
+/// All instances share a single DBus connection, which is established on first
+/// use and re-established automatically once it is lost. See [`AlarmClient`]
+/// for the available methods.
+///
+/// Since the shared connection is bound to the tokio runtime it was created
+/// on, applications using multiple runtimes should create an [`AlarmClient`]
+/// for each runtime instead.
+#[derive(Copy, Clone, Default, Debug)]
 pub struct Alarms;
 
-impl Alarms {
+impl Deref for Alarms {
+    type Target = AlarmClient;
+
+    fn deref(&self) -> &AlarmClient {
+        &ALARMS
+    }
+}
+
+/// Alarm interface owning its DBus connection.
+///
+/// Clones share the same connection.
+#[derive(Clone, Default, Debug)]
+pub struct AlarmClient {
+    rezz: Rezz,
+}
+
+impl AlarmClient {
+    /// Create a new alarm interface.
+    ///
+    /// The interface connects to the system bus on first use, reconnecting
+    /// automatically once the connection is lost.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Create a new alarm interface using an existing DBus connection.
+    ///
+    /// Calls will fail once the connection is lost.
+    pub fn with_connection(connection: Connection) -> Self {
+        Self { rezz: Rezz { connection: Some(connection), ..Default::default() } }
+    }
+
+    /// Get a timer interface sharing this interface's DBus connection.
+    pub fn timers(&self) -> TimerClient {
+        TimerClient { rezz: self.rezz.clone() }
+    }
+
     /// Add a new alarm.
     pub async fn add(&self, alarm: Alarm) -> Result<(), Error> {
-        let connection = Connection::system().await?;
-        let rezz = RezzProxy::new(&connection).await?;
-        rezz.add_alarm(alarm).await?;
-        Ok(())
+        self.rezz
+            .call(|rezz| {
+                let alarm = alarm.clone();
+                async move { rezz.add_alarm(alarm).await }
+            })
+            .await
+    }
+
+    /// Add multiple new alarms at once.
+    ///
+    /// If any of the alarms cannot be added, no alarm is added.
+    pub async fn add_all(&self, alarms: Vec<Alarm>) -> Result<(), Error> {
+        self.rezz
+            .call(|rezz| {
+                let alarms = alarms.clone();
+                async move { rezz.add_alarms(alarms).await }
+            })
+            .await
+    }
+
+    /// Add multiple alarms at once, replacing existing alarms with the same
+    /// IDs.
+    ///
+    /// If any of the alarms cannot be added, no alarm is added or removed.
+    pub async fn replace_all(&self, alarms: Vec<Alarm>) -> Result<(), Error> {
+        self.rezz
+            .call(|rezz| {
+                let alarms = alarms.clone();
+                async move { rezz.replace_alarms(alarms).await }
+            })
+            .await
     }
 
     /// Remove an existing alarm.
     pub async fn remove(&self, id: String) -> Result<(), Error> {
-        let connection = Connection::system().await?;
-        let rezz = RezzProxy::new(&connection).await?;
-        rezz.remove_alarm(id).await?;
-        Ok(())
+        self.rezz
+            .call(|rezz| {
+                let id = id.clone();
+                async move { rezz.remove_alarm(id).await }
+            })
+            .await
+    }
+
+    /// Remove multiple existing alarms at once.
+    ///
+    /// If any of the alarms cannot be removed, no alarm is removed.
+    pub async fn remove_all(&self, ids: Vec<String>) -> Result<(), Error> {
+        self.rezz
+            .call(|rezz| {
+                let ids = ids.clone();
+                async move { rezz.remove_alarms(ids).await }
+            })
+            .await
+    }
+
+    /// Remove all alarms of the current user.
+    pub async fn clear(&self) -> Result<(), Error> {
+        self.rezz.call(|rezz| async move { rezz.clear_alarms().await }).await
+    }
+
+    /// Remove the alarms of all users.
+    ///
+    /// This requires authorization for rezz's `clear-all` polkit action.
+    pub async fn clear_all(&self) -> Result<(), Error> {
+        self.rezz.call(|rezz| async move { rezz.clear_all_alarms().await }).await
+    }
+
+    /// Postpone a ringing alarm.
+    ///
+    /// The alarm is replaced by a copy with the same ID, which rings again
+    /// after `delay`.
+    ///
+    /// Repeating alarms are kept for their next occurrence instead, with a
+    /// separate copy using the ID suffix [`SNOOZE_ID_SUFFIX`].
+    pub async fn snooze(&self, alarm: Alarm, delay: Duration) -> Result<(), Error> {
+        let ring_time = SystemTime::now() + delay;
+        let unix_time_ms = ring_time.duration_since(SystemTime::UNIX_EPOCH).unwrap_or_default();
+        let mut snoozed = Alarm::from_unix_time_ms(
+            &alarm.id,
+            unix_time_ms.as_millis() as i64,
+            alarm.ring_seconds,
+        );
+        snoozed.kind = alarm.kind;
+        snoozed.late_policy = alarm.late_policy;
+
+        if alarm.repeats() {
+            snoozed.id.push_str(SNOOZE_ID_SUFFIX);
+        }
+
+        // Replace atomically, to keep the alarm if the snooze is rejected.
+        self.replace_all(vec![snoozed]).await
+    }
+
+    /// Dismiss an alarm which has finished ringing.
+    ///
+    /// The alarm is removed, unless it repeats and is kept for its next
+    /// occurrence. Missing alarms are ignored, since rezz might have removed
+    /// them already.
+    pub async fn dismiss(&self, alarm: &Alarm) -> Result<(), Error> {
+        if alarm.repeats() {
+            return Ok(());
+        }
+
+        match self.remove(alarm.id.clone()).await {
+            Ok(()) | Err(Error::AlarmNotFound(_)) => Ok(()),
+            Err(err) => Err(err),
+        }
+    }
+
+    /// Enable or disable an existing alarm.
+    ///
+    /// Disabled alarms are kept, but never ring.
+    pub async fn set_enabled(&self, id: String, enabled: bool) -> Result<(), Error> {
+        self.rezz
+            .call(|rezz| {
+                let id = id.clone();
+                async move { rezz.set_enabled(id, enabled).await }
+            })
+            .await
+    }
+
+    /// Replace the local dates on which an alarm does not ring.
+    ///
+    /// Repeating alarms skip their occurrences on these dates, while other
+    /// alarms are disabled if they would ring on one of them.
+    pub async fn set_exceptions(&self, id: String, dates: Vec<Date>) -> Result<(), Error> {
+        let dates: Vec<_> = dates.into_iter().map(Date::to_julian_day).collect();
+        self.rezz
+            .call(|rezz| {
+                let (id, dates) = (id.clone(), dates.clone());
+                async move { rezz.set_exceptions(id, dates).await }
+            })
+            .await
+    }
+
+    /// Skip the next occurrence of an alarm.
+    ///
+    /// Repeating alarms move to their following occurrence, while other alarms
+    /// are disabled.
+    pub async fn skip_next(&self, id: String) -> Result<(), Error> {
+        self.rezz
+            .call(|rezz| {
+                let id = id.clone();
+                async move { rezz.skip_next(id).await }
+            })
+            .await
     }
 
     /// Load the alarm database.
     ///
     /// This will create the database, to simplify inotify usage.
+    ///
+    /// Only alarms owned by the current user are included.
     pub async fn load(&self) -> Result<Vec<Alarm>, Error> {
-        let connection = Connection::system().await?;
-        let rezz = RezzProxy::new(&connection).await?;
-        let alarms = rezz.alarms().await?;
-        Ok(alarms)
+        self.rezz.call(|rezz| async move { rezz.alarms().await }).await
+    }
+
+    /// Load the alarms of all users.
+    ///
+    /// This requires authorization for rezz's `manage-others` polkit action.
+    pub async fn load_all(&self) -> Result<Vec<Alarm>, Error> {
+        self.rezz.call(|rezz| async move { rezz.all_alarms().await }).await
+    }
+}
+
+/// Countdown timer interface.
+///
+/// Timers share the DBus connection of [`Alarms`]. Running timers wake the
+/// system from suspend just like alarms and ring through the [`Subscriber`]
+/// once they elapse. See [`TimerClient`] for the available methods.
+#[derive(Copy, Clone, Default, Debug)]
+pub struct Timers;
+
+impl Deref for Timers {
+    type Target = TimerClient;
+
+    fn deref(&self) -> &TimerClient {
+        &TIMERS
+    }
+}
+
+/// Countdown timer interface owning its DBus connection.
+///
+/// Use [`AlarmClient::timers`] to create a timer interface sharing the
+/// connection of an [`AlarmClient`].
+#[derive(Clone, Default, Debug)]
+pub struct TimerClient {
+    rezz: Rezz,
+}
+
+impl TimerClient {
+    /// Start a new timer.
+    ///
+    /// The timer will ring for `ring_seconds` once `duration` has elapsed.
+    pub async fn start(
+        &self,
+        id: String,
+        duration: Duration,
+        ring_seconds: u32,
+    ) -> Result<(), Error> {
+        let duration_ms = duration.as_millis() as u64;
+        self.rezz
+            .call(|rezz| {
+                let id = id.clone();
+                async move { rezz.start_timer(id, duration_ms, ring_seconds).await }
+            })
+            .await
+    }
+
+    /// Pause a running timer.
+    pub async fn pause(&self, id: String) -> Result<(), Error> {
+        self.rezz
+            .call(|rezz| {
+                let id = id.clone();
+                async move { rezz.pause_timer(id).await }
+            })
+            .await
+    }
+
+    /// Resume a paused timer.
+    pub async fn resume(&self, id: String) -> Result<(), Error> {
+        self.rezz
+            .call(|rezz| {
+                let id = id.clone();
+                async move { rezz.resume_timer(id).await }
+            })
+            .await
+    }
+
+    /// Remove an existing timer.
+    ///
+    /// This also stops a timer which is currently ringing.
+    pub async fn remove(&self, id: String) -> Result<(), Error> {
+        self.rezz
+            .call(|rezz| {
+                let id = id.clone();
+                async move { rezz.remove_timer(id).await }
+            })
+            .await
+    }
+
+    /// Load all timers.
+    pub async fn load(&self) -> Result<Vec<Timer>, Error> {
+        self.rezz.call(|rezz| async move { rezz.timers().await }).await
+    }
+}
+
+/// Lazily connected rezz DBus proxy.
+#[derive(Clone, Default, Debug)]
+struct Rezz {
+    proxy: Arc<AsyncMutex<Option<RezzProxy<'static>>>>,
+    /// Connection used instead of the system bus.
+    connection: Option<Connection>,
+}
+
+impl Rezz {
+    /// Run a rezz DBus call.
+    ///
+    /// If the DBus connection was lost before the call could be sent, the call
+    /// is retried once with a new connection. Calls which were sent already
+    /// are never retried, since rezz might have handled them.
+    async fn call<T, F, R>(&self, f: F) -> Result<T, Error>
+    where
+        F: Fn(RezzProxy<'static>) -> R,
+        R: Future<Output = zbus::Result<T>>,
+    {
+        let rezz = self.proxy().await?;
+        match f(rezz).await {
+            Err(zbus::Error::InputOutput(err)) => {
+                // Reconnect on the next call.
+                self.proxy.lock().await.take();
+
+                // Socket write errors are reported by the OS, while a
+                // connection lost after sending is reported by zbus itself.
+                if err.raw_os_error().is_none() || self.connection.is_some() {
+                    return Err(zbus::Error::InputOutput(err).into());
+                }
+
+                let rezz = self.proxy().await?;
+                Ok(f(rezz).await?)
+            },
+            result => Ok(result?),
+        }
+    }
+
+    /// Get the rezz proxy, connecting to DBus if necessary.
+    async fn proxy(&self) -> Result<RezzProxy<'static>, Error> {
+        let mut proxy = self.proxy.lock().await;
+
+        if let Some(proxy) = &*proxy {
+            return Ok(proxy.clone());
+        }
+
+        let connection = match &self.connection {
+            Some(connection) => connection.clone(),
+            None => Connection::system().await?,
+        };
+
+        // Disable property caching, since rezz might have been restarted.
+        let rezz =
+            RezzProxy::builder(&connection).cache_properties(CacheProperties::No).build().await?;
+        *proxy = Some(rezz.clone());
+
+        Ok(rezz)
     }
 }
 
 /// Subscriber for alarm events.
-pub struct Subscriber<'a> {
-    alarms_stream: PropertyStream<'a, Vec<Alarm>>,
-    alarms: Vec<Alarm>,
+///
+/// Events can be consumed either through [`Subscriber::next`] or the
+/// subscriber's [`Stream`] implementation. All events own their data, so the
+/// subscriber is not borrowed while handling them.
+pub struct Subscriber {
+    events: Pin<Box<dyn Stream<Item = Event> + Send>>,
+    schedule: Schedule,
 }
 
-impl Subscriber<'static> {
+impl Subscriber {
     /// Create a new DBus alarm subscription.
     pub async fn new() -> Result<Self, Error> {
         // Setup DBus connection.
@@ -58,74 +430,347 @@ impl Subscriber<'static> {
         let rezz = RezzProxy::new(&connection).await?;
 
         // Create listener for alarms change.
-        let mut alarms = rezz.alarms().await?;
-        alarms.sort_unstable();
+        let alarms = rezz.alarms().await?;
         let alarms_stream = rezz.receive_alarms_changed().await;
 
-        Ok(Self { alarms_stream, alarms })
+        // Create listener for timers change.
+        let timers = rezz.timers().await?;
+        let timers_stream = rezz.receive_timers_changed().await;
+
+        // Create listener for rezz restarts.
+        let owner_stream = rezz.inner().receive_owner_changed().await?;
+
+        let schedule = Schedule::new(alarms, timers);
+        let source = EventSource {
+            owner_stream,
+            alarms_stream,
+            timers_stream,
+            connection,
+            schedule: schedule.clone(),
+            pending_events: VecDeque::new(),
+            reconnect_time: None,
+            reconnect_delay: RECONNECT_DELAY,
+        };
+        let events = stream::unfold(source, |mut source| async move {
+            let event = source.next().await?;
+            Some((event, source))
+        })
+        .fuse();
+
+        Ok(Self { events: Box::pin(events), schedule })
     }
 
-    /// Get the next alarm event.
-    pub async fn next(&mut self) -> Option<Event<'_>> {
-        let next_alarm = Self::next_alarm(&mut self.alarms);
-
-        tokio::select! {
-            // Handle alarm updates.
-            Some(new_alarms) = self.alarms_stream.next() => {
-                if let Ok(mut alarms) = new_alarms.get().await {
-                    // Ensure alarms are always sorted by ring time.
-                    alarms.sort_unstable();
-                    self.alarms = alarms;
-
-                    return Some(Event::AlarmsChanged(&self.alarms));
-                }
-            },
-            // Ring the alarm.
-            _ = Self::wait_alarm(next_alarm) => {
-                // Remove the alarm once it starts ringing.
-                let next_id = &next_alarm?.id.clone();
-                let index = self.alarms.iter().position(|alarm| &alarm.id == next_id)?;
-                let alarm = self.alarms.remove(index);
-
-                return Some(Event::Ring(alarm));
-            },
-        }
+    /// Set the policy for alarms discovered after their ring time.
+    pub fn set_late_policy(&self, late_policy: LatePolicy) {
+        self.schedule.state().late_policy = late_policy;
+    }
 
-        None
+    /// Get the next alarm event.
+    ///
+    /// Returns `None` once the DBus connection is closed.
+    pub async fn next(&mut self) -> Option<Event> {
+        self.events.next().await
     }
 
     /// Get all alarms.
     ///
     /// This list of alarms will always be ordered by alarm time, with the
-    /// smallest timestamp being first in the slice.
-    pub fn alarms(&self) -> &[Alarm] {
-        self.alarms.as_slice()
+    /// smallest timestamp being first in the list.
+    pub fn alarms(&self) -> Vec<Alarm> {
+        self.schedule.alarms()
     }
 
     /// Get the next alarm.
     ///
-    /// This will ignore all alarms which are elapsed beyond their ringing
-    /// duration.
+    /// This will ignore disabled alarms and all alarms which are elapsed beyond
+    /// their ringing duration.
+    pub fn next_alarm(&self) -> Option<Alarm> {
+        self.schedule.next_alarm()
+    }
+
+    /// Get all timers.
+    pub fn timers(&self) -> Vec<Timer> {
+        self.schedule.timers()
+    }
+
+    /// Get a shared handle to the subscriber's alarm schedule.
+    pub fn schedule(&self) -> Schedule {
+        self.schedule.clone()
+    }
+}
+
+impl Stream for Subscriber {
+    type Item = Event;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        self.events.as_mut().poll_next(cx)
+    }
+}
+
+/// Shared alarm schedule of a [`Subscriber`].
+///
+/// This can be used to query the upcoming alarms without access to the
+/// subscriber itself, since the subscriber is usually busy waiting for the
+/// next event.
+#[derive(Clone)]
+pub struct Schedule {
+    state: Arc<Mutex<ScheduleState>>,
+}
+
+impl Schedule {
+    fn new(mut alarms: Vec<Alarm>, timers: Vec<Timer>) -> Self {
+        alarms.sort_unstable();
+        let state = ScheduleState { alarms, timers, late_policy: Default::default() };
+        Self { state: Arc::new(Mutex::new(state)) }
+    }
+
+    /// Get all alarms.
     ///
-    /// The input slice is sorted to ensure optimal performance.
-    fn next_alarm(alarms: &mut [Alarm]) -> Option<&Alarm> {
-        // Get seconds since unix epoch.
-        let current_secs =
-            SystemTime::now().duration_since(SystemTime::UNIX_EPOCH).unwrap().as_secs();
+    /// This list of alarms will always be ordered by alarm time, with the
+    /// smallest timestamp being first in the list.
+    pub fn alarms(&self) -> Vec<Alarm> {
+        self.state().alarms.clone()
+    }
+
+    /// Get the next alarm.
+    ///
+    /// This will ignore disabled alarms and all alarms which are elapsed beyond
+    /// their ringing duration.
+    pub fn next_alarm(&self) -> Option<Alarm> {
+        // Get milliseconds since unix epoch.
+        let current_millis =
+            SystemTime::now().duration_since(SystemTime::UNIX_EPOCH).unwrap().as_millis() as i64;
 
         // Get the next non-elapsed alarm.
-        alarms
+        self.state()
+            .alarms
             .iter()
-            .find(|alarm| alarm.unix_time as u64 + alarm.ring_seconds as u64 >= current_secs)
+            .filter(|alarm| alarm.enabled)
+            .find(|alarm| alarm.ring_end() * 1000 + alarm.unix_millis as i64 >= current_millis)
+            .cloned()
     }
 
-    /// Convert alarm to tokio async sleep.
-    async fn wait_alarm(alarm: Option<&Alarm>) -> Result<(), Error> {
+    /// Get all timers.
+    pub fn timers(&self) -> Vec<Timer> {
+        self.state().timers.clone()
+    }
+
+    /// Get the next running timer.
+    ///
+    /// This will ignore all timers which are elapsed beyond their ringing
+    /// duration.
+    fn next_timer(&self) -> Option<Timer> {
+        // Get milliseconds since unix epoch.
+        let current_millis =
+            SystemTime::now().duration_since(SystemTime::UNIX_EPOCH).unwrap().as_millis() as i64;
+
+        self.state()
+            .timers
+            .iter()
+            .filter_map(|timer| Some((timer.end_time_ms()?, timer)))
+            .filter(|(end, timer)| end + timer.ring_seconds as i64 * 1000 >= current_millis)
+            .min_by_key(|(end, _)| *end)
+            .map(|(_, timer)| timer.clone())
+    }
+
+    /// Get the next alarm or timer due to ring.
+    fn next_due(&self) -> Option<Due> {
+        let alarm = self.next_alarm().map(Due::Alarm);
+        let timer = self.next_timer().map(Due::Timer);
+        alarm.into_iter().chain(timer).min_by_key(Due::ring_time)
+    }
+
+    /// Replace all alarms.
+    ///
+    /// Returns the new alarms, sorted by ring time.
+    fn set_alarms(&self, mut alarms: Vec<Alarm>) -> Vec<Alarm> {
+        // Ensure alarms are always sorted by ring time.
+        alarms.sort_unstable();
+        self.state().alarms = alarms.clone();
+        alarms
+    }
+
+    /// Replace all timers.
+    fn set_timers(&self, timers: Vec<Timer>) {
+        self.state().timers = timers;
+    }
+
+    /// Remove an alarm or timer once it starts ringing.
+    ///
+    /// Returns the event for the ringing alarm or timer, or `None` if it was
+    /// removed, disabled or paused in the meantime.
+    fn ring(&self, due: &Due) -> Option<Event> {
+        let now = SystemTime::now();
+        let mut state = self.state();
+        match due {
+            Due::Alarm(alarm) => {
+                let index = state.alarms.iter().position(|a| a.id == alarm.id && a.enabled)?;
+                let alarm = state.alarms.remove(index);
+                Some(state.late_policy.ring_event(alarm, now))
+            },
+            Due::Timer(timer) => {
+                let index = state.timers.iter().position(|t| t == timer)?;
+                let timer = state.timers.remove(index);
+                Some(state.late_policy.timer_event(timer, now))
+            },
+        }
+    }
+
+    /// Lock the schedule's state.
+    fn state(&self) -> MutexGuard<'_, ScheduleState> {
+        self.state.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+/// Mutable state of a [`Schedule`].
+struct ScheduleState {
+    late_policy: LatePolicy,
+    alarms: Vec<Alarm>,
+    timers: Vec<Timer>,
+}
+
+/// Alarm or timer due to ring next.
+enum Due {
+    Alarm(Alarm),
+    Timer(Timer),
+}
+
+impl Due {
+    /// Get the time at which this should start ringing.
+    fn ring_time(&self) -> SystemTime {
+        match self {
+            Self::Alarm(alarm) => ring_time(alarm),
+            Self::Timer(timer) => timer_ring_time(timer),
+        }
+    }
+}
+
+/// Producer for [`Subscriber`] events.
+struct EventSource {
+    alarms_stream: PropertyStream<'static, Vec<Alarm>>,
+    timers_stream: PropertyStream<'static, Vec<Timer>>,
+    owner_stream: OwnerChangedStream<'static>,
+    pending_events: VecDeque<Event>,
+    reconnect_time: Option<Instant>,
+    reconnect_delay: Duration,
+    connection: Connection,
+    schedule: Schedule,
+}
+
+impl EventSource {
+    /// Wait for the next alarm event.
+    ///
+    /// Returns `None` once the alarms stream is closed.
+    async fn next(&mut self) -> Option<Event> {
+        if let Some(event) = self.pending_events.pop_front() {
+            return Some(event);
+        }
+
+        loop {
+            let next_due = self.schedule.next_due();
+
+            tokio::select! {
+                // Handle rezz restarts.
+                owner = self.owner_stream.next() => match owner? {
+                    Some(_) => {
+                        self.reconnect_delay = RECONNECT_DELAY;
+                        if let Some(event) = self.try_reconnect().await {
+                            return Some(event);
+                        }
+                    },
+                    None => {
+                        self.reconnect_time = None;
+                        return Some(Event::Disconnected);
+                    },
+                },
+                // Retry failed reconnects.
+                _ = Self::wait_reconnect(self.reconnect_time) => {
+                    if let Some(event) = self.try_reconnect().await {
+                        return Some(event);
+                    }
+                },
+                // Handle alarm updates.
+                new_alarms = self.alarms_stream.next() => {
+                    if let Ok(alarms) = new_alarms?.get().await {
+                        let alarms = self.schedule.set_alarms(alarms);
+                        return Some(Event::AlarmsChanged(alarms));
+                    }
+                },
+                // Handle timer updates.
+                new_timers = self.timers_stream.next() => {
+                    if let Ok(timers) = new_timers?.get().await {
+                        self.schedule.set_timers(timers.clone());
+                        return Some(Event::TimersChanged(timers));
+                    }
+                },
+                // Ring the alarm or timer.
+                _ = Self::wait_alarm(next_due.as_ref()) => {
+                    let event = next_due.and_then(|due| self.schedule.ring(&due));
+                    if event.is_some() {
+                        return event;
+                    }
+                },
+            }
+        }
+    }
+
+    /// Attempt to re-subscribe to a restarted rezz daemon.
+    ///
+    /// Since rezz might not be ready yet, failed attempts are retried with
+    /// exponential backoff until the daemon is available or stops again.
+    async fn try_reconnect(&mut self) -> Option<Event> {
+        match self.reconnect().await {
+            Ok((alarms, timers)) => {
+                self.reconnect_time = None;
+                self.pending_events.push_back(Event::AlarmsChanged(alarms));
+                self.pending_events.push_back(Event::TimersChanged(timers));
+                Some(Event::Connected)
+            },
+            Err(_) => {
+                self.reconnect_time = Some(Instant::now() + self.reconnect_delay);
+                self.reconnect_delay = (self.reconnect_delay * 2).min(MAX_RECONNECT_DELAY);
+                None
+            },
+        }
+    }
+
+    /// Re-subscribe to a restarted rezz daemon.
+    ///
+    /// Returns the daemon's current alarms and timers.
+    async fn reconnect(&mut self) -> Result<(Vec<Alarm>, Vec<Timer>), Error> {
+        // Use a new proxy, to avoid property values cached from the old daemon.
+        let rezz = RezzProxy::new(&self.connection).await?;
+        let alarms = rezz.alarms().await?;
+        let timers = rezz.timers().await?;
+        self.alarms_stream = rezz.receive_alarms_changed().await;
+        self.timers_stream = rezz.receive_timers_changed().await;
+
+        let alarms = self.schedule.set_alarms(alarms);
+        self.schedule.set_timers(timers.clone());
+
+        Ok((alarms, timers))
+    }
+
+    /// Wait for the next reconnect attempt.
+    ///
+    /// This uses the monotonic clock, so retries never wake up the system from
+    /// suspend. Never completes without a pending reconnect attempt.
+    async fn wait_reconnect(time: Option<Instant>) {
+        match time {
+            Some(time) => tokio::time::sleep_until(time).await,
+            None => future::pending().await,
+        }
+    }
+
+    /// Convert alarm or timer to tokio async sleep.
+    ///
+    /// Never completes without any alarm or timer, to avoid waking up the
+    /// system from suspend while idle.
+    async fn wait_alarm(due: Option<&Due>) -> Result<(), Error> {
         // Get time until alarm.
-        let target = match alarm {
-            Some(alarm) => SystemTime::UNIX_EPOCH + Duration::from_secs(alarm.unix_time as u64),
-            // Default to an hour without alarm present.
-            None => SystemTime::now() + Duration::from_secs(60 * 60),
+        let target = match due {
+            Some(due) => due.ring_time(),
+            None => future::pending().await,
         };
 
         // Wait for timer to elapse.
@@ -136,7 +781,345 @@ impl Subscriber<'static> {
 }
 
 /// Alarm subscription events.
-pub enum Event<'a> {
-    AlarmsChanged(&'a [Alarm]),
-    Ring(Alarm),
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub enum Event {
+    /// Alarms were added or removed.
+    AlarmsChanged(Vec<Alarm>),
+    /// Alarm should start ringing for the specified duration.
+    ///
+    /// Clients should choose how to ring based on the alarm's
+    /// [`rezz::AlarmKind`], like playing only a short chime for reminders.
+    Ring { alarm: Alarm, duration: Duration },
+    /// Late alarm was skipped due to [`LatePolicy::Skip`], or because its
+    /// ringing window has passed with [`LatePolicy::Remaining`].
+    Missed(Alarm),
+    /// Timers were started, paused, resumed or removed.
+    TimersChanged(Vec<Timer>),
+    /// Timer elapsed and should start ringing for the specified duration.
+    TimerElapsed { timer: Timer, duration: Duration },
+    /// Rezz daemon became available again.
+    ///
+    /// This is always followed by an [`Event::AlarmsChanged`] and an
+    /// [`Event::TimersChanged`] with the daemon's current alarms and timers.
+    Connected,
+    /// Rezz daemon became unavailable.
+    ///
+    /// Known alarms will continue ringing while the daemon is unavailable.
+    Disconnected,
+}
+
+/// Handling of alarms discovered after their ring time.
+///
+/// This applies when an alarm's ring time has elapsed while its ringing window
+/// has not, like when the system was suspended or no client was running.
+///
+/// Alarms can override the subscriber's policy using their
+/// [`rezz::Alarm::late_policy`].
+#[derive(Default, Copy, Clone, PartialEq, Eq, Debug)]
+pub enum LatePolicy {
+    /// Ring for the remainder of the alarm's ringing window.
+    ///
+    /// Alarms whose ringing window has passed entirely are skipped.
+    #[default]
+    Remaining,
+    /// Ring for the alarm's full ringing duration.
+    Full,
+    /// Do not ring, emitting [`Event::Missed`] instead.
+    Skip,
+}
+
+impl LatePolicy {
+    /// Get the event for an alarm which has reached its ring time.
+    ///
+    /// The alarm's own policy takes precedence over this one.
+    fn ring_event(&self, alarm: Alarm, now: SystemTime) -> Event {
+        let policy = match alarm.late_policy {
+            AlarmLatePolicy::Client => *self,
+            AlarmLatePolicy::Remaining => Self::Remaining,
+            AlarmLatePolicy::Full => Self::Full,
+            AlarmLatePolicy::Skip => Self::Skip,
+        };
+
+        // Use rezz's ringing window, which is limited for repeating alarms.
+        let ring_seconds = (alarm.ring_end() - alarm.unix_time).clamp(0, u32::MAX as i64);
+        match policy.ring_duration(ring_time(&alarm), ring_seconds as u32, now) {
+            Some(duration) => Event::Ring { alarm, duration },
+            None => Event::Missed(alarm),
+        }
+    }
+
+    /// Get the event for a timer which has elapsed.
+    ///
+    /// Since timers are started explicitly, late timers are never skipped and
+    /// ring for their remaining duration with [`LatePolicy::Skip`] instead.
+    fn timer_event(&self, timer: Timer, now: SystemTime) -> Event {
+        let policy = if *self == Self::Skip { Self::Remaining } else { *self };
+        let duration = policy.ring_duration(timer_ring_time(&timer), timer.ring_seconds, now);
+        Event::TimerElapsed { duration: duration.unwrap_or_default(), timer }
+    }
+
+    /// Get the ring duration for a ring time which has been reached at `now`.
+    ///
+    /// Returns `None` if ringing should be skipped.
+    fn ring_duration(
+        &self,
+        ring_time: SystemTime,
+        ring_seconds: u32,
+        now: SystemTime,
+    ) -> Option<Duration> {
+        let ring_duration = Duration::from_secs(ring_seconds as u64);
+
+        // Get time elapsed since the ring time.
+        let elapsed = now.duration_since(ring_time).unwrap_or_default();
+
+        // Ring for the full duration unless late.
+        if elapsed < LATE_THRESHOLD {
+            return Some(ring_duration);
+        }
+
+        match self {
+            Self::Remaining => Some(ring_duration.saturating_sub(elapsed)).filter(|d| !d.is_zero()),
+            Self::Full => Some(ring_duration),
+            Self::Skip => None,
+        }
+    }
+}
+
+impl From<LatePolicy> for AlarmLatePolicy {
+    fn from(late_policy: LatePolicy) -> Self {
+        match late_policy {
+            LatePolicy::Remaining => Self::Remaining,
+            LatePolicy::Full => Self::Full,
+            LatePolicy::Skip => Self::Skip,
+        }
+    }
+}
+
+impl FromStr for LatePolicy {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "remaining" => Ok(Self::Remaining),
+            "full" => Ok(Self::Full),
+            "skip" => Ok(Self::Skip),
+            _ => Err(Error::InvalidLatePolicy(s.into())),
+        }
+    }
+}
+
+/// Get an alarm's ring time.
+fn ring_time(alarm: &Alarm) -> SystemTime {
+    SystemTime::UNIX_EPOCH + Duration::from_millis(alarm.unix_time_ms().max(0) as u64)
+}
+
+/// Get a running timer's ring time.
+///
+/// Paused timers never ring, so their ring time is in the distant future.
+fn timer_ring_time(timer: &Timer) -> SystemTime {
+    match timer.end_time_ms() {
+        Some(end) => SystemTime::UNIX_EPOCH + Duration::from_millis(end.max(0) as u64),
+        None => SystemTime::UNIX_EPOCH + Duration::from_secs(u32::MAX as u64),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rezz::ServiceError;
+
+    use super::*;
+    use crate::testing::{self, MockRezz, RezzCall};
+
+    /// Point an alarm interface at a mock rezz connection.
+    async fn connect(alarms: &AlarmClient, connection: &Connection) {
+        let builder = RezzProxy::builder(connection).cache_properties(CacheProperties::No);
+        *alarms.rezz.proxy.lock().await = Some(builder.build().await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn shared_load() {
+        let alarms = vec![Alarm::new("wake", 1_900_000_000, 60)];
+        let rezz = MockRezz { alarms: alarms.clone(), ..Default::default() };
+        let (client, _server, _) = testing::mock_rezz(rezz).await;
+        connect(&Alarms, &client).await;
+
+        assert_eq!(Alarms.load().await.unwrap(), alarms);
+    }
+
+    #[tokio::test]
+    async fn client_load() {
+        let alarms = vec![Alarm::new("wake", 1_900_000_000, 60)];
+        let rezz = MockRezz { alarms: alarms.clone(), ..Default::default() };
+        let (client, _server, _) = testing::mock_rezz(rezz).await;
+
+        let client = AlarmClient::with_connection(client);
+        assert_eq!(client.load().await.unwrap(), alarms);
+    }
+
+    #[tokio::test]
+    async fn no_retry_after_timeout() {
+        let error = |_: &str| zbus::fdo::Error::NoReply("mock timeout".into()).into();
+        let rezz = MockRezz { error: Some(error), ..Default::default() };
+        let (client, _server, calls) = testing::mock_rezz(rezz).await;
+        let alarms = AlarmClient::new();
+        connect(&alarms, &client).await;
+
+        let result = alarms.add(Alarm::new("wake", 1_900_000_000, 60)).await;
+
+        assert!(matches!(result, Err(Error::DBus(_))));
+        assert_eq!(*calls.lock().unwrap(), [RezzCall::Add("wake".into())]);
+    }
+
+    #[tokio::test]
+    async fn snooze_replaces() {
+        let (client, _server, calls) = testing::mock_rezz(MockRezz::default()).await;
+        let alarms = AlarmClient::with_connection(client);
+
+        let alarm = Alarm::new("wake", 1_900_000_000, 60);
+        alarms.snooze(alarm.clone(), Duration::from_secs(60)).await.unwrap();
+
+        let mut repeating = alarm;
+        repeating.repeat_days = 0b1;
+        alarms.snooze(repeating, Duration::from_secs(60)).await.unwrap();
+
+        let expected =
+            [RezzCall::Replace(vec!["wake".into()]), RezzCall::Replace(vec!["wake#snooze".into()])];
+        assert_eq!(*calls.lock().unwrap(), expected);
+    }
+
+    #[tokio::test]
+    async fn snooze_rejected() {
+        let error = |id: &str| ServiceError::QuotaExceeded(id.into());
+        let rezz = MockRezz { error: Some(error), ..Default::default() };
+        let (client, _server, calls) = testing::mock_rezz(rezz).await;
+        let alarms = AlarmClient::with_connection(client);
+
+        let alarm = Alarm::new("wake", 1_900_000_000, 60);
+        let result = alarms.snooze(alarm, Duration::from_secs(60)).await;
+
+        // The alarm is never removed without its snoozed replacement.
+        assert!(matches!(result, Err(Error::QuotaExceeded(_))));
+        assert_eq!(*calls.lock().unwrap(), [RezzCall::Replace(vec!["wake".into()])]);
+    }
+
+    #[tokio::test]
+    async fn dismiss_one_shot() {
+        let error = |id: &str| ServiceError::AlarmNotFound(id.into());
+        let rezz = MockRezz { error: Some(error), ..Default::default() };
+        let (client, _server, calls) = testing::mock_rezz(rezz).await;
+        let alarms = AlarmClient::with_connection(client);
+
+        // Alarms removed by rezz already are dismissed successfully.
+        let alarm = Alarm::new("wake", 1_900_000_000, 60);
+        alarms.dismiss(&alarm).await.unwrap();
+
+        assert_eq!(*calls.lock().unwrap(), [RezzCall::Remove("wake".into())]);
+    }
+
+    #[tokio::test]
+    async fn dismiss_repeating() {
+        let (client, _server, calls) = testing::mock_rezz(MockRezz::default()).await;
+        let alarms = AlarmClient::with_connection(client);
+
+        let mut alarm = Alarm::new("wake", 1_900_000_000, 60);
+        alarm.repeat_days = 0b1;
+        alarms.dismiss(&alarm).await.unwrap();
+
+        assert!(calls.lock().unwrap().is_empty());
+    }
+
+    #[test]
+    fn next_repeating_alarm() {
+        let now = SystemTime::now().duration_since(SystemTime::UNIX_EPOCH).unwrap();
+        let two_days_ago = now.as_secs() as i64 - 2 * 24 * 60 * 60;
+
+        // Repeating alarms stop ringing after one day, like in rezz.
+        let mut repeating = Alarm::new("repeating", two_days_ago, rezz::RING_FOREVER);
+        repeating.repeat_days = 0b1;
+        let schedule = Schedule::new(vec![repeating.clone()], Vec::new());
+        assert_eq!(schedule.next_alarm(), None);
+
+        let one_shot = Alarm::new("one-shot", two_days_ago, rezz::RING_FOREVER);
+        let schedule = Schedule::new(vec![repeating, one_shot.clone()], Vec::new());
+        assert_eq!(schedule.next_alarm(), Some(one_shot));
+    }
+
+    /// Get the ring duration of a 60 second alarm after `elapsed`.
+    fn late_ring_duration(policy: LatePolicy, elapsed: Duration) -> Option<Duration> {
+        let ring_time = SystemTime::UNIX_EPOCH + Duration::from_secs(1_900_000_000);
+        policy.ring_duration(ring_time, 60, ring_time + elapsed)
+    }
+
+    #[test]
+    fn late_policy_remaining() {
+        let policy = LatePolicy::Remaining;
+        let before = LATE_THRESHOLD - Duration::from_millis(1);
+        let after = LATE_THRESHOLD + Duration::from_secs(1);
+
+        assert_eq!(late_ring_duration(policy, before), Some(Duration::from_secs(60)));
+        assert_eq!(late_ring_duration(policy, LATE_THRESHOLD), Some(Duration::from_secs(30)));
+        assert_eq!(late_ring_duration(policy, after), Some(Duration::from_secs(29)));
+        assert_eq!(
+            late_ring_duration(policy, Duration::from_secs(59)),
+            Some(Duration::from_secs(1))
+        );
+
+        // Alarms whose ringing window has passed are missed.
+        assert_eq!(late_ring_duration(policy, Duration::from_secs(60)), None);
+        assert_eq!(late_ring_duration(policy, Duration::from_secs(90)), None);
+    }
+
+    #[test]
+    fn late_policy_full() {
+        let policy = LatePolicy::Full;
+        let before = LATE_THRESHOLD - Duration::from_millis(1);
+        let after = LATE_THRESHOLD + Duration::from_secs(1);
+
+        assert_eq!(late_ring_duration(policy, before), Some(Duration::from_secs(60)));
+        assert_eq!(late_ring_duration(policy, LATE_THRESHOLD), Some(Duration::from_secs(60)));
+        assert_eq!(late_ring_duration(policy, after), Some(Duration::from_secs(60)));
+    }
+
+    #[test]
+    fn late_policy_skip() {
+        let policy = LatePolicy::Skip;
+        let before = LATE_THRESHOLD - Duration::from_millis(1);
+        let after = LATE_THRESHOLD + Duration::from_secs(1);
+
+        assert_eq!(late_ring_duration(policy, before), Some(Duration::from_secs(60)));
+        assert_eq!(late_ring_duration(policy, LATE_THRESHOLD), None);
+        assert_eq!(late_ring_duration(policy, after), None);
+    }
+
+    #[test]
+    fn late_policy_per_alarm() {
+        let mut alarm = Alarm::new("alarm", 1_900_000_000, 60);
+        let late = ring_time(&alarm) + LATE_THRESHOLD;
+
+        // Alarms use the subscriber's policy by default.
+        let event = LatePolicy::Skip.ring_event(alarm.clone(), late);
+        assert_eq!(event, Event::Missed(alarm.clone()));
+
+        // Alarm policies take precedence over the subscriber's policy.
+        alarm.late_policy = AlarmLatePolicy::Full;
+        let event = LatePolicy::Skip.ring_event(alarm.clone(), late);
+        assert_eq!(event, Event::Ring { alarm: alarm.clone(), duration: Duration::from_secs(60) });
+
+        alarm.late_policy = AlarmLatePolicy::Skip;
+        let event = LatePolicy::Full.ring_event(alarm.clone(), late);
+        assert_eq!(event, Event::Missed(alarm));
+    }
+
+    #[test]
+    fn late_policy_skip_timer() {
+        let timer = Timer::new("tea", 60_000, 60);
+        let ring_time = timer_ring_time(&timer);
+        let late = ring_time + LATE_THRESHOLD;
+
+        // Late timers ring for their remaining duration instead of being skipped.
+        let event = LatePolicy::Skip.timer_event(timer, late);
+        assert!(
+            matches!(event, Event::TimerElapsed { duration, .. } if duration == Duration::from_secs(30))
+        );
+    }
 }
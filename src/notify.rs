@@ -0,0 +1,273 @@
+//! Desktop notifications for ringing alarms.
+
+use std::collections::HashMap;
+use std::future;
+use std::time::Duration;
+
+use futures_util::stream::StreamExt;
+use zbus::zvariant::Value;
+use zbus::{Connection, proxy};
+
+use crate::error::Error;
+
+/// Delay before a snoozed alarm rings again.
+pub const SNOOZE_DURATION: Duration = Duration::from_secs(9 * 60);
+
+/// Application name shown in notifications.
+const APP_NAME: &str = "Alarm";
+
+/// Notification urgency for reminders.
+const NORMAL_URGENCY: u8 = 1;
+
+/// Notification urgency for alarms with actions.
+const CRITICAL_URGENCY: u8 = 2;
+
+/// `NotificationClosed` reason for notifications dismissed by the user.
+const DISMISSED_REASON: u32 = 2;
+
+#[proxy(
+    interface = "org.freedesktop.Notifications",
+    default_service = "org.freedesktop.Notifications",
+    default_path = "/org/freedesktop/Notifications"
+)]
+trait Notifications {
+    #[allow(clippy::too_many_arguments)]
+    fn notify(
+        &self,
+        app_name: &str,
+        replaces_id: u32,
+        app_icon: &str,
+        summary: &str,
+        body: &str,
+        actions: &[&str],
+        hints: HashMap<&str, Value<'_>>,
+        expire_timeout: i32,
+    ) -> zbus::Result<u32>;
+
+    fn close_notification(&self, id: u32) -> zbus::Result<()>;
+
+    #[zbus(signal)]
+    fn action_invoked(&self, id: u32, action_key: &str) -> zbus::Result<()>;
+
+    #[zbus(signal)]
+    fn notification_closed(&self, id: u32, reason: u32) -> zbus::Result<()>;
+}
+
+/// User action on a ringing alarm's notification.
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum Action {
+    /// Stop ringing.
+    Stop,
+    /// Stop ringing and ring again after [`SNOOZE_DURATION`].
+    Snooze,
+}
+
+impl Action {
+    /// Notification action key.
+    fn key(&self) -> &'static str {
+        match self {
+            Self::Stop => "stop",
+            Self::Snooze => "snooze",
+        }
+    }
+
+    /// Label of the notification's action button.
+    fn label(&self) -> &'static str {
+        match self {
+            Self::Stop => "Stop",
+            Self::Snooze => "Snooze",
+        }
+    }
+}
+
+/// Notification for a ringing alarm or timer.
+///
+/// This uses the `org.freedesktop.Notifications` service on the session bus.
+pub struct RingNotification {
+    proxy: NotificationsProxy<'static>,
+    actions: ActionInvokedStream,
+    closed: NotificationClosedStream,
+    id: u32,
+}
+
+impl RingNotification {
+    /// Show a new notification.
+    ///
+    /// Notifications with actions use critical urgency and never expire, to
+    /// keep them visible while the alarm is ringing.
+    pub async fn show(summary: &str, body: &str, actions: &[Action]) -> Result<Self, Error> {
+        let connection = Connection::session().await?;
+        Self::show_on(&connection, summary, body, actions).await
+    }
+
+    /// Show a new notification using a specific DBus connection.
+    pub(crate) async fn show_on(
+        connection: &Connection,
+        summary: &str,
+        body: &str,
+        actions: &[Action],
+    ) -> Result<Self, Error> {
+        let proxy = NotificationsProxy::new(connection).await?;
+
+        // Subscribe before showing the notification, to avoid missing actions.
+        let action_stream = proxy.receive_action_invoked().await?;
+        let closed = proxy.receive_notification_closed().await?;
+
+        let action_list: Vec<_> =
+            actions.iter().flat_map(|action| [action.key(), action.label()]).collect();
+
+        let (urgency, timeout) =
+            if actions.is_empty() { (NORMAL_URGENCY, -1) } else { (CRITICAL_URGENCY, 0) };
+        let hints = HashMap::from([("urgency", Value::from(urgency))]);
+
+        let id = proxy.notify(APP_NAME, 0, "", summary, body, &action_list, hints, timeout).await?;
+
+        Ok(Self { proxy, actions: action_stream, closed, id })
+    }
+
+    /// Wait for the user to act on the notification.
+    ///
+    /// Dismissing the notification is treated as [`Action::Stop`]. If the
+    /// notification is closed otherwise or the notification server is lost,
+    /// this will never return.
+    pub async fn action(&mut self) -> Action {
+        loop {
+            tokio::select! {
+                Some(invoked) = self.actions.next() => {
+                    let args = match invoked.args() {
+                        Ok(args) if args.id == self.id => args,
+                        _ => continue,
+                    };
+
+                    match args.action_key {
+                        key if key == Action::Stop.key() => return Action::Stop,
+                        key if key == Action::Snooze.key() => return Action::Snooze,
+                        _ => (),
+                    }
+                },
+                Some(closed) = self.closed.next() => match closed.args() {
+                    Ok(args) if args.id == self.id && args.reason == DISMISSED_REASON => {
+                        return Action::Stop;
+                    },
+                    _ => (),
+                },
+                else => future::pending().await,
+            }
+        }
+    }
+
+    /// Close the notification.
+    pub async fn close(self) {
+        let _ = self.proxy.close_notification(self.id).await;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use zbus::object_server::SignalEmitter;
+
+    use super::*;
+    use crate::testing::{
+        self, MockNotifications, NOTIFICATION_ID as ID, NOTIFICATIONS_PATH, NotifyCall,
+    };
+
+    /// Wait for the next notification action, failing after a timeout.
+    async fn next_action(notification: &mut RingNotification) -> Action {
+        let action = tokio::time::timeout(Duration::from_secs(5), notification.action());
+        action.await.expect("missing notification action")
+    }
+
+    /// Get a signal emitter for the mock server.
+    fn emitter(server: &Connection) -> SignalEmitter<'_> {
+        SignalEmitter::new(server, NOTIFICATIONS_PATH).unwrap()
+    }
+
+    #[tokio::test]
+    async fn show_with_actions() {
+        let (client, _server, calls) = testing::mock_notifications().await;
+
+        let actions = [Action::Stop, Action::Snooze];
+        let notification = RingNotification::show_on(&client, "Alarm", "wake", &actions).await;
+        assert_eq!(notification.unwrap().id, ID);
+
+        let expected = NotifyCall::Notify {
+            app_name: APP_NAME.into(),
+            summary: "Alarm".into(),
+            body: "wake".into(),
+            actions: ["stop", "Stop", "snooze", "Snooze"].map(String::from).into(),
+            urgency: CRITICAL_URGENCY,
+            timeout: 0,
+        };
+        assert_eq!(*calls.lock().unwrap(), [expected]);
+    }
+
+    #[tokio::test]
+    async fn show_without_actions() {
+        let (client, _server, calls) = testing::mock_notifications().await;
+
+        RingNotification::show_on(&client, "Reminder", "tea", &[]).await.unwrap();
+
+        let expected = NotifyCall::Notify {
+            app_name: APP_NAME.into(),
+            summary: "Reminder".into(),
+            body: "tea".into(),
+            actions: Vec::new(),
+            urgency: NORMAL_URGENCY,
+            timeout: -1,
+        };
+        assert_eq!(*calls.lock().unwrap(), [expected]);
+    }
+
+    #[tokio::test]
+    async fn action_stop() {
+        let (client, server, _) = testing::mock_notifications().await;
+        let actions = [Action::Stop, Action::Snooze];
+        let mut notification =
+            RingNotification::show_on(&client, "Alarm", "", &actions).await.unwrap();
+
+        MockNotifications::action_invoked(&emitter(&server), ID, "stop").await.unwrap();
+
+        assert_eq!(next_action(&mut notification).await, Action::Stop);
+    }
+
+    #[tokio::test]
+    async fn action_snooze() {
+        let (client, server, _) = testing::mock_notifications().await;
+        let actions = [Action::Stop, Action::Snooze];
+        let mut notification =
+            RingNotification::show_on(&client, "Alarm", "", &actions).await.unwrap();
+
+        // Actions of other notifications are ignored.
+        let emitter = emitter(&server);
+        MockNotifications::action_invoked(&emitter, ID + 1, "stop").await.unwrap();
+        MockNotifications::action_invoked(&emitter, ID, "snooze").await.unwrap();
+
+        assert_eq!(next_action(&mut notification).await, Action::Snooze);
+    }
+
+    #[tokio::test]
+    async fn dismissed() {
+        let (client, server, _) = testing::mock_notifications().await;
+        let actions = [Action::Stop, Action::Snooze];
+        let mut notification =
+            RingNotification::show_on(&client, "Alarm", "", &actions).await.unwrap();
+
+        // Only closing by the user is treated as stop action.
+        let emitter = emitter(&server);
+        MockNotifications::notification_closed(&emitter, ID, 1).await.unwrap();
+        MockNotifications::notification_closed(&emitter, ID, DISMISSED_REASON).await.unwrap();
+
+        assert_eq!(next_action(&mut notification).await, Action::Stop);
+    }
+
+    #[tokio::test]
+    async fn close() {
+        let (client, _server, calls) = testing::mock_notifications().await;
+        let notification = RingNotification::show_on(&client, "Alarm", "", &[]).await.unwrap();
+
+        notification.close().await;
+
+        let calls = calls.lock().unwrap();
+        assert_eq!(calls.last(), Some(&NotifyCall::Close(ID)));
+    }
+}
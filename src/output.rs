@@ -0,0 +1,154 @@
+//! Outputs for ringing alarms.
+
+use zbus::Connection;
+
+use crate::audio::AlarmSound;
+use crate::error::Error;
+use crate::feedback::{self, EventFeedback};
+use crate::vibrate::{Vibration, VibrationPattern, VibratorDevice};
+
+/// Outputs used for ringing alarms.
+#[derive(Clone, Debug)]
+pub struct OutputConfig {
+    /// Use feedbackd's themed feedback instead of sound and vibration, if it
+    /// is available.
+    pub feedbackd: bool,
+    /// Vibration motor, vibration is disabled without one.
+    pub vibrator: Option<VibratorDevice>,
+    pub vibration_pattern: VibrationPattern,
+}
+
+impl Default for OutputConfig {
+    fn default() -> Self {
+        Self { feedbackd: true, vibrator: None, vibration_pattern: Default::default() }
+    }
+}
+
+/// Sound and vibration of a ringing alarm.
+///
+/// All outputs are stopped once this is dropped or [`AlarmOutput::stop`] is
+/// called on it.
+#[derive(Default)]
+pub struct AlarmOutput {
+    feedback: Option<EventFeedback>,
+    sound: Option<AlarmSound>,
+    vibration: Option<Vibration>,
+}
+
+impl AlarmOutput {
+    /// Start all configured outputs.
+    ///
+    /// Failing outputs are skipped, returning their errors alongside the
+    /// started outputs.
+    ///
+    /// If feedbackd is enabled and running, it replaces the alarm sound and
+    /// vibration, leaving them to the active feedback profile.
+    pub async fn start(config: &OutputConfig) -> (Self, Vec<Error>) {
+        let session = match config.feedbackd {
+            true => Some(Connection::session().await.map_err(Error::from)),
+            false => None,
+        };
+        Self::start_on(session, config).await
+    }
+
+    /// Start all configured outputs, using feedbackd on a specific session bus
+    /// connection.
+    async fn start_on(
+        session: Option<Result<Connection, Error>>,
+        config: &OutputConfig,
+    ) -> (Self, Vec<Error>) {
+        let mut output = Self::default();
+        let mut errors = Vec::new();
+
+        let triggered = match session {
+            Some(Ok(connection)) => output.trigger_feedback_on(&connection).await,
+            Some(Err(err)) => Err(err),
+            None => Ok(false),
+        };
+        match triggered {
+            Ok(true) => return (output, errors),
+            Ok(false) => (),
+            Err(err) => errors.push(err),
+        }
+
+        if let Err(err) = output.play_sound() {
+            errors.push(err);
+        }
+
+        if let Some(vibrator) = &config.vibrator {
+            if let Err(err) = output.vibrate(vibrator, config.vibration_pattern) {
+                errors.push(err);
+            }
+        }
+
+        (output, errors)
+    }
+
+    /// Start feedbackd's alarm event feedback.
+    ///
+    /// Returns `false` if feedbackd is not available.
+    pub async fn trigger_feedback(&mut self) -> Result<bool, Error> {
+        self.trigger_feedback_on(&Connection::session().await?).await
+    }
+
+    /// Start feedbackd's alarm event feedback on a specific connection.
+    async fn trigger_feedback_on(&mut self, connection: &Connection) -> Result<bool, Error> {
+        self.feedback = EventFeedback::trigger_on(connection, feedback::ALARM_EVENT).await?;
+        Ok(self.feedback.is_some())
+    }
+
+    /// Start playing the alarm sound.
+    pub fn play_sound(&mut self) -> Result<(), Error> {
+        self.sound = Some(AlarmSound::play()?);
+        Ok(())
+    }
+
+    /// Start vibrating with the specified pattern.
+    pub fn vibrate(
+        &mut self,
+        device: &VibratorDevice,
+        pattern: VibrationPattern,
+    ) -> Result<(), Error> {
+        self.vibration = Some(Vibration::start(device, pattern)?);
+        Ok(())
+    }
+
+    /// Check if any output is active.
+    pub fn is_active(&self) -> bool {
+        self.feedback.is_some() || self.sound.is_some() || self.vibration.is_some()
+    }
+
+    /// Stop all outputs.
+    pub async fn stop(self) {
+        if let Some(feedback) = self.feedback {
+            feedback.end().await;
+        }
+
+        if let Some(sound) = self.sound {
+            sound.stop();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing;
+
+    #[tokio::test]
+    async fn fallback_without_feedbackd() {
+        let (client, _server, _) = testing::mock_feedback(true).await;
+
+        let config = OutputConfig::default();
+        let (output, errors) = AlarmOutput::start_on(Some(Ok(client)), &config).await;
+
+        // The alarm sound is attempted instead, which fails without an audio
+        // device.
+        assert!(output.feedback.is_none());
+        match errors.as_slice() {
+            [] => assert!(output.sound.is_some()),
+            [Error::AudioStream(_) | Error::AudioPlayback(_)] => assert!(output.sound.is_none()),
+            errors => panic!("unexpected errors: {errors:?}"),
+        }
+    }
+}
@@ -0,0 +1,177 @@
+//! Reminders for alarms of kind [`AlarmKind::Reminder`].
+//!
+//! [`AlarmKind::Reminder`]: rezz::AlarmKind::Reminder
+
+use rezz::Alarm;
+use zbus::Connection;
+use zbus::fdo::{RequestNameFlags, RequestNameReply};
+
+use crate::audio::{self, AlarmSound};
+use crate::error::Error;
+use crate::notify::RingNotification;
+use crate::{AlarmClient, Alarms};
+
+/// Notification summary of reminders.
+pub const SUMMARY: &str = "Reminder";
+
+/// Prefix of the session bus names claimed for handling reminders.
+const BUS_NAME_PREFIX: &str = "org.catacombing.alarm.Reminder";
+
+/// Errors of the individual steps of [`remind`].
+#[derive(Default, Debug)]
+pub struct ReminderErrors {
+    /// The notification could not be shown.
+    ///
+    /// Clients should fall back to a different way of showing the reminder.
+    pub notification: Option<Error>,
+    /// The reminder chime could not be played.
+    pub chime: Option<Error>,
+    /// The reminder's alarm could not be removed.
+    pub remove: Option<Error>,
+}
+
+/// Show a reminder for a ringing alarm.
+///
+/// This shows a notification and plays the reminder chime. Since reminders
+/// are never stopped like wake-up alarms, the alarm is removed once the chime
+/// has finished. Repeating alarms are kept for their next occurrence.
+///
+/// Only one client handles each reminder, by claiming a session bus name for
+/// it. Reminders which are handled by another client already are ignored.
+///
+/// Failing steps are skipped, returning their errors.
+pub async fn remind(alarm: &Alarm) -> ReminderErrors {
+    let session = Connection::session().await.map_err(Error::from);
+    remind_on(session, &Alarms, alarm).await
+}
+
+/// Show a reminder using a specific session bus connection and rezz client.
+async fn remind_on(
+    session: Result<Connection, Error>,
+    alarms: &AlarmClient,
+    alarm: &Alarm,
+) -> ReminderErrors {
+    let mut errors = ReminderErrors::default();
+
+    let session = session.map_err(|err| errors.notification = Some(err)).ok();
+
+    // Handle reminders without a session bus, since they cannot be claimed.
+    let name = bus_name(alarm);
+    if let Some(session) = &session {
+        let flags = RequestNameFlags::DoNotQueue.into();
+        match session.request_name_with_flags(name.as_str(), flags).await {
+            Ok(RequestNameReply::PrimaryOwner) | Err(_) => (),
+            Ok(_) => return errors,
+        }
+
+        let notification = RingNotification::show_on(session, SUMMARY, &alarm.id, &[]).await;
+        errors.notification = notification.err();
+    }
+
+    // Keep the chime alive until playback is done.
+    match AlarmSound::chime() {
+        Ok(sound) => {
+            tokio::time::sleep(audio::CHIME_DURATION).await;
+            sound.stop();
+        },
+        Err(err) => errors.chime = Some(err),
+    }
+
+    errors.remove = alarms.dismiss(alarm).await.err();
+
+    if let Some(session) = &session {
+        let _ = session.release_name(name.as_str()).await;
+    }
+
+    errors
+}
+
+/// Get the session bus name claimed for handling a reminder.
+///
+/// Alarm IDs are hashed using FNV-1a, since they can contain characters which
+/// are not allowed in bus names.
+fn bus_name(alarm: &Alarm) -> String {
+    let hash = alarm.id.bytes().fold(0xcbf2_9ce4_8422_2325_u64, |hash, byte| {
+        (hash ^ byte as u64).wrapping_mul(0x0100_0000_01b3)
+    });
+    format!("{BUS_NAME_PREFIX}.R{hash:016x}_{}", alarm.unix_time)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{self, MockRezz, NotifyCall, RezzCall};
+
+    #[tokio::test]
+    async fn remind_and_remove() {
+        let (session, _server, notifications) = testing::mock_notifications().await;
+        let (client, _server, calls) = testing::mock_rezz(MockRezz::default()).await;
+        let alarms = AlarmClient::with_connection(client);
+
+        let alarm = Alarm::new("tea", 1_900_000_000, 60);
+        let errors = remind_on(Ok(session), &alarms, &alarm).await;
+
+        assert!(errors.notification.is_none());
+        assert!(errors.remove.is_none());
+        let notifications = notifications.lock().unwrap();
+        assert!(matches!(
+            notifications.as_slice(),
+            [NotifyCall::Notify { summary, body, .. }] if summary == SUMMARY && body == "tea"
+        ));
+        assert_eq!(*calls.lock().unwrap(), [RezzCall::Remove("tea".into())]);
+    }
+
+    #[tokio::test]
+    async fn remove_without_notification() {
+        let (client, _server, calls) = testing::mock_rezz(MockRezz::default()).await;
+        let alarms = AlarmClient::with_connection(client);
+
+        let session = Err(Error::DBus(zbus::Error::Unsupported));
+        let alarm = Alarm::new("tea", 1_900_000_000, 60);
+        let errors = remind_on(session, &alarms, &alarm).await;
+
+        assert!(errors.notification.is_some());
+        assert_eq!(*calls.lock().unwrap(), [RezzCall::Remove("tea".into())]);
+    }
+
+    #[tokio::test]
+    async fn claimed_reminder() {
+        let (session, _server, notifications) = testing::mock_notifications().await;
+        let (client, _server, calls) = testing::mock_rezz(MockRezz::default()).await;
+        let alarms = AlarmClient::with_connection(client);
+
+        // Concurrent clients handle the reminder only once.
+        let alarm = Alarm::new("tea", 1_900_000_000, 60);
+        tokio::join!(
+            remind_on(Ok(session.clone()), &alarms, &alarm),
+            remind_on(Ok(session.clone()), &alarms, &alarm),
+        );
+
+        assert_eq!(notifications.lock().unwrap().len(), 1);
+        assert_eq!(*calls.lock().unwrap(), [RezzCall::Remove("tea".into())]);
+
+        // The claim is released once the reminder was handled.
+        remind_on(Ok(session), &alarms, &alarm).await;
+        assert_eq!(notifications.lock().unwrap().len(), 2);
+    }
+
+    #[test]
+    fn valid_bus_name() {
+        let alarm = Alarm::new("tea, kettle: \"boiling\"", 1_900_000_000, 60);
+        assert!(zbus::names::WellKnownName::try_from(bus_name(&alarm)).is_ok());
+    }
+
+    #[tokio::test]
+    async fn keep_repeating_reminder() {
+        let (client, _server, calls) = testing::mock_rezz(MockRezz::default()).await;
+        let alarms = AlarmClient::with_connection(client);
+
+        let session = Err(Error::DBus(zbus::Error::Unsupported));
+        let mut alarm = Alarm::new("tea", 1_900_000_000, 60);
+        alarm.repeat_days = 0b111_1111;
+        let errors = remind_on(session, &alarms, &alarm).await;
+
+        assert!(errors.remove.is_none());
+        assert!(calls.lock().unwrap().is_empty());
+    }
+}
@@ -0,0 +1,209 @@
+//! Mock DBus services for testing clients.
+//!
+//! Every mock records the method calls it received, and is served using
+//! [`rezz::testing::mock_service`].
+
+use std::collections::HashMap;
+
+use rezz::testing::{self, MockCalls};
+use rezz::{Alarm, ServiceError};
+use zbus::object_server::SignalEmitter;
+use zbus::zvariant::OwnedValue;
+use zbus::{Connection, fdo, interface};
+
+/// Object path of the rezz daemon.
+pub const REZZ_PATH: &str = "/org/catacombing/rezz";
+
+/// Object path of the notification server.
+pub const NOTIFICATIONS_PATH: &str = "/org/freedesktop/Notifications";
+
+/// Object path of the feedback server.
+pub const FEEDBACK_PATH: &str = "/org/sigxcpu/Feedback";
+
+/// ID assigned to new notifications by [`MockNotifications`].
+pub const NOTIFICATION_ID: u32 = 7;
+
+/// ID assigned to new feedback events by [`MockFeedback`].
+pub const FEEDBACK_ID: u32 = 3;
+
+/// Method calls received by [`MockRezz`].
+#[derive(PartialEq, Eq, Debug)]
+pub enum RezzCall {
+    Add(String),
+    Replace(Vec<String>),
+    Remove(String),
+}
+
+/// Mock `org.catacombing.rezz` service.
+#[derive(Default)]
+pub struct MockRezz {
+    /// Alarms of the `Alarms` property.
+    pub alarms: Vec<Alarm>,
+    /// Error returned by all methods, after recording the call.
+    pub error: Option<fn(&str) -> ServiceError>,
+    pub calls: MockCalls<RezzCall>,
+}
+
+impl MockRezz {
+    /// Record a method call and reply with the configured error.
+    fn reply(&self, id: &str, call: RezzCall) -> Result<(), ServiceError> {
+        self.calls.lock().unwrap().push(call);
+
+        match self.error {
+            Some(error) => Err(error(id)),
+            None => Ok(()),
+        }
+    }
+}
+
+#[interface(name = "org.catacombing.rezz")]
+impl MockRezz {
+    fn add_alarm(&self, alarm: Alarm) -> Result<(), ServiceError> {
+        self.reply(&alarm.id, RezzCall::Add(alarm.id.clone()))
+    }
+
+    fn replace_alarms(&self, alarms: Vec<Alarm>) -> Result<(), ServiceError> {
+        let ids: Vec<_> = alarms.into_iter().map(|alarm| alarm.id).collect();
+        let id = ids.first().cloned().unwrap_or_default();
+        self.reply(&id, RezzCall::Replace(ids))
+    }
+
+    fn remove_alarm(&self, id: String) -> Result<(), ServiceError> {
+        self.reply(&id, RezzCall::Remove(id.clone()))
+    }
+
+    #[zbus(property)]
+    fn alarms(&self) -> Vec<Alarm> {
+        self.alarms.clone()
+    }
+}
+
+/// Method calls received by [`MockNotifications`].
+#[derive(PartialEq, Eq, Debug)]
+pub enum NotifyCall {
+    Notify {
+        app_name: String,
+        summary: String,
+        body: String,
+        actions: Vec<String>,
+        urgency: u8,
+        timeout: i32,
+    },
+    Close(u32),
+}
+
+/// Mock `org.freedesktop.Notifications` server.
+#[derive(Default)]
+pub struct MockNotifications {
+    pub calls: MockCalls<NotifyCall>,
+}
+
+#[interface(name = "org.freedesktop.Notifications")]
+impl MockNotifications {
+    #[allow(clippy::too_many_arguments)]
+    fn notify(
+        &self,
+        app_name: String,
+        _replaces_id: u32,
+        _app_icon: String,
+        summary: String,
+        body: String,
+        actions: Vec<String>,
+        hints: HashMap<String, OwnedValue>,
+        timeout: i32,
+    ) -> u32 {
+        let urgency = u8::try_from(&hints["urgency"]).unwrap();
+        let call = NotifyCall::Notify { app_name, summary, body, actions, urgency, timeout };
+        self.calls.lock().unwrap().push(call);
+
+        NOTIFICATION_ID
+    }
+
+    fn close_notification(&self, id: u32) {
+        self.calls.lock().unwrap().push(NotifyCall::Close(id));
+    }
+
+    #[zbus(signal)]
+    pub async fn action_invoked(
+        emitter: &SignalEmitter<'_>,
+        id: u32,
+        action_key: &str,
+    ) -> zbus::Result<()>;
+
+    #[zbus(signal)]
+    pub async fn notification_closed(
+        emitter: &SignalEmitter<'_>,
+        id: u32,
+        reason: u32,
+    ) -> zbus::Result<()>;
+}
+
+/// Method calls received by [`MockFeedback`].
+#[derive(PartialEq, Eq, Debug)]
+pub enum FeedbackCall {
+    Trigger { app_id: String, event: String, timeout: i32 },
+    End(u32),
+}
+
+/// Mock `org.sigxcpu.Feedback` server.
+#[derive(Default)]
+pub struct MockFeedback {
+    /// Reply like the bus does when feedbackd is not running.
+    pub missing: bool,
+    pub calls: MockCalls<FeedbackCall>,
+}
+
+#[interface(name = "org.sigxcpu.Feedback")]
+impl MockFeedback {
+    fn trigger_feedback(
+        &self,
+        app_id: String,
+        event: String,
+        _hints: HashMap<String, OwnedValue>,
+        timeout: i32,
+    ) -> fdo::Result<u32> {
+        if self.missing {
+            return Err(fdo::Error::ServiceUnknown("org.sigxcpu.Feedback".into()));
+        }
+
+        self.calls.lock().unwrap().push(FeedbackCall::Trigger { app_id, event, timeout });
+
+        Ok(FEEDBACK_ID)
+    }
+
+    fn end_feedback(&self, id: u32) {
+        self.calls.lock().unwrap().push(FeedbackCall::End(id));
+    }
+}
+
+/// Serve a mock rezz service.
+///
+/// Returns the client and server connections, and the calls received by the
+/// mock.
+pub async fn mock_rezz(rezz: MockRezz) -> (Connection, Connection, MockCalls<RezzCall>) {
+    let calls = rezz.calls.clone();
+    let (client, server) = testing::mock_service(REZZ_PATH, rezz).await;
+    (client, server, calls)
+}
+
+/// Serve a mock notification server.
+///
+/// Returns the client and server connections, and the calls received by the
+/// mock.
+pub async fn mock_notifications() -> (Connection, Connection, MockCalls<NotifyCall>) {
+    let notifications = MockNotifications::default();
+    let calls = notifications.calls.clone();
+    let (client, server) = testing::mock_service(NOTIFICATIONS_PATH, notifications).await;
+    (client, server, calls)
+}
+
+/// Serve a mock feedback server.
+///
+/// Returns the client and server connections, and the calls received by the
+/// mock.
+pub async fn mock_feedback(missing: bool) -> (Connection, Connection, MockCalls<FeedbackCall>) {
+    let feedback = MockFeedback { missing, ..Default::default() };
+    let calls = feedback.calls.clone();
+    let (client, server) = testing::mock_service(FEEDBACK_PATH, feedback).await;
+    (client, server, calls)
+}
@@ -6,57 +6,114 @@
 //! [tokio_walltime]: https://crates.io/crates/tokio-walltime
 //! [MIT]: https://git.sr.ht/~pounce/tokio-walltime/tree/main/item/LICENSE
 
-use std::io::Error as IoError;
-use std::mem::MaybeUninit;
+use std::io::{Error as IoError, ErrorKind as IoErrorKind};
+use std::os::fd::{AsRawFd, FromRawFd, OwnedFd};
 use std::ptr;
-use std::time::SystemTime;
+use std::time::{Duration, SystemTime};
 
-use tokio::signal::unix::{SignalKind, signal};
+use tokio::io::Interest;
+use tokio::io::unix::AsyncFd;
 
-/// Create a new timer.
-unsafe fn add_timer(seconds: i64) -> Result<libc::timer_t, IoError> {
-    unsafe {
-        // Get current time.
-        let mut now = MaybeUninit::<libc::timespec>::uninit();
-        if libc::clock_gettime(libc::CLOCK_REALTIME, now.as_mut_ptr()) != 0 {
-            return Err(IoError::last_os_error());
-        }
+/// Realtime clock with expiration events.
+trait Clock {
+    /// Get the current realtime clock time.
+    fn now(&self) -> SystemTime;
 
-        // Calculate target wakeup time.
-        let mut time = now.assume_init();
-        time.tv_sec += seconds as libc::time_t;
-
-        // Create the timer.
-        let mut timer = MaybeUninit::<libc::timer_t>::uninit();
-        let mut event = MaybeUninit::<libc::sigevent>::zeroed().assume_init();
-        event.sigev_signo = SignalKind::alarm().as_raw_value();
-        event.sigev_notify = libc::SIGEV_SIGNAL;
-        if libc::timer_create(libc::CLOCK_REALTIME, &mut event, timer.as_mut_ptr()) != 0 {
-            return Err(IoError::last_os_error());
-        }
-        let timer = timer.assume_init();
+    /// Wait until the target time or the next discontinuous clock change.
+    async fn wait_until(&mut self, target: SystemTime) -> Result<(), IoError>;
+}
 
-        // Activate the timer.
-        let timerspec = libc::itimerspec {
-            it_interval: libc::timespec { tv_sec: 0, tv_nsec: 0 },
-            it_value: time,
-        };
-        let result = libc::timer_settime(timer, libc::TIMER_ABSTIME, &timerspec, ptr::null_mut());
-        match result {
-            0 => Ok(timer),
-            _ => {
-                remove_timer(timer)?;
-                Err(IoError::last_os_error())
+/// Realtime clock timer.
+struct Timer {
+    fd: AsyncFd<OwnedFd>,
+}
+
+impl Timer {
+    /// Create a new disarmed timer.
+    ///
+    /// This will use `CLOCK_REALTIME_ALARM` to wake up the system from suspend
+    /// if the process has the `CAP_WAKE_ALARM` capability, falling back to
+    /// `CLOCK_REALTIME` otherwise.
+    fn new() -> Result<Self, IoError> {
+        let fd = match create_timerfd(libc::CLOCK_REALTIME_ALARM) {
+            Ok(fd) => fd,
+            // Fall back to a regular timer without capability or kernel support.
+            Err(err) if matches!(err.raw_os_error(), Some(libc::EPERM | libc::EINVAL)) => {
+                create_timerfd(libc::CLOCK_REALTIME)?
             },
+            Err(err) => return Err(err),
+        };
+
+        Ok(Self { fd: AsyncFd::with_interest(fd, Interest::READABLE)? })
+    }
+
+    /// Arm the timer to expire at the specified time.
+    ///
+    /// The timer will also be cancelled if the realtime clock is changed
+    /// discontinuously.
+    fn set(&self, target: SystemTime) -> Result<(), IoError> {
+        // A zero expiration disarms the timer, so clamp it to expire immediately.
+        let since_epoch = target.duration_since(SystemTime::UNIX_EPOCH).unwrap_or_default();
+        let since_epoch = since_epoch.max(Duration::from_nanos(1));
+        let it_value = libc::timespec {
+            tv_sec: since_epoch.as_secs() as libc::time_t,
+            tv_nsec: since_epoch.subsec_nanos() as libc::c_long,
+        };
+        let timerspec =
+            libc::itimerspec { it_interval: libc::timespec { tv_sec: 0, tv_nsec: 0 }, it_value };
+
+        let flags = libc::TFD_TIMER_ABSTIME | libc::TFD_TIMER_CANCEL_ON_SET;
+        let fd = self.fd.as_raw_fd();
+        match unsafe { libc::timerfd_settime(fd, flags, &timerspec, ptr::null_mut()) } {
+            0 => Ok(()),
+            _ => Err(IoError::last_os_error()),
+        }
+    }
+
+    /// Wait for the timer to expire or the clock to change.
+    async fn wait(&self) -> Result<(), IoError> {
+        loop {
+            let mut guard = self.fd.readable().await?;
+
+            let result = guard.try_io(|fd| {
+                let mut expirations = 0u64;
+                let buf = &mut expirations as *mut u64 as *mut libc::c_void;
+                match unsafe { libc::read(fd.as_raw_fd(), buf, size_of::<u64>()) } {
+                    -1 => Err(IoError::last_os_error()),
+                    _ => Ok(()),
+                }
+            });
+
+            match result {
+                Ok(Ok(())) => return Ok(()),
+                // Clock was changed discontinuously.
+                Ok(Err(err)) if err.raw_os_error() == Some(libc::ECANCELED) => return Ok(()),
+                Ok(Err(err)) if err.kind() == IoErrorKind::Interrupted => continue,
+                Ok(Err(err)) => return Err(err),
+                // Spurious wakeup, readiness was cleared by `try_io`.
+                Err(_would_block) => continue,
+            }
         }
     }
 }
 
-/// Delete an existing timer.
-unsafe fn remove_timer(timer: libc::timer_t) -> Result<(), IoError> {
-    match unsafe { libc::timer_delete(timer) } {
-        0 => Ok(()),
-        _ => Err(IoError::last_os_error()),
+impl Clock for Timer {
+    fn now(&self) -> SystemTime {
+        SystemTime::now()
+    }
+
+    async fn wait_until(&mut self, target: SystemTime) -> Result<(), IoError> {
+        self.set(target)?;
+        self.wait().await
+    }
+}
+
+/// Create a non-blocking timer file descriptor.
+fn create_timerfd(clock_id: libc::clockid_t) -> Result<OwnedFd, IoError> {
+    let flags = libc::TFD_NONBLOCK | libc::TFD_CLOEXEC;
+    match unsafe { libc::timerfd_create(clock_id, flags) } {
+        -1 => Err(IoError::last_os_error()),
+        fd => Ok(unsafe { OwnedFd::from_raw_fd(fd) }),
     }
 }
 
@@ -68,35 +125,128 @@ unsafe fn remove_timer(timer: libc::timer_t) -> Result<(), IoError> {
 ///
 /// This timer operates using the realtime clock as a reference instead. If the
 /// system is suspended at the time that the timer would expire, the timer
-/// expires immediately after the system resumes from sleep.
+/// expires immediately after the system resumes from sleep. If the process has
+/// the `CAP_WAKE_ALARM` capability, the timer will also wake the system from
+/// suspend, independent of the RTC alarm set by rezz.
+///
+/// Since the timer is backed by a file descriptor, no signal handlers are
+/// installed, leaving `SIGALRM` to the embedding application.
+///
+/// Discontinuous changes of the realtime clock, like manual adjustments or NTP
+/// steps, cause the remaining time to be re-evaluated. Since the target is an
+/// absolute UTC timestamp, timezone changes do not affect it.
 ///
 /// # Errors
 ///
 /// Returns an error if:
-///  - Setting a underlying signal handler fails for any reason (see
-///    [`signal#errors`]).
-///  - Getting the current time (via `clock_gettime(2)`) fails.
-///  - Creating the timer (via `timer_create(2)`) fails.
-///  - Setting the timer (via `timer_settime(2)`) fails.
-///  - Cleaning up the timer after it has triggered (via `timer_delete(2)`)
-///    fails.
+///  - Creating the timer (via `timerfd_create(2)`) fails.
+///  - Registering the timer with the tokio reactor fails.
+///  - Setting the timer (via `timerfd_settime(2)`) fails.
+///  - Reading the timer expiration (via `read(2)`) fails.
 pub async fn sleep_until(target: SystemTime) -> Result<(), IoError> {
-    // We must schedule our signal handler before the first signal appears.
-    let mut alarm = signal(SignalKind::alarm())?;
-
-    loop {
-        let now = SystemTime::now();
-        let remaining = match target.duration_since(now) {
-            Ok(remaining) => remaining,
-            Err(_) => return Ok(()),
-        };
+    sleep_until_with(&mut Timer::new()?, target).await
+}
 
-        // Set a timer for the specified time.
-        let timer = unsafe { add_timer(remaining.as_secs() as i64)? };
+/// Wait until a clock reaches the specified instant.
+async fn sleep_until_with(clock: &mut impl Clock, target: SystemTime) -> Result<(), IoError> {
+    // Re-arm the timer until the target is reached, since clock changes
+    // cancel the timer early.
+    while clock.now() < target {
+        clock.wait_until(target).await?;
+    }
 
-        // Wait for the signal.
-        alarm.recv().await;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::VecDeque;
+
+    use super::*;
+
+    /// Clock event ending a wait.
+    enum Event {
+        /// Timer expired at its target.
+        Expire,
+        /// Clock was set to a new time, cancelling the timer.
+        Jump(SystemTime),
+    }
+
+    /// Clock with scripted expirations and jumps.
+    struct FakeClock {
+        now: SystemTime,
+        events: VecDeque<Event>,
+        waits: Vec<SystemTime>,
+    }
+
+    impl FakeClock {
+        fn new(now: SystemTime, events: impl Into<VecDeque<Event>>) -> Self {
+            Self { now, events: events.into(), waits: Vec::new() }
+        }
+    }
+
+    impl Clock for FakeClock {
+        fn now(&self) -> SystemTime {
+            self.now
+        }
+
+        async fn wait_until(&mut self, target: SystemTime) -> Result<(), IoError> {
+            self.waits.push(target);
+            match self.events.pop_front().expect("unexpected wait") {
+                Event::Expire => self.now = self.now.max(target),
+                Event::Jump(time) => self.now = time,
+            }
+            Ok(())
+        }
+    }
+
+    fn at(seconds: u64) -> SystemTime {
+        SystemTime::UNIX_EPOCH + Duration::from_secs(seconds)
+    }
+
+    #[tokio::test]
+    async fn expired_target() {
+        let mut clock = FakeClock::new(at(100), []);
+        sleep_until_with(&mut clock, at(50)).await.unwrap();
+        assert!(clock.waits.is_empty());
+    }
+
+    #[tokio::test]
+    async fn expire_without_jump() {
+        let mut clock = FakeClock::new(at(0), [Event::Expire]);
+        sleep_until_with(&mut clock, at(60)).await.unwrap();
+        assert_eq!(clock.waits, [at(60)]);
+    }
+
+    #[tokio::test]
+    async fn forward_jump_past_target() {
+        let mut clock = FakeClock::new(at(0), [Event::Jump(at(3600))]);
+        sleep_until_with(&mut clock, at(60)).await.unwrap();
+        assert_eq!(clock.waits, [at(60)]);
+    }
+
+    #[tokio::test]
+    async fn forward_jump_before_target() {
+        let mut clock = FakeClock::new(at(0), [Event::Jump(at(30)), Event::Expire]);
+        sleep_until_with(&mut clock, at(60)).await.unwrap();
+        assert_eq!(clock.waits, [at(60), at(60)]);
+        assert_eq!(clock.now, at(60));
+    }
+
+    #[tokio::test]
+    async fn backward_jump() {
+        let mut clock = FakeClock::new(at(3600), [Event::Jump(at(0)), Event::Expire]);
+
+        // Backward jumps re-arm the same absolute target, instead of waking up early.
+        sleep_until_with(&mut clock, at(3660)).await.unwrap();
+        assert_eq!(clock.waits, [at(3660), at(3660)]);
+        assert_eq!(clock.now, at(3660));
+    }
 
-        unsafe { remove_timer(timer)? }
+    #[tokio::test]
+    async fn realtime_timer() {
+        let target = SystemTime::now() + Duration::from_millis(50);
+        sleep_until(target).await.unwrap();
+        assert!(SystemTime::now() >= target);
     }
 }
@@ -0,0 +1,414 @@
+//! Vibration motor control.
+
+use std::fs::{self, File, OpenOptions};
+use std::io::{Error as IoError, Write};
+use std::os::fd::AsRawFd;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::sync::mpsc::{self, RecvTimeoutError, Sender};
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+use std::{mem, slice};
+
+use crate::error::Error;
+
+/// Default LED-class vibrator location.
+const LED_VIBRATOR_PATH: &str = "/sys/class/leds/vibrator";
+
+/// Directory with the sysfs entries of all input devices.
+const INPUT_CLASS_PATH: &str = "/sys/class/input";
+
+/// Force-feedback event type.
+const EV_FF: u16 = 0x15;
+
+/// Rumble force-feedback effect.
+const FF_RUMBLE: u16 = 0x50;
+
+/// Upload a force-feedback effect, `_IOW('E', 0x80, struct ff_effect)`.
+const EVIOCSFF: libc::Ioctl =
+    nix::request_code_write!(b'E', 0x80, size_of::<libc::ff_effect>()) as libc::Ioctl;
+
+/// Remove a force-feedback effect, `_IOW('E', 0x81, int)`.
+const EVIOCRMFF: libc::Ioctl =
+    nix::request_code_write!(b'E', 0x81, size_of::<libc::c_int>()) as libc::Ioctl;
+
+/// Vibration pattern, alternating between vibrating and pausing.
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub struct VibrationPattern {
+    /// Duration of each vibration.
+    pub on: Duration,
+    /// Pause between vibrations.
+    pub off: Duration,
+}
+
+impl Default for VibrationPattern {
+    fn default() -> Self {
+        Self { on: Duration::from_millis(1000), off: Duration::from_millis(1000) }
+    }
+}
+
+impl FromStr for VibrationPattern {
+    type Err = Error;
+
+    /// Parse a pattern in the `ON_MS:OFF_MS` format, like `1000:500`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || Error::InvalidVibrationPattern(s.into());
+
+        let (on, off) = s.split_once(':').ok_or_else(invalid)?;
+        let on = Duration::from_millis(on.trim().parse().map_err(|_| invalid())?);
+        let off = Duration::from_millis(off.trim().parse().map_err(|_| invalid())?);
+
+        // Reject patterns which would never vibrate.
+        if on.is_zero() {
+            return Err(invalid());
+        }
+
+        Ok(Self { on, off })
+    }
+}
+
+/// Vibration motor device.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub enum VibratorDevice {
+    /// Force-feedback input device with `FF_RUMBLE` support, like
+    /// `/dev/input/event1`.
+    ForceFeedback(PathBuf),
+    /// LED-class vibrator directory, like `/sys/class/leds/vibrator`.
+    Led(PathBuf),
+}
+
+impl VibratorDevice {
+    /// Get the vibrator at the specified path.
+    ///
+    /// Directories with a `brightness` file are treated as LED-class devices,
+    /// all other paths as force-feedback input devices.
+    pub fn from_path(path: impl Into<PathBuf>) -> Self {
+        let path = path.into();
+        if path.join("brightness").is_file() { Self::Led(path) } else { Self::ForceFeedback(path) }
+    }
+
+    /// Find the system's vibration motor.
+    ///
+    /// This prefers the LED-class vibrator, falling back to the first input
+    /// device with rumble support.
+    pub fn detect() -> Option<Self> {
+        let led_path = Path::new(LED_VIBRATOR_PATH);
+        if led_path.join("brightness").is_file() {
+            return Some(Self::Led(led_path.into()));
+        }
+
+        let mut devices: Vec<_> = fs::read_dir(INPUT_CLASS_PATH)
+            .ok()?
+            .flatten()
+            .map(|entry| entry.file_name().to_string_lossy().into_owned())
+            .filter(|name| name.starts_with("event"))
+            .collect();
+        devices.sort_unstable();
+
+        devices.into_iter().find(|name| supports_rumble(name)).map(|name| {
+            let path = Path::new("/dev/input").join(name);
+            Self::ForceFeedback(path)
+        })
+    }
+}
+
+/// Running vibration pattern.
+///
+/// The vibration is stopped once this is dropped or [`Vibration::stop`] is
+/// called on it.
+pub struct Vibration {
+    stop_tx: Option<Sender<()>>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl Vibration {
+    /// Start repeating a vibration pattern.
+    pub fn start(device: &VibratorDevice, pattern: VibrationPattern) -> Result<Self, Error> {
+        let motor = Motor::open(device)?;
+        Ok(Self::spawn(motor, pattern))
+    }
+
+    /// Repeat a vibration pattern on a motor in a background thread.
+    fn spawn(mut motor: impl Switch, pattern: VibrationPattern) -> Self {
+        let (stop_tx, stop_rx) = mpsc::channel();
+        let thread = thread::spawn(move || {
+            loop {
+                // Ignore motor errors, to keep retrying until stopped.
+                let _ = motor.set(true);
+                if stop_rx.recv_timeout(pattern.on) != Err(RecvTimeoutError::Timeout) {
+                    break;
+                }
+
+                let _ = motor.set(false);
+                if stop_rx.recv_timeout(pattern.off) != Err(RecvTimeoutError::Timeout) {
+                    break;
+                }
+            }
+        });
+
+        Self { stop_tx: Some(stop_tx), thread: Some(thread) }
+    }
+
+    /// Stop the vibration.
+    pub fn stop(self) {}
+}
+
+impl Drop for Vibration {
+    fn drop(&mut self) {
+        // Disconnect the channel, to wake up the pattern thread.
+        self.stop_tx.take();
+
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+/// Motor which can be turned on and off.
+trait Switch: Send + 'static {
+    /// Turn the motor on or off.
+    fn set(&mut self, on: bool) -> Result<(), IoError>;
+}
+
+/// Handle for switching a vibration motor.
+///
+/// The motor is turned off once this is dropped.
+enum Motor {
+    ForceFeedback { file: File, effect_id: i16 },
+    Led { brightness: PathBuf, max_brightness: String },
+}
+
+impl Motor {
+    /// Open a vibration motor device.
+    fn open(device: &VibratorDevice) -> Result<Self, IoError> {
+        match device {
+            VibratorDevice::ForceFeedback(path) => {
+                let file = OpenOptions::new().read(true).write(true).open(path)?;
+
+                // Upload an infinite rumble effect at full strength.
+                let rumble =
+                    libc::ff_rumble_effect { strong_magnitude: u16::MAX, weak_magnitude: u16::MAX };
+                let mut effect: libc::ff_effect = unsafe { mem::zeroed() };
+                effect.type_ = FF_RUMBLE;
+                effect.id = -1;
+                unsafe {
+                    let u = effect.u.as_mut_ptr() as *mut libc::ff_rumble_effect;
+                    u.write(rumble);
+                }
+
+                if unsafe { libc::ioctl(file.as_raw_fd(), EVIOCSFF, &mut effect) } == -1 {
+                    return Err(IoError::last_os_error());
+                }
+
+                Ok(Self::ForceFeedback { file, effect_id: effect.id })
+            },
+            VibratorDevice::Led(path) => {
+                let max_brightness = fs::read_to_string(path.join("max_brightness"))
+                    .map(|max| max.trim().to_owned())
+                    .unwrap_or_else(|_| String::from("1"));
+
+                // Ensure the brightness can be changed, to report errors early.
+                let brightness = path.join("brightness");
+                OpenOptions::new().write(true).open(&brightness)?;
+
+                Ok(Self::Led { brightness, max_brightness })
+            },
+        }
+    }
+}
+
+impl Switch for Motor {
+    fn set(&mut self, on: bool) -> Result<(), IoError> {
+        match self {
+            Self::ForceFeedback { file, effect_id } => {
+                let mut event: libc::input_event = unsafe { mem::zeroed() };
+                event.type_ = EV_FF;
+                event.code = *effect_id as u16;
+                event.value = on as i32;
+
+                let size = size_of::<libc::input_event>();
+                let data = &event as *const libc::input_event as *const u8;
+                file.write_all(unsafe { slice::from_raw_parts(data, size) })
+            },
+            Self::Led { brightness, max_brightness } => {
+                let value = if on { max_brightness.as_str() } else { "0" };
+                fs::write(brightness, value)
+            },
+        }
+    }
+}
+
+impl Drop for Motor {
+    fn drop(&mut self) {
+        let _ = self.set(false);
+
+        // Release the uploaded force-feedback effect.
+        if let Self::ForceFeedback { file, effect_id } = self {
+            let effect_id = *effect_id as libc::c_int;
+            unsafe { libc::ioctl(file.as_raw_fd(), EVIOCRMFF, effect_id) };
+        }
+    }
+}
+
+/// Check if an input device supports rumble effects.
+fn supports_rumble(device: &str) -> bool {
+    let path = Path::new(INPUT_CLASS_PATH).join(device).join("device/capabilities/ff");
+    fs::read_to_string(path).is_ok_and(|capabilities| has_rumble(&capabilities))
+}
+
+/// Check for rumble support in a sysfs force-feedback capability bitmap.
+///
+/// The bitmap is stored as space-separated hex words, starting with the most
+/// significant.
+fn has_rumble(capabilities: &str) -> bool {
+    let word_bits = usize::BITS as usize;
+    let word_index = FF_RUMBLE as usize / word_bits;
+    let word = capabilities.split_whitespace().rev().nth(word_index);
+    let word = word.and_then(|word| usize::from_str_radix(word, 16).ok()).unwrap_or(0);
+
+    word & (1 << (FF_RUMBLE as usize % word_bits)) != 0
+}
+
+#[cfg(test)]
+mod tests {
+    use std::process;
+    use std::sync::mpsc::Receiver;
+
+    use super::*;
+
+    /// Temporary fake LED-class vibrator.
+    struct FakeLed {
+        path: PathBuf,
+    }
+
+    impl FakeLed {
+        fn new(name: &str) -> Self {
+            let path =
+                std::env::temp_dir().join(format!("alarm-vibrator-{}-{name}", process::id()));
+            fs::create_dir_all(&path).unwrap();
+            fs::write(path.join("brightness"), "0").unwrap();
+            fs::write(path.join("max_brightness"), "255\n").unwrap();
+            Self { path }
+        }
+
+        fn brightness(&self) -> String {
+            fs::read_to_string(self.path.join("brightness")).unwrap()
+        }
+    }
+
+    impl Drop for FakeLed {
+        fn drop(&mut self) {
+            let _ = fs::remove_dir_all(&self.path);
+        }
+    }
+
+    #[test]
+    fn parse_pattern() {
+        let pattern = VibrationPattern::from_str("500:250").unwrap();
+        assert_eq!(pattern.on, Duration::from_millis(500));
+        assert_eq!(pattern.off, Duration::from_millis(250));
+
+        let pattern = VibrationPattern::from_str(" 100 : 0 ").unwrap();
+        assert_eq!(pattern.on, Duration::from_millis(100));
+        assert_eq!(pattern.off, Duration::ZERO);
+    }
+
+    #[test]
+    fn parse_invalid_pattern() {
+        for pattern in ["", "500", "500:", ":500", "0:500", "-1:500", "500:250:100", "a:b"] {
+            let result = VibrationPattern::from_str(pattern);
+            assert!(matches!(result, Err(Error::InvalidVibrationPattern(_))), "{pattern:?}");
+        }
+    }
+
+    #[test]
+    #[cfg(target_pointer_width = "64")]
+    fn rumble_capability() {
+        // FF_RUMBLE is bit 16 of the second 64-bit word.
+        assert!(has_rumble("10000 0"));
+        assert!(has_rumble("107030000 0\n"));
+        assert!(has_rumble("0 10000 0"));
+
+        assert!(!has_rumble(""));
+        assert!(!has_rumble("0"));
+        assert!(!has_rumble("10000"));
+        assert!(!has_rumble("0 ffffffffffffffff"));
+        assert!(!has_rumble("8000 0"));
+        assert!(!has_rumble("invalid 0"));
+    }
+
+    #[test]
+    fn led_device() {
+        let led = FakeLed::new("device");
+        assert_eq!(VibratorDevice::from_path(&led.path), VibratorDevice::Led(led.path.clone()));
+
+        let path = PathBuf::from("/dev/input/event0");
+        assert_eq!(VibratorDevice::from_path(&path), VibratorDevice::ForceFeedback(path));
+    }
+
+    /// Motor reporting every state change after it was applied.
+    struct ObservedMotor {
+        motor: Motor,
+        states: Sender<bool>,
+    }
+
+    impl ObservedMotor {
+        /// Open an LED vibrator, returning the receiver for its state changes.
+        fn open(led: &FakeLed) -> (Self, Receiver<bool>) {
+            let motor = Motor::open(&VibratorDevice::Led(led.path.clone())).unwrap();
+            let (states, rx) = mpsc::channel();
+            (Self { motor, states }, rx)
+        }
+    }
+
+    impl Switch for ObservedMotor {
+        fn set(&mut self, on: bool) -> Result<(), IoError> {
+            let result = self.motor.set(on);
+            let _ = self.states.send(on);
+            result
+        }
+    }
+
+    /// Wait for the next motor state change, failing after a timeout.
+    fn next_state(states: &Receiver<bool>) -> bool {
+        states.recv_timeout(Duration::from_secs(5)).expect("missing motor state change")
+    }
+
+    #[test]
+    fn led_vibration() {
+        let led = FakeLed::new("vibration");
+        let (motor, states) = ObservedMotor::open(&led);
+
+        let pattern = VibrationPattern { on: Duration::from_secs(60), off: Duration::ZERO };
+        let vibration = Vibration::spawn(motor, pattern);
+        assert!(next_state(&states));
+        assert_eq!(led.brightness(), "255");
+
+        vibration.stop();
+        assert_eq!(led.brightness(), "0");
+    }
+
+    #[test]
+    fn led_pattern() {
+        let led = FakeLed::new("pattern");
+        let (motor, states) = ObservedMotor::open(&led);
+
+        let pattern =
+            VibrationPattern { on: Duration::from_millis(10), off: Duration::from_secs(60) };
+        let _vibration = Vibration::spawn(motor, pattern);
+        assert!(next_state(&states));
+        assert!(!next_state(&states));
+        assert_eq!(led.brightness(), "0");
+    }
+
+    #[test]
+    fn missing_device() {
+        let led = FakeLed::new("missing");
+        let device = VibratorDevice::Led(led.path.join("missing"));
+        let pattern = VibrationPattern::default();
+        assert!(matches!(Vibration::start(&device, pattern), Err(Error::Io(_))));
+
+        let device = VibratorDevice::ForceFeedback(led.path.join("event0"));
+        assert!(matches!(Vibration::start(&device, pattern), Err(Error::Io(_))));
+    }
+}